@@ -0,0 +1,35 @@
+#![feature(test)]
+
+extern crate test;
+
+use std::sync::Barrier;
+use std::thread::scope;
+
+use cs431_homework::hello_server::Cache;
+use test::{black_box, Bencher};
+
+const THREADS: usize = 32;
+
+/// All the threads ask for the same missing key at once, so one of them computes the value and
+/// the others wait for it to be handed off.
+#[bench]
+fn waiters_on_one_key(b: &mut Bencher) {
+    b.iter(|| {
+        let cache = Cache::default();
+        let barrier = Barrier::new(THREADS);
+        scope(|s| {
+            for _ in 0..THREADS {
+                let _ = s.spawn(|| {
+                    let _ = barrier.wait();
+                    black_box(cache.get_or_insert_with(0usize, |k| {
+                        // Give the others time to start waiting.
+                        for _ in 0..1000 {
+                            std::thread::yield_now();
+                        }
+                        k
+                    }));
+                });
+            }
+        });
+    });
+}
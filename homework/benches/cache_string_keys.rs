@@ -0,0 +1,59 @@
+#![feature(test)]
+
+extern crate test;
+
+use std::collections::hash_map::{Entry, HashMap, RandomState};
+use std::hash::BuildHasher;
+use std::sync::{Arc, Mutex, RwLock};
+
+use cs431_homework::hello_server::Cache;
+use test::{black_box, Bencher};
+
+const KEYS: usize = 1024;
+
+/// Long enough that hashing a key costs more than probing the map.
+fn keys() -> Vec<String> {
+    (0..KEYS)
+        .map(|i| format!("{}/{i:08}", "/request/path".repeat(32)))
+        .collect()
+}
+
+/// Misses on every key of a fresh cache. The key is hashed once, and the hash is reused for the
+/// read lookup and the write `entry`.
+#[bench]
+fn miss_single_hash(b: &mut Bencher) {
+    let keys = keys();
+    b.iter(|| {
+        let cache = Cache::default();
+        for key in &keys {
+            black_box(cache.get_or_insert_with(key.clone(), |key| key.len()));
+        }
+    });
+}
+
+/// The same misses replayed on a plain map with the lookups of the cache before the hash was
+/// cached: the key is hashed for choosing the shard, then again by the map for the read lookup,
+/// the check for eviction and the write `entry`.
+#[bench]
+fn miss_double_hash(b: &mut Bencher) {
+    let keys = keys();
+    let hasher = RandomState::new();
+    b.iter(|| {
+        let map = RwLock::new(HashMap::<_, Arc<Mutex<usize>>>::new());
+        for key in &keys {
+            black_box(hasher.hash_one(key));
+            if let Some(slot) = map.read().unwrap().get(key).cloned() {
+                black_box(*slot.lock().unwrap());
+                continue;
+            }
+            let mut map = map.write().unwrap();
+            black_box(map.contains_key(key));
+            let slot = match map.entry(key.clone()) {
+                Entry::Occupied(entry) => Arc::clone(entry.get()),
+                Entry::Vacant(entry) => Arc::clone(entry.insert(Arc::new(Mutex::new(0)))),
+            };
+            drop(map);
+            *slot.lock().unwrap() = key.len();
+        }
+    });
+}
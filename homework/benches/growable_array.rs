@@ -0,0 +1,113 @@
+#![feature(test)]
+
+extern crate test;
+
+use core::sync::atomic::Ordering::*;
+
+use crossbeam_epoch::pin;
+use cs431_homework::{GrowableArray, GrowableArrayCursor};
+use test::{black_box, Bencher};
+
+const STEPS: usize = 10_000;
+
+/// Number of the slots in a segment.
+const SEGMENT_SIZE: usize = 1 << 10;
+
+/// Number of the segments allocated in an iteration of the allocation benchmarks.
+const SEGMENTS: usize = 256;
+
+/// Prepares an array whose first `STEPS` slots are allocated.
+fn array() -> GrowableArray<usize> {
+    let array = GrowableArray::new();
+    let _ = array.try_get(STEPS - 1, &pin()).unwrap();
+    array
+}
+
+#[bench]
+fn get_pin_per_op(b: &mut Bencher) {
+    let array = array();
+    b.iter(|| {
+        for i in 0..STEPS {
+            let guard = pin();
+            let _ = black_box(array.try_get(i, &guard).unwrap().load(Relaxed, &guard));
+        }
+    });
+}
+
+#[bench]
+fn get_batch(b: &mut Bencher) {
+    let array = array();
+    b.iter(|| {
+        let guard = pin();
+        let batch = array.batch(&guard);
+        for i in 0..STEPS {
+            let _ = black_box(batch.load(i, Relaxed));
+        }
+    });
+}
+
+#[bench]
+fn get_frozen(b: &mut Bencher) {
+    let frozen = array().freeze(&pin());
+    b.iter(|| {
+        for i in 0..STEPS {
+            let _ = black_box(frozen.get_frozen(i));
+        }
+    });
+}
+
+/// Iterating over a dense range with the traversal from the root for each index, versus skipping
+/// it with the cached segment.
+#[bench]
+fn get_dense(b: &mut Bencher) {
+    let array = array();
+    b.iter(|| {
+        let guard = pin();
+        for i in 0..STEPS {
+            let _ = black_box(array.try_get(i, &guard).unwrap().load(Relaxed, &guard));
+        }
+    });
+}
+
+#[bench]
+fn cached_get_dense(b: &mut Bencher) {
+    let array = array();
+    b.iter(|| {
+        let guard = pin();
+        let mut cursor = GrowableArrayCursor::new();
+        for i in 0..STEPS {
+            let _ = black_box(
+                array
+                    .cached_get(&mut cursor, i, &guard)
+                    .load(Relaxed, &guard),
+            );
+        }
+    });
+}
+
+/// Counting the segments with the counter maintained on growth, versus traversing the tree.
+#[bench]
+fn segment_count(b: &mut Bencher) {
+    let array = array();
+    b.iter(|| black_box(array.segment_count()));
+}
+
+#[bench]
+fn segment_count_traversal(b: &mut Bencher) {
+    let array = array();
+    let guard = pin();
+    b.iter(|| black_box(array.path_stats(&guard).segments.iter().sum::<usize>()));
+}
+
+/// Throughput of `Segment::new`, which allocates the segments already zeroed.
+#[bench]
+fn alloc_segments(b: &mut Bencher) {
+    b.iter(|| {
+        // The children segments are pre-allocated, so each `get` allocates one element segment.
+        let array = GrowableArray::<usize>::builder().initial_height(2).build();
+        let guard = pin();
+        for i in 0..SEGMENTS {
+            let _ = black_box(array.try_get(i * SEGMENT_SIZE, &guard).unwrap());
+        }
+    });
+}
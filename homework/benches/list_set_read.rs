@@ -0,0 +1,62 @@
+//! Read throughput of `FineGrainedListSet` versus a coarse-grained `Mutex<BTreeSet>`.
+//!
+//! Run with `cargo bench --bench list_set_read`.
+
+use std::collections::BTreeSet;
+use std::hint::black_box;
+use std::sync::Mutex;
+use std::thread::scope;
+use std::time::{Duration, Instant};
+
+use cs431_homework::{ConcurrentSet, FineGrainedListSet};
+use rand::prelude::*;
+
+const SIZES: [usize; 3] = [16, 256, 4096];
+const THREADS: [usize; 5] = [1, 2, 4, 8, 16];
+/// Total number of list nodes visited by each thread, to keep the runtime independent of the size.
+const WORK_PER_THREAD: usize = 1 << 22;
+
+/// Runs `contains` on random keys in `0..2 * size` from `threads` threads and returns the elapsed
+/// time. Half of the keys are present.
+fn run<F: Fn(&usize) -> bool + Sync>(contains: F, size: usize, threads: usize) -> Duration {
+    let ops = WORK_PER_THREAD / size;
+    let start = Instant::now();
+    scope(|s| {
+        for _ in 0..threads {
+            let _ = s.spawn(|| {
+                let mut rng = thread_rng();
+                for _ in 0..ops {
+                    let _ = black_box(contains(&rng.gen_range(0..2 * size)));
+                }
+            });
+        }
+    });
+    start.elapsed()
+}
+
+fn main() {
+    println!(
+        "{:>6} {:>8} {:>16} {:>16} {:>8}",
+        "size", "threads", "fine (Mops/s)", "coarse (Mops/s)", "ratio"
+    );
+    for size in SIZES {
+        let fine = FineGrainedListSet::new();
+        let coarse = Mutex::new(BTreeSet::new());
+        for key in (0..2 * size).step_by(2) {
+            assert!(fine.insert(key));
+            assert!(coarse.lock().unwrap().insert(key));
+        }
+
+        for threads in THREADS {
+            let ops = (WORK_PER_THREAD / size * threads) as f64;
+            let fine = ops / run(|key| fine.contains(key), size, threads).as_secs_f64() / 1e6;
+            let coarse = ops
+                / run(|key| coarse.lock().unwrap().contains(key), size, threads).as_secs_f64()
+                / 1e6;
+            println!(
+                "{size:>6} {threads:>8} {fine:>16.3} {coarse:>16.3} {:>8.3}",
+                fine / coarse
+            );
+        }
+    }
+}
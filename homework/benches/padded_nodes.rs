@@ -0,0 +1,49 @@
+//! Throughput of `FineGrainedListSet` operations on adjacent nodes, to measure the false sharing
+//! between the nodes.
+//!
+//! Each thread repeatedly locks the only node of its own set, and the nodes of the sets are
+//! allocated one after another, so the unpadded nodes of different threads share cache lines.
+//! Compare `cargo bench --bench padded_nodes` with
+//! `cargo bench --bench padded_nodes --features padded-nodes`.
+
+use std::hint::black_box;
+use std::thread::scope;
+use std::time::{Duration, Instant};
+
+use cs431_homework::{ConcurrentSet, FineGrainedListSet};
+
+const THREADS: [usize; 4] = [1, 2, 4, 8];
+const OPS_PER_THREAD: usize = 1 << 22;
+
+/// Runs `contains` on the node of each set from its own thread and returns the elapsed time.
+fn run(sets: &[Box<FineGrainedListSet<usize>>]) -> Duration {
+    let start = Instant::now();
+    scope(|s| {
+        for (key, set) in sets.iter().enumerate() {
+            let _ = s.spawn(move || {
+                for _ in 0..OPS_PER_THREAD {
+                    let _ = black_box(set.contains(&key));
+                }
+            });
+        }
+    });
+    start.elapsed()
+}
+
+fn main() {
+    let padded = cfg!(feature = "padded-nodes");
+    println!("padded nodes: {padded}");
+    println!("{:>8} {:>12}", "threads", "Mops/s");
+    for threads in THREADS {
+        let sets = (0..threads)
+            .map(|_| Box::new(FineGrainedListSet::new()))
+            .collect::<Vec<_>>();
+        // 连续分配，未对齐时相邻线程的节点落在同一个cache line
+        for (key, set) in sets.iter().enumerate() {
+            assert!(set.insert(key));
+        }
+        let ops = (OPS_PER_THREAD * threads) as f64;
+        let throughput = ops / run(&sets).as_secs_f64() / 1e6;
+        println!("{threads:>8} {throughput:>12.3}");
+    }
+}
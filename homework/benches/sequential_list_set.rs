@@ -0,0 +1,41 @@
+//! Single-threaded throughput of `SequentialListSet` versus `FineGrainedListSet` on a mixed
+//! workload of 100k operations.
+//!
+//! Run with `cargo bench --bench sequential_list_set`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use cs431_homework::{ConcurrentSet, FineGrainedListSet};
+use rand::prelude::*;
+
+const OPS: usize = 100_000;
+const KEYS: usize = 4096;
+
+/// Runs `OPS` random operations on `set` prepared with half of the keys: 50% `contains`, 25%
+/// `insert`, and 25% `remove`. Returns the elapsed time.
+fn run<S: ConcurrentSet<usize>>(set: S) -> Duration {
+    let mut rng = StdRng::seed_from_u64(0);
+    let start = Instant::now();
+    for _ in 0..OPS {
+        let key = rng.gen_range(0..KEYS);
+        let _ = black_box(match rng.gen_range(0..4) {
+            0 => set.insert(key),
+            1 => set.remove(&key),
+            _ => set.contains(&key),
+        });
+    }
+    start.elapsed()
+}
+
+fn main() {
+    let prepare = || FineGrainedListSet::from_sorted_iter((0..KEYS).step_by(2));
+    let mops = |elapsed: Duration| OPS as f64 / elapsed.as_secs_f64() / 1e6;
+    println!("{:>12} {:>12}", "set", "Mops/s");
+    println!("{:>12} {:>12.3}", "fine", mops(run(prepare())));
+    println!(
+        "{:>12} {:>12.3}",
+        "sequential",
+        mops(run(prepare().into_sequential()))
+    );
+}
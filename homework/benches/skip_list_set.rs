@@ -0,0 +1,61 @@
+//! Throughput of `SkipListSet` versus `FineGrainedListSet` by the size of the set, to find where
+//! the skip list starts to win.
+//!
+//! Run with `cargo bench --bench skip_list_set`.
+
+use std::hint::black_box;
+use std::thread::scope;
+use std::time::{Duration, Instant};
+
+use cs431_homework::{ConcurrentSet, FineGrainedListSet, SkipListSet};
+use rand::prelude::*;
+
+const SIZES: [usize; 7] = [4, 16, 64, 256, 1024, 4096, 16384];
+const THREADS: [usize; 2] = [1, 4];
+/// Total number of list nodes visited by each thread on the list, to keep the runtime of the list
+/// independent of the size.
+const WORK_PER_THREAD: usize = 1 << 22;
+
+/// Prepares a set with the even keys in `0..2 * size`, and runs random operations on the keys
+/// split across `threads` threads: 80% `contains`, 10% `insert`, and 10% `remove`. Returns the
+/// throughput in Mops/s.
+fn run<S: ConcurrentSet<usize> + Default + Sync>(size: usize, threads: usize) -> f64 {
+    let ops = (WORK_PER_THREAD / size).max(1 << 10);
+    let set = S::default();
+    let _ = set.insert_many((0..2 * size).step_by(2));
+    let start = Instant::now();
+    scope(|s| {
+        for _ in 0..threads {
+            let _ = s.spawn(|| {
+                let mut rng = thread_rng();
+                for _ in 0..ops {
+                    let key = rng.gen_range(0..2 * size);
+                    let _ = black_box(match rng.gen_range(0..10) {
+                        0 => set.insert(key),
+                        1 => set.remove(&key),
+                        _ => set.contains(&key),
+                    });
+                }
+            });
+        }
+    });
+    let elapsed: Duration = start.elapsed();
+    (ops * threads) as f64 / elapsed.as_secs_f64() / 1e6
+}
+
+fn main() {
+    println!(
+        "{:>6} {:>8} {:>16} {:>16} {:>8}",
+        "size", "threads", "list (Mops/s)", "skip (Mops/s)", "ratio"
+    );
+    for size in SIZES {
+        for threads in THREADS {
+            let list = run::<FineGrainedListSet<usize>>(size, threads);
+            let skip = run::<SkipListSet<usize>>(size, threads);
+            println!(
+                "{size:>6} {threads:>8} {list:>16.3} {skip:>16.3} {:>8.3}",
+                skip / list
+            );
+        }
+    }
+}
@@ -0,0 +1,56 @@
+//! Throughput of `UnrolledListSet` versus `FineGrainedListSet` on a mixed workload of 100k
+//! operations.
+//!
+//! Run with `cargo bench --bench unrolled_list_set`.
+
+use std::hint::black_box;
+use std::thread::scope;
+use std::time::{Duration, Instant};
+
+use cs431_homework::{ConcurrentSet, FineGrainedListSet, UnrolledListSet};
+use rand::prelude::*;
+
+const OPS: usize = 100_000;
+const KEYS: usize = 4096;
+const THREADS: [usize; 4] = [1, 2, 4, 8];
+
+/// Prepares a set with half of the keys, and runs `OPS` random operations split across `threads`
+/// threads: 50% `contains`, 25% `insert`, and 25% `remove`. Returns the elapsed time.
+fn run<S: ConcurrentSet<usize> + Default + Sync>(threads: usize) -> Duration {
+    let set = S::default();
+    let _ = set.insert_many((0..KEYS).step_by(2));
+    let start = Instant::now();
+    scope(|s| {
+        for _ in 0..threads {
+            let _ = s.spawn(|| {
+                let mut rng = thread_rng();
+                for _ in 0..OPS / threads {
+                    let key = rng.gen_range(0..KEYS);
+                    let _ = black_box(match rng.gen_range(0..4) {
+                        0 => set.insert(key),
+                        1 => set.remove(&key),
+                        _ => set.contains(&key),
+                    });
+                }
+            });
+        }
+    });
+    start.elapsed()
+}
+
+fn main() {
+    println!(
+        "{:>8} {:>16} {:>16} {:>16} {:>16}",
+        "threads", "fine (Mops/s)", "B=8 (Mops/s)", "B=32 (Mops/s)", "B=128 (Mops/s)"
+    );
+    for threads in THREADS {
+        let mops = |elapsed: Duration| OPS as f64 / elapsed.as_secs_f64() / 1e6;
+        println!(
+            "{threads:>8} {:>16.3} {:>16.3} {:>16.3} {:>16.3}",
+            mops(run::<FineGrainedListSet<usize>>(threads)),
+            mops(run::<UnrolledListSet<usize, 8>>(threads)),
+            mops(run::<UnrolledListSet<usize, 32>>(threads)),
+            mops(run::<UnrolledListSet<usize, 128>>(threads)),
+        );
+    }
+}
@@ -25,4 +25,13 @@ pub trait ConcurrentSet<T> {
 
     /// Removes the value from the set. Returns whether the value was present in the set.
     fn remove(&self, value: &T) -> bool;
+
+    /// Adds all the values to the set. Returns whether each value was newly inserted, in the order
+    /// of the input. If a value appears more than once, only the first one can be newly inserted.
+    fn insert_many<I: IntoIterator<Item = T>>(&self, values: I) -> Vec<bool>
+    where
+        Self: Sized,
+    {
+        values.into_iter().map(|value| self.insert(value)).collect()
+    }
 }
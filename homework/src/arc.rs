@@ -9,11 +9,25 @@ use std::ops::Deref;
 use std::ptr::NonNull;
 #[cfg(not(feature = "check-loom"))]
 use std::sync::atomic::{fence, AtomicUsize, Ordering};
-use std::{fmt, mem};
+use std::{fmt, mem, ptr};
 
 #[cfg(feature = "check-loom")]
 use loom::sync::atomic::{fence, AtomicUsize, Ordering};
 
+mod atomic;
+mod cancel;
+mod latest;
+mod list;
+mod observer;
+mod stack;
+
+pub use atomic::AtomicArc;
+pub use cancel::CancellationToken;
+pub use latest::SharedLatest;
+pub use list::{ArcLinkedList, ArcListNode};
+pub use observer::{Observer, ObserverRegistry};
+pub use stack::ArcStack;
+
 const MAX_REFCOUNT: usize = (isize::MAX) as usize;
 
 /// A thread-safe reference-counting pointer. 'Arc' stands for 'Atomically
@@ -154,40 +168,127 @@ const MAX_REFCOUNT: usize = (isize::MAX) as usize;
 /// counting in general.
 ///
 /// [rc_examples]: std::rc#examples
-pub struct Arc<T> {
+pub struct Arc<T: ?Sized> {
     ptr: NonNull<ArcInner<T>>,
     phantom: PhantomData<ArcInner<T>>,
 }
 
-unsafe impl<T: Sync + Send> Send for Arc<T> {}
-unsafe impl<T: Sync + Send> Sync for Arc<T> {}
+unsafe impl<T: ?Sized + Sync + Send> Send for Arc<T> {}
+unsafe impl<T: ?Sized + Sync + Send> Sync for Arc<T> {}
 
-impl<T> Arc<T> {
+impl<T: ?Sized> Arc<T> {
     fn from_inner(ptr: NonNull<ArcInner<T>>) -> Self {
         Self {
             ptr,
             phantom: PhantomData,
         }
     }
+
+    /// Moves the boxed `inner` into an `Arc`. The box may be coerced to an unsized `T`, e.g. a
+    /// trait object, which `Arc::new` can't create.
+    fn from_box(inner: Box<ArcInner<T>>) -> Self {
+        Self::from_inner(Box::leak(inner).into())
+    }
 }
 
-struct ArcInner<T> {
+// `repr(C)` so that the layout of `ArcInner<[T]>` can be computed to allocate it.
+#[repr(C)]
+struct ArcInner<T: ?Sized> {
     count: AtomicUsize,
-    data: T,
+    /// Number of `Weak`s, plus one if there are any `Arc`s. `usize::MAX` while `is_unique` has
+    /// locked out `downgrade`.
+    weak: AtomicUsize,
+    /// Dropped in place when `count` reaches zero, while the allocation itself lives on until
+    /// `weak` reaches zero.
+    data: ManuallyDrop<T>,
 }
 
-unsafe impl<T: Sync + Send> Send for ArcInner<T> {}
-unsafe impl<T: Sync + Send> Sync for ArcInner<T> {}
+unsafe impl<T: ?Sized + Sync + Send> Send for ArcInner<T> {}
+unsafe impl<T: ?Sized + Sync + Send> Sync for ArcInner<T> {}
+
+impl<T> ArcInner<T> {
+    /// Creates the `ArcInner` of a new `Arc`, with a strong count of one.
+    fn new(data: T) -> Self {
+        Self {
+            count: AtomicUsize::new(1),
+            weak: AtomicUsize::new(1),
+            data: ManuallyDrop::new(data),
+        }
+    }
+}
 
 impl<T> Arc<T> {
     /// Constructs a new `Arc<T>`.
     #[inline]
     pub fn new(data: T) -> Arc<T> {
-        let x = Box::new(ArcInner {
-            count: AtomicUsize::new(1),
-            data,
-        });
-        Self::from_inner(Box::leak(x).into())
+        // Even if `T` is zero-sized, `ArcInner<T>` is not because of the counts. So this always
+        // allocates, and `data` is a properly aligned field of the allocation.
+        Self::from_box(Box::new(ArcInner::new(data)))
+    }
+}
+
+impl<T: ?Sized> Arc<T> {
+    /// Creates a new [`Weak`] pointer to this allocation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cs431_homework::Arc;
+    ///
+    /// let five = Arc::new(5);
+    /// let weak_five = Arc::downgrade(&five);
+    ///
+    /// assert_eq!(*weak_five.upgrade().unwrap(), 5);
+    /// ```
+    pub fn downgrade(this: &Self) -> Weak<T> {
+        let inner = this.inner();
+        let mut cur = inner.weak.load(Ordering::Relaxed);
+        loop {
+            // The weak count is locked by `is_unique`. Wait until it is released.
+            if cur == usize::MAX {
+                spin_loop();
+                cur = inner.weak.load(Ordering::Relaxed);
+                continue;
+            }
+            if cur >= MAX_REFCOUNT {
+                panic!("Arc::downgrade() would overflow the weak count");
+            }
+            // Acquire: synchronizes with the `Release` store that unlocks the weak count in
+            // `is_unique`.
+            match inner.weak.compare_exchange_weak(
+                cur,
+                cur + 1,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return Weak { ptr: this.ptr },
+                Err(old) => cur = old,
+            }
+        }
+    }
+
+    /// Gets the number of [`Weak`] pointers to this allocation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cs431_homework::Arc;
+    ///
+    /// let five = Arc::new(5);
+    /// let _weak_five = Arc::downgrade(&five);
+    ///
+    /// assert_eq!(1, Arc::weak_count(&five));
+    /// ```
+    #[inline]
+    pub fn weak_count(this: &Self) -> usize {
+        let cnt = this.inner().weak.load(Ordering::Acquire);
+        // If the weak count is currently locked, there were no `Weak`s when it was locked.
+        if cnt == usize::MAX {
+            0
+        } else {
+            // Exclude the implicit weak reference held by all the `Arc`s.
+            cnt - 1
+        }
     }
 
     /// Returns a mutable reference into the given `Arc` if there are
@@ -217,11 +318,45 @@ impl<T> Arc<T> {
         }
     }
 
+    /// Like `get_mut`, but calls `observer` right after the uniqueness check succeeds and before
+    /// the reference is returned.
+    ///
+    /// Instrumentation for the tests, e.g. to inject a scheduling point of loom at the
+    /// linearization point of `get_mut`. Only available to the tests and with `check-loom`.
+    #[cfg(any(test, feature = "check-loom"))]
+    #[doc(hidden)]
+    pub fn get_mut_observed(this: &mut Self, observer: impl FnOnce()) -> Option<&mut T> {
+        if Self::is_unique(this) {
+            observer();
+            // SAFETY: `is_unique` saw no other `Arc` and no `Weak`, so another `Arc` or `Weak` can
+            // only be created from `this`. `observer` cannot reach `this` while it is mutably
+            // borrowed here, so `this` is still the unique reference after `observer` returns.
+            unsafe { Some(Self::get_mut_unchecked(this)) }
+        } else {
+            None
+        }
+    }
+
     // Used in `get_mut` and `make_mut` to check if the given `Arc` is the unique reference to the
     // underlying data.
     #[inline]
     fn is_unique(&mut self) -> bool {
-        return self.inner().count.load(Ordering::SeqCst) == 1;
+        // Lock the weak count so that no `Weak` can be created while checking the strong count.
+        // If there is no `Weak` now, then no other `Arc` can be created by `upgrade` either.
+        if self
+            .inner()
+            .weak
+            .compare_exchange(1, usize::MAX, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+        {
+            // Acquire: synchronizes with the `Release` decrements in `drop`, so that the accesses
+            // through the other `Arc`s happen before our mutable access.
+            let unique = self.inner().count.load(Ordering::Acquire) == 1;
+            self.inner().weak.store(1, Ordering::Release);
+            unique
+        } else {
+            false
+        }
     }
 
     /// Returns a mutable reference into the given `Arc` without any check.
@@ -273,7 +408,25 @@ impl<T> Arc<T> {
     /// ```
     #[inline]
     pub fn count(this: &Self) -> usize {
-        return this.inner().count.load(Ordering::SeqCst);
+        return this.inner().count.load(Ordering::Acquire);
+    }
+
+    /// Asserts that `this` is the only reference to the allocation, i.e. there is no other `Arc`
+    /// or `Weak`, e.g. as a postcondition of a test whose other clones must all have been dropped.
+    #[cfg(test)]
+    #[track_caller]
+    pub(crate) fn assert_unique(this: &Self) {
+        let (count, weak) = (Self::count(this), Self::weak_count(this));
+        assert!(
+            count == 1 && weak == 0,
+            "Arc is not unique: {count} Arcs and {weak} Weaks"
+        );
+    }
+
+    /// Overwrites the reference count, for testing the sanity checks on corrupted counts.
+    #[cfg(test)]
+    fn set_count(this: &Self, count: usize) {
+        this.inner().count.store(count, Ordering::Relaxed);
     }
 
     #[inline]
@@ -303,7 +456,118 @@ impl<T> Arc<T> {
     /// ```
     #[inline]
     pub fn ptr_eq(this: &Self, other: &Self) -> bool {
-        this.ptr.as_ptr() == other.ptr.as_ptr()
+        ptr::addr_eq(this.ptr.as_ptr(), other.ptr.as_ptr())
+    }
+
+    /// Returns `true` if the contents of the two `Arc`s are equal. Returns `true` without comparing
+    /// the contents if they point to the same allocation, as `std::sync::Arc`'s `PartialEq` does
+    /// for `T: Eq`.
+    ///
+    /// Unlike the `std` optimization, the identity check is applied for any `T: PartialEq`, so it
+    /// is `true` for a clone even if the contents are not equal to themselves (e.g. `f64::NAN`).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cs431_homework::Arc;
+    ///
+    /// let five = Arc::new(5);
+    /// assert!(Arc::content_eq(&five, &Arc::clone(&five)));
+    /// assert!(Arc::content_eq(&five, &Arc::new(5)));
+    /// assert!(!Arc::content_eq(&five, &Arc::new(6)));
+    /// ```
+    #[inline]
+    pub fn content_eq(this: &Self, other: &Self) -> bool
+    where
+        T: PartialEq,
+    {
+        Self::ptr_eq(this, other) || **this == **other
+    }
+}
+
+impl<T> Arc<T> {
+    /// Consumes the `Arc`, returning the wrapped pointer. The reference count is not decremented,
+    /// so the pointer must be converted back with [`Arc::from_raw`] to avoid a leak.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cs431_homework::Arc;
+    ///
+    /// let x = Arc::new("hello".to_owned());
+    /// let x_ptr = Arc::into_raw(x);
+    /// assert_eq!(unsafe { &*x_ptr }, "hello");
+    /// # drop(unsafe { Arc::from_raw(x_ptr) });
+    /// ```
+    pub fn into_raw(this: Self) -> *const T {
+        let this = ManuallyDrop::new(this);
+        // `addr_of!` doesn't create a reference, so the pointer keeps the provenance of the whole
+        // allocation, which `from_raw` needs to recover the counts.
+        let data = unsafe { ptr::addr_of!((*this.ptr.as_ptr()).data) };
+        data.cast()
+    }
+
+    /// Constructs an `Arc<T>` from a pointer returned by [`Arc::into_raw`], taking over the
+    /// reference that was leaked by it.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been returned by `Arc::<T>::into_raw`, and each call to `into_raw` must be
+    /// matched by at most one call to `from_raw`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cs431_homework::Arc;
+    ///
+    /// let x = Arc::new("hello".to_owned());
+    /// let x_ptr = Arc::into_raw(x);
+    /// let x = unsafe { Arc::from_raw(x_ptr) };
+    /// assert_eq!(&*x, "hello");
+    /// ```
+    pub unsafe fn from_raw(ptr: *const T) -> Self {
+        let offset = mem::offset_of!(ArcInner<T>, data);
+        // SAFETY: `ptr` points to the `data` field of an `ArcInner<T>`, with the provenance of the
+        // whole allocation.
+        let inner = unsafe { ptr.cast::<u8>().sub(offset) }.cast::<ArcInner<T>>();
+        Self::from_inner(unsafe { NonNull::new_unchecked(inner.cast_mut()) })
+    }
+
+    /// Creates a new [`Weak`] pointer to this allocation and returns it as a type-erased raw
+    /// pointer, e.g. to keep weak handles to values of different types in a single registry. The
+    /// weak count is incremented, so the pointer must be converted back with
+    /// [`Arc::from_raw_weak`] to avoid a leak.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cs431_homework::Arc;
+    ///
+    /// let x = Arc::new("hello".to_owned());
+    /// let handle = Arc::clone_to_raw_weak(&x);
+    /// assert_eq!(Arc::weak_count(&x), 1);
+    /// let weak = unsafe { Arc::<String>::from_raw_weak(handle) };
+    /// assert_eq!(*weak.upgrade().unwrap(), "hello");
+    /// drop(x);
+    /// assert!(weak.upgrade().is_none());
+    /// ```
+    pub fn clone_to_raw_weak(this: &Self) -> *const () {
+        let weak = ManuallyDrop::new(Arc::downgrade(this));
+        // 指向`ArcInner`而不是`data`，保留整个分配的provenance，`data`被drop后也能访问计数
+        weak.ptr.as_ptr().cast_const().cast()
+    }
+
+    /// Constructs a [`Weak`] from a pointer returned by [`Arc::clone_to_raw_weak`], taking over
+    /// the weak reference that was leaked by it.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been returned by `Arc::<T>::clone_to_raw_weak` for the same `T`, and each
+    /// call to `clone_to_raw_weak` must be matched by at most one call to `from_raw_weak`.
+    pub unsafe fn from_raw_weak(ptr: *const ()) -> Weak<T> {
+        // SAFETY: `ptr` points to an `ArcInner<T>` kept alive by the leaked weak reference.
+        let ptr = unsafe { NonNull::new_unchecked(ptr.cast::<ArcInner<T>>().cast_mut()) };
+        Weak { ptr }
     }
 
     /// Returns the inner value, if the given `Arc` is unique.
@@ -324,12 +588,64 @@ impl<T> Arc<T> {
     /// ```
     #[inline]
     pub fn try_unwrap(this: Self) -> Result<T, Self> {
-        if this.inner().count.load(Ordering::SeqCst) == 1 {
-            let this = ManuallyDrop::new(this);
-            let data = unsafe { std::ptr::read(&this.inner().data as *const _ as *mut T) };
-            Ok(data)
-        } else {
-            Err(this)
+        // Use CAS rather than a plain load so that a concurrent `Weak::upgrade` can't revive it.
+        if this
+            .inner()
+            .count
+            .compare_exchange(1, 0, Ordering::Relaxed, Ordering::Relaxed)
+            .is_err()
+        {
+            return Err(this);
+        }
+        // Synchronizes with the `Release` decrements in `drop`, as in `drop` itself.
+        fence(Ordering::Acquire);
+        let this = ManuallyDrop::new(this);
+        let data = unsafe { ManuallyDrop::into_inner(ptr::read(&this.inner().data)) };
+        // Release the implicit weak reference held by the `Arc`s.
+        drop(Weak { ptr: this.ptr });
+        Ok(data)
+    }
+}
+
+impl<T> From<Vec<T>> for Arc<[T]> {
+    /// Moves the elements of the `Vec` into a newly allocated `Arc<[T]>`.
+    ///
+    /// When the last `Arc` is dropped, the elements are dropped in order from the first one to the
+    /// last one, as in a slice.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cs431_homework::Arc;
+    ///
+    /// let slice: Arc<[i32]> = Arc::from(vec![1, 2, 3]);
+    /// assert_eq!(&*slice, [1, 2, 3]);
+    /// ```
+    fn from(mut vec: Vec<T>) -> Self {
+        let len = vec.len();
+        // The same layout as `ArcInner<[T]>` thanks to `repr(C)`.
+        let (layout, data_offset) = Layout::new::<ArcInner<()>>()
+            .extend(Layout::array::<T>(len).unwrap())
+            .unwrap();
+        let layout = layout.pad_to_align();
+        // SAFETY: `layout` is not zero-sized because of the counts.
+        let mem = unsafe { std::alloc::alloc(layout) };
+        if mem.is_null() {
+            std::alloc::handle_alloc_error(layout);
+        }
+        // 胖指针的元数据是切片长度
+        let inner = ptr::slice_from_raw_parts_mut(mem.cast::<T>(), len) as *mut ArcInner<[T]>;
+        unsafe {
+            ptr::addr_of_mut!((*inner).count).write(AtomicUsize::new(1));
+            ptr::addr_of_mut!((*inner).weak).write(AtomicUsize::new(1));
+            debug_assert_eq!(
+                ptr::addr_of_mut!((*inner).data).cast::<u8>(),
+                mem.add(data_offset)
+            );
+            // The elements are moved, so the `Vec` must not drop them.
+            ptr::copy_nonoverlapping(vec.as_ptr(), mem.add(data_offset).cast::<T>(), len);
+            vec.set_len(0);
+            Self::from_inner(NonNull::new_unchecked(inner))
         }
     }
 }
@@ -367,21 +683,40 @@ impl<T: Clone> Arc<T> {
         } else {
             // 当前需要克隆一份inner，开辟空间后要使得this指向它
             // 从而达到make_mtu的效果
-            let inner = this.inner();
-            inner.count.fetch_sub(1, Ordering::SeqCst);
-            let mut new_data = inner.data.clone();
-            let new_inner = Box::new(ArcInner {
-                count: AtomicUsize::new(1),
-                data: new_data,
-            });
-            let new_ptr = NonNull::new(Box::leak(new_inner)).unwrap();
-            let old_ptr = mem::replace(&mut this.ptr, new_ptr);
+            // 必须先克隆再释放旧的引用，否则其他线程可能在克隆前释放数据
+            *this = Arc::new((**this).clone());
             unsafe { Self::get_mut_unchecked(this) }
         }
     }
+
+    /// Converts the `Arc` into a [`std::sync::Arc`] with the same value.
+    ///
+    /// The two types have different memory layouts, so the value is always moved into a new
+    /// allocation. If `this` is the only `Arc` to the value, the value is moved out without
+    /// cloning. Otherwise, the value is cloned and `this` is dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cs431_homework::Arc;
+    ///
+    /// let std = Arc::into_std(Arc::new(vec![1, 2, 3]));
+    /// assert_eq!(*Arc::from_std(std), [1, 2, 3]);
+    /// ```
+    pub fn into_std(this: Self) -> std::sync::Arc<T> {
+        let value = Self::try_unwrap(this).unwrap_or_else(|this| (*this).clone());
+        std::sync::Arc::new(value)
+    }
+
+    /// Converts a [`std::sync::Arc`] into an `Arc` with the same value, moving the value out
+    /// without cloning if `std` is the only reference to it, like `into_std`.
+    pub fn from_std(std: std::sync::Arc<T>) -> Self {
+        let value = std::sync::Arc::try_unwrap(std).unwrap_or_else(|std| (*std).clone());
+        Arc::new(value)
+    }
 }
 
-impl<T> Clone for Arc<T> {
+impl<T: ?Sized> Clone for Arc<T> {
     /// Makes a clone of the `Arc` pointer.
     ///
     /// This creates another pointer to the same allocation, increasing the
@@ -403,7 +738,9 @@ impl<T> Clone for Arc<T> {
     #[inline]
     fn clone(&self) -> Arc<T> {
         let inner = self.inner();
-        let old_count = inner.count.fetch_add(1, Ordering::SeqCst);
+        // Relaxed: a new reference can only be made from an existing one, which already keeps the
+        // value alive, and passing it to another thread synchronizes by itself.
+        let old_count = inner.count.fetch_add(1, Ordering::Relaxed);
         if old_count >= MAX_REFCOUNT {
             panic!("Arc::clone() would overflow the reference count");
         }
@@ -414,7 +751,7 @@ impl<T> Clone for Arc<T> {
     }
 }
 
-impl<T> Deref for Arc<T> {
+impl<T: ?Sized> Deref for Arc<T> {
     type Target = T;
 
     #[inline]
@@ -423,7 +760,7 @@ impl<T> Deref for Arc<T> {
     }
 }
 
-impl<T> Drop for Arc<T> {
+impl<T: ?Sized> Drop for Arc<T> {
     /// Drops the `Arc`.
     ///
     /// This will decrement the reference count. If the reference
@@ -450,30 +787,213 @@ impl<T> Drop for Arc<T> {
     /// ```
     fn drop(&mut self) {
         let inner = self.inner();
-        if inner.count.fetch_sub(1, Ordering::SeqCst) == 1 {
+        // Release: the accesses to the value through this `Arc` must happen before the value is
+        // dropped by whichever `Arc` is the last one.
+        let old_count = inner.count.fetch_sub(1, Ordering::Release);
+        // The count can't be zero while this `Arc` is alive, unless it was dropped twice.
+        debug_assert!(
+            old_count >= 1,
+            "Arc::drop() underflowed the reference count (double drop?)"
+        );
+        if old_count == 1 {
+            // Acquire: synchronizes with the `Release` decrements of all the other `Arc`s. A fence
+            // instead of an `AcqRel` decrement, so that only the last `Arc` pays for it.
+            fence(Ordering::Acquire);
             unsafe {
                 // Safety: We're dropping the last reference to the inner value,
                 // so there can be no other references to it.
-                let _ = Box::from_raw(self.ptr.as_ptr());
+                //
+                // For `Arc<[T]>`, this drops the elements front-to-back like a slice.
+                ManuallyDrop::drop(&mut (*self.ptr.as_ptr()).data);
             }
+            // Release the implicit weak reference held by the `Arc`s, which deallocates the
+            // `ArcInner` if there is no `Weak` left.
+            drop(Weak { ptr: self.ptr });
         }
     }
 }
 
-impl<T: fmt::Display> fmt::Display for Arc<T> {
+impl<T: ?Sized + fmt::Display> fmt::Display for Arc<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         fmt::Display::fmt(&**self, f)
     }
 }
 
-impl<T: fmt::Debug> fmt::Debug for Arc<T> {
+impl<T: ?Sized + fmt::Debug> fmt::Debug for Arc<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         fmt::Debug::fmt(&**self, f)
     }
 }
 
-impl<T> fmt::Pointer for Arc<T> {
+impl<T: ?Sized> fmt::Pointer for Arc<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         fmt::Pointer::fmt(&(&**self), f)
     }
 }
+
+/// `Weak` is a version of [`Arc`] that holds a non-owning reference to the managed allocation.
+///
+/// The allocation is accessed by calling [`upgrade`] on the `Weak` pointer, which returns an
+/// <code>[Option]<[Arc]\<T>></code>. Since a `Weak` reference does not count towards ownership, it
+/// will not prevent the value stored in the allocation from being dropped, and `Weak` itself makes
+/// no guarantees about the value still being present.
+///
+/// A `Weak` pointer is useful for keeping a temporary reference to the allocation managed by
+/// [`Arc`] without preventing its inner value from being dropped. It is also used to prevent
+/// circular references between [`Arc`] pointers.
+///
+/// The typical way to obtain a `Weak` pointer is to call [`Arc::downgrade`].
+///
+/// [`upgrade`]: Weak::upgrade
+pub struct Weak<T: ?Sized> {
+    ptr: NonNull<ArcInner<T>>,
+}
+
+unsafe impl<T: ?Sized + Sync + Send> Send for Weak<T> {}
+unsafe impl<T: ?Sized + Sync + Send> Sync for Weak<T> {}
+
+impl<T: ?Sized> Weak<T> {
+    /// Attempts to upgrade the `Weak` pointer to an [`Arc`], delaying dropping of the inner value
+    /// if successful.
+    ///
+    /// Returns [`None`] if the inner value has since been dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cs431_homework::Arc;
+    ///
+    /// let five = Arc::new(5);
+    ///
+    /// let weak_five = Arc::downgrade(&five);
+    ///
+    /// let strong_five: Option<Arc<_>> = weak_five.upgrade();
+    /// assert!(strong_five.is_some());
+    ///
+    /// // Destroy all strong pointers.
+    /// drop(strong_five);
+    /// drop(five);
+    ///
+    /// assert!(weak_five.upgrade().is_none());
+    /// ```
+    pub fn upgrade(&self) -> Option<Arc<T>> {
+        let count = self.count();
+        let mut cur = count.load(Ordering::Relaxed);
+        loop {
+            // Once the count reaches zero, the inner value is (being) dropped.
+            if cur == 0 {
+                return None;
+            }
+            if cur >= MAX_REFCOUNT {
+                panic!("Weak::upgrade() would overflow the reference count");
+            }
+            match count.compare_exchange_weak(cur, cur + 1, Ordering::Acquire, Ordering::Relaxed) {
+                Ok(_) => return Some(Arc::from_inner(self.ptr)),
+                Err(old) => cur = old,
+            }
+        }
+    }
+
+    /// Gets the number of [`Arc`]s pointing to this allocation.
+    pub fn strong_count(&self) -> usize {
+        self.count().load(Ordering::Acquire)
+    }
+
+    /// Returns the strong count of the allocation.
+    #[inline]
+    fn count(&self) -> &AtomicUsize {
+        // `data`可能已经被drop，不能创建指向整个`ArcInner`的引用，只通过字段指针访问计数
+        // SAFETY: The `ArcInner` is not deallocated while this `Weak` is alive.
+        unsafe { &*ptr::addr_of!((*self.ptr.as_ptr()).count) }
+    }
+
+    /// Returns the weak count of the allocation.
+    #[inline]
+    fn weak(&self) -> &AtomicUsize {
+        // SAFETY: The `ArcInner` is not deallocated while this `Weak` is alive.
+        unsafe { &*ptr::addr_of!((*self.ptr.as_ptr()).weak) }
+    }
+}
+
+impl<T: ?Sized> Clone for Weak<T> {
+    /// Makes a clone of the `Weak` pointer that points to the same allocation.
+    fn clone(&self) -> Weak<T> {
+        let old_count = self.weak().fetch_add(1, Ordering::Relaxed);
+        if old_count >= MAX_REFCOUNT {
+            panic!("Weak::clone() would overflow the weak count");
+        }
+        Weak { ptr: self.ptr }
+    }
+}
+
+impl<T: ?Sized> Drop for Weak<T> {
+    /// Drops the `Weak` pointer.
+    ///
+    /// The allocation is deallocated once neither `Arc` nor `Weak` points to it.
+    fn drop(&mut self) {
+        if self.weak().fetch_sub(1, Ordering::Release) == 1 {
+            fence(Ordering::Acquire);
+            unsafe {
+                // Safety: There is no `Arc` or `Weak` left, and the inner value was already
+                // dropped by the last `Arc`.
+                let _ = Box::from_raw(self.ptr.as_ptr());
+            }
+        }
+    }
+}
+
+impl<T: ?Sized> fmt::Debug for Weak<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "(Weak)")
+    }
+}
+
+/// Busy-waits for a moment, yielding to the model checker under loom.
+#[inline]
+fn spin_loop() {
+    #[cfg(not(feature = "check-loom"))]
+    std::hint::spin_loop();
+    #[cfg(feature = "check-loom")]
+    loom::thread::yield_now();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "underflowed the reference count")]
+    fn drop_underflow() {
+        let arc = Arc::new(0);
+        // Pretend that `arc` was already dropped.
+        Arc::set_count(&arc, 0);
+        drop(arc);
+    }
+
+    #[test]
+    fn assert_unique() {
+        let arc = Arc::new(0);
+        let clones = (0..4).map(|_| Arc::clone(&arc)).collect::<Vec<_>>();
+        let weak = Arc::downgrade(&arc);
+        drop(clones);
+        drop(weak);
+        Arc::assert_unique(&arc);
+    }
+
+    #[test]
+    #[should_panic(expected = "Arc is not unique: 2 Arcs and 0 Weaks")]
+    fn assert_unique_clone_survives() {
+        let arc = Arc::new(0);
+        let _clone = Arc::clone(&arc);
+        Arc::assert_unique(&arc);
+    }
+
+    #[test]
+    #[should_panic(expected = "Arc is not unique: 1 Arcs and 1 Weaks")]
+    fn assert_unique_weak_survives() {
+        let arc = Arc::new(0);
+        let _weak = Arc::downgrade(&arc);
+        Arc::assert_unique(&arc);
+    }
+}
@@ -0,0 +1,125 @@
+//! Atomic slot of an `Arc`.
+
+use std::marker::PhantomData;
+use std::mem::ManuallyDrop;
+#[cfg(not(feature = "check-loom"))]
+use std::sync::atomic::{AtomicPtr, Ordering::*};
+use std::{fmt, ptr};
+
+use crossbeam_epoch::Guard;
+#[cfg(feature = "check-loom")]
+use loom::sync::atomic::{AtomicPtr, Ordering::*};
+
+use super::Arc;
+
+/// Slot holding an [`Arc`] that is loaded and replaced atomically, e.g. for a field of a lock-free
+/// data structure.
+///
+/// The slot owns one reference to the value it holds. A load clones the `Arc` by incrementing the
+/// count of the value, and a store or a successful `compare_exchange` releases the reference to the
+/// replaced value. A concurrent load may have read the pointer to the replaced value but not yet
+/// incremented its count, so the reference is released only after the current epoch is over, by
+/// deferring it to `guard`. Hence all the guards passed to a slot must be pinned by the same
+/// collector, e.g. the global collector with [`crossbeam_epoch::pin`].
+///
+/// # Examples
+///
+/// ```
+/// use cs431_homework::{Arc, AtomicArc};
+///
+/// let guard = &crossbeam_epoch::pin();
+/// let slot = AtomicArc::new(Arc::new(1));
+/// let one = slot.load(guard);
+/// slot.store(Arc::new(2), guard);
+/// assert_eq!(*one, 1);
+/// assert!(slot.compare_exchange(&one, Arc::new(3), guard).is_err());
+/// assert!(slot.compare_exchange(&slot.load(guard), Arc::new(3), guard).is_ok());
+/// assert_eq!(*slot.load(guard), 3);
+/// ```
+pub struct AtomicArc<T> {
+    /// Pointer returned by `Arc::into_raw`, owning a reference to the value.
+    ptr: AtomicPtr<T>,
+    /// Sent and shared like the `Arc` it holds.
+    _marker: PhantomData<Arc<T>>,
+}
+
+impl<T> AtomicArc<T> {
+    /// Creates a slot holding `value`.
+    pub fn new(value: Arc<T>) -> Self {
+        Self {
+            ptr: AtomicPtr::new(Arc::into_raw(value).cast_mut()),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the value held by the slot.
+    pub fn load(&self, guard: &Guard) -> Arc<T> {
+        let ptr = self.ptr.load(Acquire);
+        // SAFETY: `ptr` is from `Arc::into_raw`, and the reference of the slot to it is not
+        // released until `guard` is unpinned even if it is replaced concurrently. The `Arc` is not
+        // dropped, so only the clone takes a reference.
+        let value = ManuallyDrop::new(unsafe { Arc::from_raw(ptr) });
+        Arc::clone(&value)
+    }
+
+    /// Consumes the slot, returning the value held by it.
+    pub fn into_inner(self) -> Arc<T> {
+        let this = ManuallyDrop::new(self);
+        // SAFETY: The slot is owned, so the pointer is not accessed concurrently, and the
+        // reference of the slot is moved to the returned `Arc`.
+        unsafe { Arc::from_raw(this.ptr.load(Relaxed)) }
+    }
+}
+
+impl<T: Send + Sync> AtomicArc<T> {
+    /// Replaces the value held by the slot with `value`.
+    pub fn store(&self, value: Arc<T>, guard: &Guard) {
+        let old = self.ptr.swap(Arc::into_raw(value).cast_mut(), AcqRel);
+        Self::release(old, guard);
+    }
+
+    /// Replaces the value held by the slot with `new` if the slot holds the same allocation as
+    /// `current`, i.e. `Arc::ptr_eq`. Otherwise, returns `new` back.
+    pub fn compare_exchange(
+        &self,
+        current: &Arc<T>,
+        new: Arc<T>,
+        guard: &Guard,
+    ) -> Result<(), Arc<T>> {
+        let current = ptr::from_ref::<T>(current).cast_mut();
+        let new = Arc::into_raw(new).cast_mut();
+        match self.ptr.compare_exchange(current, new, AcqRel, Acquire) {
+            Ok(old) => {
+                Self::release(old, guard);
+                Ok(())
+            }
+            // SAFETY: `new` is from `Arc::into_raw` and was not published.
+            Err(_) => Err(unsafe { Arc::from_raw(new) }),
+        }
+    }
+
+    /// Releases the reference of the slot to `old`, which is replaced, once the current epoch is
+    /// over.
+    fn release(old: *mut T, guard: &Guard) {
+        // 并发的load可能已经读到old但还没增加计数，等它们的guard都unpin后再释放
+        // SAFETY: `old` is from `Arc::into_raw` and unreachable from the slot, and the value can be
+        // dropped in any thread since `T: Send + Sync`.
+        unsafe { guard.defer_unchecked(move || drop(Arc::from_raw(old))) };
+    }
+}
+
+impl<T> Drop for AtomicArc<T> {
+    fn drop(&mut self) {
+        // SAFETY: The slot is owned, so no load is in progress.
+        drop(unsafe { Arc::from_raw(self.ptr.load(Relaxed)) });
+    }
+}
+
+impl<T> fmt::Debug for AtomicArc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // 不知道调用者用的是哪个collector，不能在这里load，只打印指针
+        f.debug_tuple("AtomicArc")
+            .field(&self.ptr.load(Relaxed))
+            .finish()
+    }
+}
@@ -0,0 +1,92 @@
+//! Cancellation token shared between tasks.
+
+#[cfg(not(feature = "check-loom"))]
+use std::sync::atomic::{AtomicBool, Ordering::*};
+
+#[cfg(feature = "check-loom")]
+use loom::sync::atomic::{AtomicBool, Ordering::*};
+
+use super::Arc;
+
+#[derive(Debug)]
+struct Inner {
+    cancelled: AtomicBool,
+    /// The token is also cancelled when its parent is.
+    parent: Option<Arc<Inner>>,
+}
+
+impl Inner {
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Acquire) || self.parent.as_ref().is_some_and(|p| p.is_cancelled())
+    }
+}
+
+/// Token to request cancellation of a task, e.g. from a thread that shuts down a server.
+///
+/// The clones of a token share the same state, so cancelling one of them cancels all of them. A
+/// [`child`](CancellationToken::child) token is cancelled when its parent is, but cancelling the
+/// child doesn't cancel the parent.
+///
+/// `cancel` is a `Release` store and `is_cancelled` is an `Acquire` load, so the writes before the
+/// cancellation are visible to a thread that observed it.
+///
+/// # Examples
+///
+/// ```
+/// use cs431_homework::CancellationToken;
+///
+/// let token = CancellationToken::new();
+/// let child = token.child();
+/// let clone = token.clone();
+///
+/// child.cancel();
+/// assert!(!token.is_cancelled());
+///
+/// clone.cancel();
+/// assert!(token.is_cancelled());
+/// assert!(token.child().is_cancelled());
+/// ```
+#[derive(Debug, Clone)]
+pub struct CancellationToken {
+    inner: Arc<Inner>,
+}
+
+impl CancellationToken {
+    /// Creates a token that is not cancelled.
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                cancelled: AtomicBool::new(false),
+                parent: None,
+            }),
+        }
+    }
+
+    /// Creates a child token, which is cancelled when `self` is cancelled.
+    ///
+    /// The child keeps its ancestors alive, and `is_cancelled` on it walks up the chain.
+    pub fn child(&self) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                cancelled: AtomicBool::new(false),
+                parent: Some(Arc::clone(&self.inner)),
+            }),
+        }
+    }
+
+    /// Cancels the token, its clones, and its descendants.
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Release);
+    }
+
+    /// Returns `true` if the token or one of its ancestors is cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.is_cancelled()
+    }
+}
+
+impl Default for CancellationToken {
+    fn default() -> Self {
+        Self::new()
+    }
+}
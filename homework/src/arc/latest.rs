@@ -0,0 +1,60 @@
+//! Single-slot holder of the latest value.
+
+use std::fmt;
+#[cfg(not(feature = "check-loom"))]
+use std::sync::Mutex;
+
+#[cfg(feature = "check-loom")]
+use loom::sync::Mutex;
+
+use super::Arc;
+
+/// Holder of the latest value published by the writers, which any number of readers can read.
+///
+/// A reader gets the value as an [`Arc`] clone, so it keeps reading the same value even if a
+/// writer publishes a new one meanwhile. The lock is held only to swap or clone the `Arc`, so the
+/// old value is dropped outside the lock when its last reader is done with it.
+///
+/// # Examples
+///
+/// ```
+/// use cs431_homework::{Arc, SharedLatest};
+///
+/// let config = SharedLatest::new(Arc::new("v1"));
+/// let old = config.load();
+/// config.store(Arc::new("v2"));
+/// assert_eq!(*old, "v1");
+/// assert_eq!(*config.load(), "v2");
+/// ```
+pub struct SharedLatest<T> {
+    latest: Mutex<Arc<T>>,
+}
+
+impl<T> SharedLatest<T> {
+    /// Creates a holder with the initial value.
+    pub fn new(value: Arc<T>) -> Self {
+        Self {
+            latest: Mutex::new(value),
+        }
+    }
+
+    /// Publishes `value`, replacing the previous value.
+    pub fn store(&self, value: Arc<T>) {
+        let old = std::mem::replace(&mut *self.latest.lock().unwrap(), value);
+        // 在锁外释放旧值，旧值的析构不会阻塞读者
+        drop(old);
+    }
+
+    /// Returns the latest value.
+    pub fn load(&self) -> Arc<T> {
+        Arc::clone(&self.latest.lock().unwrap())
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for SharedLatest<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedLatest")
+            .field("latest", &self.load())
+            .finish()
+    }
+}
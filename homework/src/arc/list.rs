@@ -0,0 +1,244 @@
+//! Doubly-linked list of `Arc` nodes with removal through a node handle.
+
+use std::fmt;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::*;
+use std::sync::{Mutex, MutexGuard};
+
+use super::{Arc, Weak};
+
+/// Source of the ids of the lists. 0 is reserved for the nodes that are not linked.
+static NEXT_LIST_ID: AtomicUsize = AtomicUsize::new(1);
+
+/// Node of [`ArcLinkedList`], returned as a handle by the pushes.
+///
+/// A node owns its successor with `next`, and refers to its predecessor with a [`Weak`] in `prev`,
+/// so the links never form a cycle of `Arc`s.
+pub struct ArcListNode<T> {
+    value: T,
+    /// Id of the list that the node is linked in, or 0 if it is not linked. Only written with the
+    /// lock of the list held.
+    owner: AtomicUsize,
+    /// `None` if the node is the head.
+    prev: Mutex<Option<Weak<ArcListNode<T>>>>,
+    /// `None` if the node is the tail.
+    next: Mutex<Option<Arc<ArcListNode<T>>>>,
+}
+
+impl<T> ArcListNode<T> {
+    /// Returns the value of the node.
+    pub fn value(&self) -> &T {
+        &self.value
+    }
+
+    /// Returns `true` if the node is linked in a list.
+    pub fn is_linked(&self) -> bool {
+        self.owner.load(Relaxed) != 0
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for ArcListNode<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ArcListNode")
+            .field("value", &self.value)
+            .field("linked", &self.is_linked())
+            .finish()
+    }
+}
+
+#[derive(Debug)]
+struct Ends<T> {
+    head: Option<Arc<ArcListNode<T>>>,
+    tail: Option<Arc<ArcListNode<T>>>,
+    len: usize,
+}
+
+/// Doubly-linked list whose nodes are [`Arc`]s, so that a node can be removed in O(1) time through
+/// the handle returned when it was pushed, e.g. to drop an entry of an LRU queue found by a lookup
+/// elsewhere.
+///
+/// The list owns the nodes through the head and the `next` pointers, while the `prev` pointers are
+/// [`Weak`]. Hence there is no cycle of `Arc`s, and the nodes are freed once they are neither in
+/// the list nor held by a handle. The structure of the list is guarded by a single lock, and the
+/// links of each node by their own locks, which are only taken with the list's lock held.
+///
+/// # Examples
+///
+/// ```
+/// use cs431_homework::ArcLinkedList;
+///
+/// let list = ArcLinkedList::new();
+/// let _ = list.push_back(1);
+/// let two = list.push_back(2);
+/// let _ = list.push_front(0);
+///
+/// assert!(list.remove(&two));
+/// assert!(!list.remove(&two));
+/// assert_eq!(*list.pop_back().unwrap().value(), 1);
+/// assert_eq!(*list.pop_front().unwrap().value(), 0);
+/// assert!(list.is_empty());
+/// ```
+pub struct ArcLinkedList<T> {
+    /// Unique among the lists, to tell whether a node is linked in this list.
+    id: usize,
+    ends: Mutex<Ends<T>>,
+}
+
+impl<T> ArcLinkedList<T> {
+    /// Creates an empty list.
+    pub fn new() -> Self {
+        Self {
+            id: NEXT_LIST_ID.fetch_add(1, Relaxed),
+            ends: Mutex::new(Ends {
+                head: None,
+                tail: None,
+                len: 0,
+            }),
+        }
+    }
+
+    /// Pushes `value` at the front of the list, returning the handle to its node.
+    pub fn push_front(&self, value: T) -> Arc<ArcListNode<T>> {
+        let node = self.new_node(value);
+        let mut ends = self.lock();
+        match ends.head.take() {
+            Some(head) => {
+                *head.prev.lock().unwrap() = Some(Arc::downgrade(&node));
+                *node.next.lock().unwrap() = Some(head);
+            }
+            None => ends.tail = Some(Arc::clone(&node)),
+        }
+        ends.head = Some(Arc::clone(&node));
+        ends.len += 1;
+        node
+    }
+
+    /// Pushes `value` at the back of the list, returning the handle to its node.
+    pub fn push_back(&self, value: T) -> Arc<ArcListNode<T>> {
+        let node = self.new_node(value);
+        let mut ends = self.lock();
+        match ends.tail.take() {
+            Some(tail) => {
+                *node.prev.lock().unwrap() = Some(Arc::downgrade(&tail));
+                *tail.next.lock().unwrap() = Some(Arc::clone(&node));
+            }
+            None => ends.head = Some(Arc::clone(&node)),
+        }
+        ends.tail = Some(Arc::clone(&node));
+        ends.len += 1;
+        node
+    }
+
+    /// Removes the node at the front of the list and returns it.
+    pub fn pop_front(&self) -> Option<Arc<ArcListNode<T>>> {
+        let mut ends = self.lock();
+        let node = ends.head.clone()?;
+        Self::unlink(&mut ends, &node);
+        Some(node)
+    }
+
+    /// Removes the node at the back of the list and returns it.
+    pub fn pop_back(&self) -> Option<Arc<ArcListNode<T>>> {
+        let mut ends = self.lock();
+        let node = ends.tail.clone()?;
+        Self::unlink(&mut ends, &node);
+        Some(node)
+    }
+
+    /// Removes `node` from the list. Returns `false` if it is not linked in this list, e.g. it was
+    /// already removed or popped.
+    pub fn remove(&self, node: &Arc<ArcListNode<T>>) -> bool {
+        let mut ends = self.lock();
+        // 只有持有本链表的锁才会写入本链表的id，所以这里读到的值是稳定的
+        if node.owner.load(Relaxed) != self.id {
+            return false;
+        }
+        Self::unlink(&mut ends, node);
+        true
+    }
+
+    /// Returns the node at the front of the list.
+    pub fn front(&self) -> Option<Arc<ArcListNode<T>>> {
+        self.lock().head.clone()
+    }
+
+    /// Returns the node at the back of the list.
+    pub fn back(&self) -> Option<Arc<ArcListNode<T>>> {
+        self.lock().tail.clone()
+    }
+
+    /// Returns the number of the nodes in the list.
+    pub fn len(&self) -> usize {
+        self.lock().len
+    }
+
+    /// Returns `true` if the list is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn new_node(&self, value: T) -> Arc<ArcListNode<T>> {
+        Arc::new(ArcListNode {
+            value,
+            owner: AtomicUsize::new(self.id),
+            prev: Mutex::new(None),
+            next: Mutex::new(None),
+        })
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Ends<T>> {
+        self.ends.lock().unwrap()
+    }
+
+    /// Unlinks `node`, which is linked in the list of `ends`.
+    fn unlink(ends: &mut Ends<T>, node: &Arc<ArcListNode<T>>) {
+        node.owner.store(0, Relaxed);
+        // 前驱在链表中，被它的前驱或head持有，所以upgrade一定成功
+        let prev = node
+            .prev
+            .lock()
+            .unwrap()
+            .take()
+            .map(|prev| prev.upgrade().expect("linked predecessor is alive"));
+        let next = node.next.lock().unwrap().take();
+        if let Some(next) = &next {
+            *next.prev.lock().unwrap() = prev.as_ref().map(Arc::downgrade);
+        }
+        match &prev {
+            Some(prev) => *prev.next.lock().unwrap() = next.clone(),
+            None => ends.head = next.clone(),
+        }
+        if next.is_none() {
+            ends.tail = prev;
+        }
+        ends.len -= 1;
+    }
+}
+
+impl<T> Default for ArcLinkedList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for ArcLinkedList<T> {
+    fn drop(&mut self) {
+        // 逐个断开next，避免长链表递归drop时栈溢出；被句柄持有的节点也不再指向链表里的其他节点
+        let ends = self.ends.get_mut().unwrap();
+        let _ = ends.tail.take();
+        let mut cur = ends.head.take();
+        while let Some(node) = cur {
+            node.owner.store(0, Relaxed);
+            let _ = node.prev.lock().unwrap().take();
+            cur = node.next.lock().unwrap().take();
+        }
+    }
+}
+
+impl<T> fmt::Debug for ArcLinkedList<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ArcLinkedList")
+            .field("len", &self.len())
+            .finish()
+    }
+}
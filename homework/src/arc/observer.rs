@@ -0,0 +1,113 @@
+//! Observer registry that does not keep its observers alive.
+
+use std::fmt;
+use std::sync::Mutex;
+
+use super::{Arc, ArcInner, Weak};
+
+/// Callback notified of events of type `E`, shared as an <code>[Arc]\<Observer\<E>></code> created
+/// by [`ObserverRegistry::observer`].
+pub type Observer<E> = dyn Fn(&E) + Send + Sync;
+
+/// Registry of observers of events of type `E`.
+///
+/// The registry only holds [`Weak`] references to the observers, so registering an observer does
+/// not extend its lifetime. Observers whose last [`Arc`] was dropped are pruned on the next
+/// [`notify`](ObserverRegistry::notify).
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+///
+/// use cs431_homework::ObserverRegistry;
+///
+/// static SUM: AtomicUsize = AtomicUsize::new(0);
+///
+/// let registry = ObserverRegistry::new();
+/// let observer = ObserverRegistry::observer(|e: &usize| {
+///     let _ = SUM.fetch_add(*e, Ordering::Relaxed);
+/// });
+/// registry.register(&observer);
+///
+/// registry.notify(&1);
+/// drop(observer);
+/// registry.notify(&2);
+///
+/// assert_eq!(SUM.load(Ordering::Relaxed), 1);
+/// assert!(registry.is_empty());
+/// ```
+pub struct ObserverRegistry<E> {
+    observers: Mutex<Vec<Weak<Observer<E>>>>,
+}
+
+impl<E> Default for ObserverRegistry<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E> ObserverRegistry<E> {
+    /// Creates a new registry without any observer.
+    pub fn new() -> Self {
+        Self {
+            observers: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Creates an observer that calls `f`, to be registered to registries.
+    pub fn observer<F: Fn(&E) + Send + Sync + 'static>(f: F) -> Arc<Observer<E>> {
+        // `Arc::new`不能创建trait object，先在`Box`里把闭包的`ArcInner`转成unsized的
+        Arc::from_box(Box::new(ArcInner::new(f)))
+    }
+
+    /// Registers the observer. The registry only holds a weak reference to it, so it stops being
+    /// notified once all the `Arc`s to it are dropped.
+    pub fn register(&self, observer: &Arc<Observer<E>>) {
+        self.observers
+            .lock()
+            .unwrap()
+            .push(Arc::downgrade(observer));
+    }
+
+    /// Notifies all the live observers of the event, and prunes the dropped ones.
+    ///
+    /// The observers are called without holding the registry's lock, so an observer may register
+    /// another observer.
+    pub fn notify(&self, event: &E) {
+        let live = {
+            let mut observers = self.observers.lock().unwrap();
+            let mut live = Vec::with_capacity(observers.len());
+            observers.retain(|observer| match observer.upgrade() {
+                Some(observer) => {
+                    live.push(observer);
+                    true
+                }
+                None => false,
+            });
+            live
+        };
+        for observer in live {
+            observer(event);
+        }
+    }
+
+    /// Returns the number of registered observers, including the dropped ones that are not pruned
+    /// yet.
+    pub fn len(&self) -> usize {
+        self.observers.lock().unwrap().len()
+    }
+
+    /// Returns `true` if there is no registered observer.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<E> fmt::Debug for ObserverRegistry<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ObserverRegistry")
+            .field("len", &self.len())
+            .finish()
+    }
+}
@@ -0,0 +1,190 @@
+//! Lock-free stack whose nodes are reclaimed by reference counting.
+
+use std::marker::PhantomData;
+use std::mem::{self, ManuallyDrop};
+#[cfg(not(feature = "check-loom"))]
+use std::sync::atomic::{AtomicIsize, AtomicUsize, Ordering::*};
+use std::{fmt, ptr};
+
+#[cfg(feature = "check-loom")]
+use loom::sync::atomic::{AtomicIsize, AtomicUsize, Ordering::*};
+
+use super::{spin_loop, Arc};
+
+/// Node of `ArcStack`. Aligned so that a pointer to it has 5 bits for the external count.
+#[repr(align(32))]
+struct Node<T> {
+    /// Moved out by the thread that pops the node.
+    value: ManuallyDrop<T>,
+    /// Counted pointer to the next node, which owns a reference to it until the node is popped.
+    next: usize,
+    /// Internal count: the number of the poppers that finished with the node, minus the number of
+    /// the poppers that acquired it, excluding the ones that are still counted in a counted
+    /// pointer. The reference of the stack to the node is released when this becomes 0 after the
+    /// node is popped.
+    internal: AtomicIsize,
+}
+
+/// Treiber's lock-free stack whose nodes are reclaimed by reference counting instead of epochs.
+///
+/// Each node is an [`Arc`], and the stack owns one reference to each node through the head or the
+/// `next` pointer of the node above. A naive `pop` that loads the head and then clones its `Arc`
+/// is unsound, since the node may be popped and freed by another thread in between. So the head is
+/// a *counted pointer* that packs the number of the poppers that acquired the node (the external
+/// count) into the low bits of the pointer, and a popper acquires the node by incrementing it
+/// together with the pointer in a single `compare_exchange`. The node keeps an internal count of
+/// the poppers that are done with it, and the stack's reference is released once the thread that
+/// popped it has transferred the external count to the internal count and all the other poppers
+/// have finished. This is the split reference counting scheme from Williams' *C++ Concurrency in
+/// Action*.
+///
+/// # ABA
+///
+/// The `compare_exchange` on the head would be fooled if the head node was popped, freed, and a
+/// new node was allocated at the same address and pushed in the meantime. This can't happen, since
+/// the node is not freed while a popper is counted in it. Even if the same node is somehow the head
+/// again, the external count has changed, so a stale `compare_exchange` fails.
+///
+/// # Progress
+///
+/// At most 31 poppers can acquire the same node at once. Further poppers spin until one of them
+/// finishes, so `pop` is lock-free only if there are at most 31 concurrent poppers.
+pub struct ArcStack<T> {
+    /// Counted pointer to the top node.
+    head: AtomicUsize,
+    _marker: PhantomData<T>,
+}
+
+unsafe impl<T: Send> Send for ArcStack<T> {}
+unsafe impl<T: Send> Sync for ArcStack<T> {}
+
+impl<T> ArcStack<T> {
+    /// Mask for the external count of a counted pointer.
+    const COUNT_MASK: usize = mem::align_of::<Node<T>>() - 1;
+
+    /// Creates an empty stack.
+    pub fn new() -> Self {
+        Self {
+            head: AtomicUsize::new(0),
+            _marker: PhantomData,
+        }
+    }
+
+    fn node(counted: usize) -> *const Node<T> {
+        (counted & !Self::COUNT_MASK) as *const Node<T>
+    }
+
+    /// Pushes `value` onto the stack.
+    pub fn push(&self, value: T) {
+        let mut node = Arc::new(Node {
+            value: ManuallyDrop::new(value),
+            next: 0,
+            internal: AtomicIsize::new(0),
+        });
+        let mut head = self.head.load(Relaxed);
+        loop {
+            // 新节点还没有共享，继承head的external count
+            Arc::get_mut(&mut node).unwrap().next = head;
+            let raw = Arc::into_raw(node);
+            match self
+                .head
+                .compare_exchange(head, raw as usize, Release, Relaxed)
+            {
+                Ok(_) => return,
+                Err(current) => {
+                    head = current;
+                    node = unsafe { Arc::from_raw(raw) };
+                }
+            }
+        }
+    }
+
+    /// Pops the top value of the stack, or returns `None` if the stack is empty.
+    pub fn pop(&self) -> Option<T> {
+        loop {
+            let mut head = self.acquire_head()?;
+            // SAFETY: The node is not freed while we are counted in it.
+            let node = unsafe { &*Self::node(head) };
+            if self.try_unlink(&mut head, node.next) {
+                // SAFETY: Only the thread that unlinked the node moves out the value.
+                let value = ManuallyDrop::into_inner(unsafe { ptr::read(&node.value) });
+                // 除了自己以外，external count中的其他popper之后会各自把internal count减一
+                let others = (head & Self::COUNT_MASK) as isize - 1;
+                if node.internal.fetch_add(others, AcqRel) == -others {
+                    unsafe { Self::release(head) };
+                }
+                return Some(value);
+            }
+            if node.internal.fetch_sub(1, AcqRel) == 1 {
+                unsafe { Self::release(head) };
+            }
+        }
+    }
+
+    /// Increments the external count of the head, and returns the incremented counted pointer.
+    /// Returns `None` if the stack is empty.
+    fn acquire_head(&self) -> Option<usize> {
+        let mut head = self.head.load(Relaxed);
+        loop {
+            if Self::node(head).is_null() {
+                return None;
+            }
+            if head & Self::COUNT_MASK == Self::COUNT_MASK {
+                spin_loop();
+                head = self.head.load(Relaxed);
+                continue;
+            }
+            match self.head.compare_exchange(head, head + 1, Acquire, Relaxed) {
+                Ok(_) => return Some(head + 1),
+                Err(current) => head = current,
+            }
+        }
+    }
+
+    /// Replaces the head node with `next`. Returns `false` if the head node is changed, which
+    /// means that the node is popped by another thread. Retries if only the external count is
+    /// changed.
+    fn try_unlink(&self, head: &mut usize, next: usize) -> bool {
+        loop {
+            match self.head.compare_exchange(*head, next, Relaxed, Relaxed) {
+                Ok(_) => return true,
+                Err(current) if Self::node(current) == Self::node(*head) => *head = current,
+                Err(_) => return false,
+            }
+        }
+    }
+
+    /// Releases the reference of the stack to the node of `counted`.
+    ///
+    /// # Safety
+    ///
+    /// The node must be popped and no popper may access it anymore.
+    unsafe fn release(counted: usize) {
+        drop(unsafe { Arc::from_raw(Self::node(counted)) });
+    }
+
+    /// Returns `true` if the stack is empty.
+    pub fn is_empty(&self) -> bool {
+        Self::node(self.head.load(Acquire)).is_null()
+    }
+}
+
+impl<T> Default for ArcStack<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for ArcStack<T> {
+    fn drop(&mut self) {
+        while self.pop().is_some() {}
+    }
+}
+
+impl<T> fmt::Debug for ArcStack<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ArcStack")
+            .field("is_empty", &self.is_empty())
+            .finish()
+    }
+}
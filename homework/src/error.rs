@@ -0,0 +1,36 @@
+//! Errors of the fallible concurrent operations.
+
+use core::fmt;
+use std::error::Error;
+
+/// Error returned by the fallible concurrent operations, e.g. `GrowableArray::try_get`,
+/// `FineGrainedListSet::try_contains`, `BlockingSortedQueue::try_push` and
+/// `BlockingSortedQueue::pop_min_timeout`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConcurrencyError {
+    /// The container is at its capacity.
+    Full,
+    /// The operation would have to wait for another thread.
+    WouldBlock,
+    /// The operation did not complete before the timeout expired.
+    Timeout,
+    /// The index is beyond the bound of the container.
+    IndexOutOfBounds,
+    /// Memory allocation failed.
+    AllocError,
+}
+
+impl fmt::Display for ConcurrencyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = match self {
+            Self::Full => "container is full",
+            Self::WouldBlock => "operation would block",
+            Self::Timeout => "operation timed out",
+            Self::IndexOutOfBounds => "index out of bounds",
+            Self::AllocError => "memory allocation failed",
+        };
+        f.write_str(msg)
+    }
+}
+
+impl Error for ConcurrencyError {}
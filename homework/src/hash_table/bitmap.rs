@@ -0,0 +1,133 @@
+//! Concurrent sparse bitmap.
+
+use core::sync::atomic::AtomicU64;
+use core::sync::atomic::Ordering::*;
+
+use crossbeam_epoch::{pin, unprotected, Guard, Owned, Shared};
+
+use super::GrowableArray;
+
+/// Number of bits in a word.
+const WORD_BITS: usize = u64::BITS as usize;
+
+/// Bitmap whose bits can be set and cleared concurrently, e.g. for tracking the allocated slots.
+///
+/// Bit `i` is the bit `i % 64` of the word `i / 64`, and the words are stored in a
+/// [`GrowableArray`]. So the bits can be sparse, and a word is allocated only when one of its bits
+/// is set for the first time. The words are never removed until the bitmap is dropped.
+#[derive(Debug, Default)]
+pub struct ConcurrentBitmap {
+    words: GrowableArray<AtomicU64>,
+}
+
+impl ConcurrentBitmap {
+    /// Creates an empty bitmap.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the bit `index`. Returns the previous value of the bit.
+    pub fn set(&self, index: usize) -> bool {
+        let guard = pin();
+        let mask = 1 << (index % WORD_BITS);
+        self.word(index / WORD_BITS, &guard).fetch_or(mask, AcqRel) & mask != 0
+    }
+
+    /// Clears the bit `index`. Returns the previous value of the bit. Doesn't allocate anything
+    /// if the word of the bit was never set.
+    pub fn clear(&self, index: usize) -> bool {
+        let guard = pin();
+        let mask = 1 << (index % WORD_BITS);
+        self.word_allocated(index / WORD_BITS, &guard)
+            .is_some_and(|word| word.fetch_and(!mask, AcqRel) & mask != 0)
+    }
+
+    /// Returns the bit `index`. Doesn't allocate anything if the word of the bit was never set.
+    pub fn test(&self, index: usize) -> bool {
+        let guard = pin();
+        let mask = 1 << (index % WORD_BITS);
+        self.word_allocated(index / WORD_BITS, &guard)
+            .is_some_and(|word| word.load(Acquire) & mask != 0)
+    }
+
+    /// Returns the number of the set bits. Only the allocated words are visited.
+    ///
+    /// The words are read one by one, so bits set or cleared concurrently may or may not be
+    /// counted.
+    pub fn count_ones(&self) -> usize {
+        let guard = pin();
+        self.words
+            .to_sorted_vec(&guard)
+            .into_iter()
+            // SAFETY: The words are never removed while the bitmap is alive.
+            .map(|(_, word)| unsafe { word.deref() }.load(Acquire).count_ones() as usize)
+            .sum()
+    }
+
+    /// Returns the word `index`, allocating it if it doesn't exist yet.
+    fn word<'g>(&self, index: usize, guard: &'g Guard) -> &'g AtomicU64 {
+        let slot = self.words.slot(index, guard);
+        let mut word = slot.load(Acquire, guard);
+        if word.is_null() {
+            word = match slot.compare_exchange(
+                Shared::null(),
+                Owned::new(AtomicU64::new(0)),
+                AcqRel,
+                Acquire,
+                guard,
+            ) {
+                Ok(word) => word,
+                Err(e) => e.current,
+            };
+        }
+        // SAFETY: The words are never removed while the bitmap is alive.
+        unsafe { word.deref() }
+    }
+
+    /// Returns the word `index` without allocating, or `None` if it doesn't exist yet.
+    fn word_allocated<'g>(&self, index: usize, guard: &'g Guard) -> Option<&'g AtomicU64> {
+        let slot = self.words.get_allocated(index, guard)?;
+        // SAFETY: The words are never removed while the bitmap is alive.
+        unsafe { slot.load(Acquire, guard).as_ref() }
+    }
+}
+
+impl Drop for ConcurrentBitmap {
+    fn drop(&mut self) {
+        // SAFETY: We have the exclusive access to the words.
+        let guard = unsafe { unprotected() };
+        for (_, word) in self.words.to_sorted_vec(guard) {
+            drop(unsafe { word.into_owned() });
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    #[ignore = "the GrowableArray homework (`get` and `Drop`) is not done yet"]
+    fn sparse_allocates_nothing() {
+        let bitmap = ConcurrentBitmap::new();
+        let far = usize::MAX - 3;
+        assert!(!bitmap.test(far));
+        assert!(!bitmap.clear(far));
+        assert!(!bitmap.test(1 << 40));
+        assert_eq!(bitmap.count_ones(), 0);
+
+        let guard = pin();
+        assert!(bitmap.words.to_sorted_vec(&guard).is_empty());
+        assert!(bitmap
+            .words
+            .get_allocated(far / WORD_BITS, &guard)
+            .is_none());
+
+        // Setting a far bit allocates only its word.
+        assert!(!bitmap.set(far));
+        assert!(bitmap.test(far));
+        assert_eq!(bitmap.words.to_sorted_vec(&guard).len(), 1);
+        assert!(!bitmap.test(0));
+        assert_eq!(bitmap.words.to_sorted_vec(&guard).len(), 1);
+    }
+}
@@ -1,10 +1,18 @@
 //! Growable array.
 
+use core::alloc::Layout;
 use core::fmt::Debug;
+use core::marker::PhantomData;
 use core::mem::{self, ManuallyDrop};
-use core::sync::atomic::Ordering::*;
+use core::ops::Range;
+use core::sync::atomic::Ordering::{self, *};
+use core::sync::atomic::{AtomicU64, AtomicUsize};
+use std::alloc::{alloc_zeroed, handle_alloc_error};
 
-use crossbeam_epoch::{Atomic, Guard, Owned, Shared};
+use crossbeam_epoch::{unprotected, Atomic, Collector, Guard, Owned, Pointer, Shared};
+use rand::{thread_rng, Rng};
+
+use crate::ConcurrencyError;
 
 /// Growable array of `Atomic<T>`.
 ///
@@ -126,14 +134,35 @@ use crossbeam_epoch::{Atomic, Guard, Owned, Shared};
 ///
 /// Instead, it should be handled by the container that the elements actually belong to. For
 /// example, in `SplitOrderedList` the destruction of elements are handled by the inner `List`.
+///
+/// # Typed indices
+///
+/// The array is indexed by `I`, which is converted to and from `usize`. It is `usize` by default,
+/// but a newtype index (e.g. `BucketId(usize)`) keeps the indices of arrays indexing different
+/// things from being mixed up. Such an array is created with `Default` or
+/// `GrowableArrayBuilder::index`. The bounds (e.g. `max_index`) are in terms of the converted
+/// `usize`.
 #[derive(Debug)]
-pub struct GrowableArray<T> {
+pub struct GrowableArray<T, I = usize> {
+    /// Root segment, tagged with the height of the tree (0 if the root is null).
     root: Atomic<Segment<T>>,
     height: usize,
+    /// The largest index that can be accessed.
+    max_index: usize,
+    /// The collector whose guards must be used to access the array, or `None` for the global
+    /// collector.
+    collector: Option<Collector>,
+    /// Number of the segments in the tree, incremented when a segment is linked. See
+    /// `segment_count`.
+    segments: AtomicUsize,
+    _index: PhantomData<fn(I) -> I>,
 }
 
 const SEGMENT_LOGSIZE: usize = 10;
 
+/// Mask for the index into a single segment.
+const SEGMENT_MASK: usize = (1 << SEGMENT_LOGSIZE) - 1;
+
 /// A fixed size array of atomic pointers to other `Segment<T>` or `T`.
 ///
 /// Each segment is either a child segment with pointers to `Segment<T>` or an element segment with
@@ -151,12 +180,92 @@ union Segment<T> {
 impl<T> Segment<T> {
     /// Create a new segment filled with null pointers. It is up to the callee to whether to use
     /// this as a children or an element segment.
-    fn new() -> Owned<Self> {
-        Owned::new(
-            // SAFETY: An array of null pointers can be interperted as either an element segment or
-            // a children segment.
-            unsafe { mem::zeroed() },
-        )
+    ///
+    /// Returns `Err(AllocError)` if the allocation fails.
+    fn new() -> Result<Owned<Self>, ConcurrencyError> {
+        // 直接分配清零的内存，分配器可能直接给出已经清零的页，省去memset
+        let layout = Layout::new::<Self>();
+        // SAFETY: `Segment` is not zero-sized.
+        let ptr = unsafe { alloc_zeroed(layout) }.cast::<Self>();
+        if ptr.is_null() {
+            return Err(ConcurrencyError::AllocError);
+        }
+        #[cfg(test)]
+        LIVE_SEGMENTS.with(|live| live.set(live.get() + 1));
+        // SAFETY: The memory is allocated with the global allocator and the layout of `Segment`,
+        // just like `Box`. An array of null pointers can be interperted as either an element
+        // segment or a children segment.
+        Ok(unsafe { Owned::from_raw(ptr) })
+    }
+
+    /// Deallocates the segment of the given `height` and all its descendant segments, but not the
+    /// elements.
+    ///
+    /// # Safety
+    ///
+    /// `segment` must be a non-null segment of the given `height` that is not accessed by anyone
+    /// else, and must not be used after this call.
+    unsafe fn dealloc(segment: Shared<'_, Self>, height: usize) {
+        let segment = unsafe { segment.into_owned() };
+        if height > 1 {
+            for child in unsafe { segment.children.iter() } {
+                let child = child.load(Relaxed, unsafe { unprotected() });
+                if !child.is_null() {
+                    unsafe { Self::dealloc(child, height - 1) };
+                }
+            }
+        }
+    }
+}
+
+impl<T> Segment<T> {
+    /// Deallocates the empty descendant segments of `segment` of the given `height`. Returns
+    /// whether `segment` itself is empty afterwards.
+    ///
+    /// # Safety
+    ///
+    /// `segment` must be a non-null segment of the given `height` that is not accessed by anyone
+    /// else.
+    unsafe fn compact(segment: Shared<'_, Self>, height: usize) -> bool {
+        let guard = unsafe { unprotected() };
+        let segment = unsafe { segment.deref() };
+        if height == 1 {
+            return unsafe { segment.elements.iter() }.all(|e| e.load(Relaxed, guard).is_null());
+        }
+        let mut empty = true;
+        for child in unsafe { segment.children.iter() } {
+            let child_segment = child.load(Relaxed, guard);
+            if child_segment.is_null() {
+                continue;
+            }
+            if unsafe { Self::compact(child_segment, height - 1) } {
+                child.store(Shared::null(), Relaxed);
+                unsafe { Self::dealloc(child_segment, height - 1) };
+            } else {
+                empty = false;
+            }
+        }
+        empty
+    }
+}
+
+#[cfg(test)]
+thread_local! {
+    /// Number of the segments allocated by the current thread minus the ones freed by it, so that
+    /// the tests can check that the segments are freed right away and not deferred to another
+    /// thread or a later epoch.
+    static LIVE_SEGMENTS: core::cell::Cell<isize> = const { core::cell::Cell::new(0) };
+
+    /// Number of the traversals from the root by the current thread, so that the tests can check
+    /// that the batch operations don't traverse a segment more than once.
+    static TRAVERSALS: core::cell::Cell<usize> = const { core::cell::Cell::new(0) };
+}
+
+/// Only counts the freed segments for the tests, as the height is unknown here.
+#[cfg(test)]
+impl<T> Drop for Segment<T> {
+    fn drop(&mut self) {
+        LIVE_SEGMENTS.with(|live| live.set(live.get() - 1));
     }
 }
 
@@ -166,25 +275,51 @@ impl<T> Debug for Segment<T> {
     }
 }
 
-impl<T> Drop for GrowableArray<T> {
+impl<T, I> Drop for GrowableArray<T, I> {
     /// Deallocate segments, but not the individual elements.
     fn drop(&mut self) {
         todo!()
     }
 }
 
-impl<T> Default for GrowableArray<T> {
+impl<T, I> Default for GrowableArray<T, I> {
     fn default() -> Self {
-        Self::new()
+        GrowableArray::builder().index().build()
     }
 }
 
 impl<T> GrowableArray<T> {
     /// Create a new growable array.
     pub fn new() -> Self {
-        Self {
-            height: 0,
-            root: Atomic::null(),
+        Self::bounded(usize::MAX)
+    }
+
+    /// Create a new growable array whose indices are at most `max_index`.
+    ///
+    /// This caps the height of the tree, so that a bogus index (e.g. from a buggy hash function)
+    /// can't make the array grow without bound.
+    pub fn bounded(max_index: usize) -> Self {
+        Self::builder().max_index(max_index).build()
+    }
+
+    /// Create a new growable array accessed with the guards of `collector` instead of the global
+    /// collector.
+    ///
+    /// The guards passed to the methods must then be pinned through a `LocalHandle` registered to
+    /// `collector`, so that the epoch registration is controlled by the caller rather than by the
+    /// thread-locals of the global collector. This is checked in debug builds.
+    pub fn with_collector(collector: &Collector) -> Self {
+        Self::builder().collector(collector).build()
+    }
+
+    /// Returns a builder to configure a new growable array.
+    pub fn builder() -> GrowableArrayBuilder<T> {
+        GrowableArrayBuilder {
+            max_index: usize::MAX,
+            initial_height: 0,
+            collector: None,
+            _marker: PhantomData,
+            _index: PhantomData,
         }
     }
 
@@ -201,7 +336,8 @@ impl<T> GrowableArray<T> {
             match current_node {
                 // 需要申请一个新节点
                 None => {
-                    let new_node = Segment::<T>::new();
+                    let new_node = Segment::<T>::new()
+                        .unwrap_or_else(|_| handle_alloc_error(Layout::new::<Segment<T>>()));
                     // 将新节点插入到树中
                     match parent.compare_exchange(current_shared, new_node, SeqCst, SeqCst, &guard)
                     {
@@ -230,3 +366,1305 @@ impl<T> GrowableArray<T> {
         }
     }
 }
+
+impl<T, I> GrowableArray<T, I> {
+    /// Returns the largest index that can be accessed.
+    pub fn max_index(&self) -> usize {
+        self.max_index
+    }
+
+    /// Returns the collector given to `with_collector`, or `None` if the array uses the global
+    /// collector.
+    pub fn collector(&self) -> Option<&Collector> {
+        self.collector.as_ref()
+    }
+
+    /// Checks that `guard` is pinned by the collector of the array in debug builds.
+    fn debug_check_guard(&self, guard: &Guard) {
+        if let Some(collector) = &self.collector {
+            debug_assert!(
+                guard.collector() == Some(collector),
+                "the guard is not pinned by the collector of the GrowableArray"
+            );
+        }
+    }
+
+    /// Returns the minimum height of the tree that can hold `index`.
+    fn height_for(index: usize) -> usize {
+        let mut height = 1;
+        while height * SEGMENT_LOGSIZE < usize::BITS as usize
+            && index >> (height * SEGMENT_LOGSIZE) != 0
+        {
+            height += 1;
+        }
+        height
+    }
+
+    /// Returns the reference to the `Atomic` pointer at `index` like `try_get`, but with the index
+    /// already converted to `usize`.
+    fn try_get_raw<'g>(
+        &self,
+        index: usize,
+        guard: &'g Guard,
+    ) -> Result<&'g Atomic<T>, ConcurrencyError> {
+        let cursor = self.try_get_leaf(index, guard)?;
+        // SAFETY: The leaf is an element segment since its height is 1.
+        Ok(unsafe { &cursor.leaf.deref().elements[index & SEGMENT_MASK] })
+    }
+
+    /// Returns the cursor at the element segment containing `index` like `try_get_leaf`, but
+    /// panics like `slot` on an error.
+    fn get_leaf<'g>(&self, index: usize, guard: &'g Guard) -> GrowableArrayCursor<'g, T, I> {
+        match self.try_get_leaf(index, guard) {
+            Ok(cursor) => cursor,
+            Err(ConcurrencyError::AllocError) => handle_alloc_error(Layout::new::<Segment<T>>()),
+            Err(_) => panic!(
+                "index {index} is out of bounds of GrowableArray with max index {}",
+                self.max_index
+            ),
+        }
+    }
+
+    /// Returns the cursor at the element segment containing `index`, allocating the missing
+    /// segments on the way.
+    fn try_get_leaf<'g>(
+        &self,
+        index: usize,
+        guard: &'g Guard,
+    ) -> Result<GrowableArrayCursor<'g, T, I>, ConcurrencyError> {
+        self.debug_check_guard(guard);
+        #[cfg(test)]
+        TRAVERSALS.with(|traversals| traversals.set(traversals.get() + 1));
+        if index > self.max_index {
+            return Err(ConcurrencyError::IndexOutOfBounds);
+        }
+        let height = Self::height_for(index);
+
+        // 树高不够时，在当前root之上加一层，旧的root成为新root的第0个child
+        let mut root = self.root.load(Acquire, guard);
+        while root.tag() < height {
+            let new_root = Segment::new()?;
+            let new_height = if root.is_null() {
+                height
+            } else {
+                // SAFETY: The new root is not shared yet.
+                unsafe { new_root.children[0].store(root.with_tag(0), Relaxed) };
+                root.tag() + 1
+            };
+            match self.root.compare_exchange(
+                root,
+                new_root.with_tag(new_height),
+                AcqRel,
+                Acquire,
+                guard,
+            ) {
+                Ok(new_root) => {
+                    let _ = self.segments.fetch_add(1, Relaxed);
+                    root = new_root;
+                }
+                // The new root is freed without touching the old root.
+                Err(e) => root = e.current,
+            }
+        }
+
+        // 从root往下走，沿途缺少的segment用CAS补上
+        let mut segment = root;
+        for level in (2..=root.tag()).rev() {
+            let slot = (index >> ((level - 1) * SEGMENT_LOGSIZE)) & SEGMENT_MASK;
+            // SAFETY: `segment` is a children segment since its height is `level > 1`.
+            let child = unsafe { &segment.deref().children[slot] };
+            segment = child.load(Acquire, guard);
+            if segment.is_null() {
+                segment = match child.compare_exchange(
+                    Shared::null(),
+                    Segment::new()?,
+                    AcqRel,
+                    Acquire,
+                    guard,
+                ) {
+                    Ok(new) => {
+                        let _ = self.segments.fetch_add(1, Relaxed);
+                        new
+                    }
+                    Err(e) => e.current,
+                };
+            }
+        }
+        Ok(GrowableArrayCursor {
+            root,
+            prefix: index >> SEGMENT_LOGSIZE,
+            leaf: segment,
+            _index: PhantomData,
+        })
+    }
+}
+
+impl<T, I: Into<usize> + From<usize>> GrowableArray<T, I> {
+    /// Returns `true` if accessing `index` with `try_get` would have to increase the height of the
+    /// tree, e.g. to defer an access that is not latency-critical. Returns `false` if `index` is
+    /// greater than `max_index`, as such an access fails without growing the tree.
+    ///
+    /// Even if this returns `false`, the access may still allocate segments below the root. The
+    /// result may be stale if another thread grows the tree concurrently.
+    pub fn would_grow(&self, index: I, guard: &Guard) -> bool {
+        let index = index.into();
+        index <= self.max_index && self.root.load(Acquire, guard).tag() < Self::height_for(index)
+    }
+
+    /// Returns the reference to the `Atomic` pointer at `index` like `try_get`, for the other
+    /// containers of this module.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is greater than `max_index`. Aborts if a segment can't be allocated.
+    pub(super) fn slot<'g>(&self, index: I, guard: &'g Guard) -> &'g Atomic<T> {
+        let index = index.into();
+        match self.try_get_raw(index, guard) {
+            Ok(slot) => slot,
+            Err(ConcurrencyError::AllocError) => handle_alloc_error(Layout::new::<Segment<T>>()),
+            Err(_) => panic!(
+                "index {index} is out of bounds of GrowableArray with max index {}",
+                self.max_index
+            ),
+        }
+    }
+
+    /// Returns the reference to the `Atomic` pointer at `index`. Allocates new segments if
+    /// necessary.
+    ///
+    /// Returns `Err(IndexOutOfBounds)` without allocating anything if `index` is greater than
+    /// `max_index`, and `Err(AllocError)` if a segment can't be allocated. The segments allocated
+    /// before the failure are kept in the array.
+    pub fn try_get<'g>(
+        &self,
+        index: I,
+        guard: &'g Guard,
+    ) -> Result<&'g Atomic<T>, ConcurrencyError> {
+        self.try_get_raw(index.into(), guard)
+    }
+
+    /// Returns the reference to the `Atomic` pointer at `index` like `try_get`, but skips the
+    /// traversal if `index` is in the element segment cached in `cursor`, e.g. to iterate over a
+    /// dense range of indices.
+    ///
+    /// The cursor caches the element segment of the last access, and is refilled by the
+    /// traversal on a miss. The cache is invalidated when the height of the tree changes or the
+    /// array is cleared, as both replace the root.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is greater than `max_index`. Aborts if a segment can't be allocated.
+    pub fn cached_get<'g>(
+        &'g self,
+        cursor: &mut GrowableArrayCursor<'g, T, I>,
+        index: I,
+        guard: &'g Guard,
+    ) -> &'g Atomic<T> {
+        let index = index.into();
+        // 旧root在guard期间不会被释放，所以root没变就说明缓存的segment还在树中
+        if !cursor.leaf.is_null()
+            && cursor.prefix == index >> SEGMENT_LOGSIZE
+            && cursor.root == self.root.load(Acquire, guard)
+        {
+            // SAFETY: The cached segment is an element segment of the current tree.
+            return unsafe { &cursor.leaf.deref().elements[index & SEGMENT_MASK] };
+        }
+        *cursor = self.get_leaf(index, guard);
+        // SAFETY: The leaf is an element segment since its height is 1.
+        unsafe { &cursor.leaf.deref().elements[index & SEGMENT_MASK] }
+    }
+
+    /// Installs `init(index)` at each null slot in `ranges`, e.g. to populate the slots of
+    /// several, possibly overlapping, ranges at once. The slots that are already non-null are kept,
+    /// and `init` is not called for them.
+    ///
+    /// The ranges are sorted and merged first, so `init` is called at most once for each index,
+    /// in ascending order, and each element segment is traversed to at most once.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a range is out of bounds. The slots before the out-of-bounds index are installed.
+    pub fn fill_batch(
+        &self,
+        ranges: &[Range<usize>],
+        mut init: impl FnMut(usize) -> Owned<T>,
+        guard: &Guard,
+    ) {
+        let mut sorted = ranges
+            .iter()
+            .filter(|range| !range.is_empty())
+            .cloned()
+            .collect::<Vec<_>>();
+        sorted.sort_unstable_by_key(|range| range.start);
+        let mut merged: Vec<Range<usize>> = Vec::with_capacity(sorted.len());
+        for range in sorted {
+            match merged.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => merged.push(range),
+            }
+        }
+
+        // 相邻的range可能落在同一个segment，缓存的segment跨range保留
+        let mut leaf: Option<GrowableArrayCursor<'_, T, I>> = None;
+        for index in merged.into_iter().flatten() {
+            let prefix = index >> SEGMENT_LOGSIZE;
+            let cursor = match leaf {
+                Some(ref cursor) if cursor.prefix == prefix => cursor,
+                _ => leaf.insert(self.get_leaf(index, guard)),
+            };
+            // SAFETY: The leaf is an element segment since its height is 1.
+            let slot = unsafe { &cursor.leaf.deref().elements[index & SEGMENT_MASK] };
+            if slot.load(Acquire, guard).is_null() {
+                // 并发安装失败时丢弃新值
+                let _ = slot.compare_exchange(Shared::null(), init(index), AcqRel, Acquire, guard);
+            }
+        }
+    }
+
+    /// Returns the reference to the `Atomic` pointer at `index` without allocating, or `None` if
+    /// the segment for `index` is not allocated yet.
+    pub(super) fn get_allocated<'g>(
+        &self,
+        index: usize,
+        guard: &'g Guard,
+    ) -> Option<&'g Atomic<T>> {
+        let root = self.root.load(Acquire, guard);
+        if root.is_null() || root.tag() < Self::height_for(index) {
+            return None;
+        }
+        let mut segment = root;
+        for level in (2..=root.tag()).rev() {
+            let slot = (index >> ((level - 1) * SEGMENT_LOGSIZE)) & SEGMENT_MASK;
+            // SAFETY: `segment` is a children segment since its height is `level > 1`.
+            segment = unsafe { segment.deref().children[slot].load(Acquire, guard) };
+            if segment.is_null() {
+                return None;
+            }
+        }
+        // SAFETY: `segment` is an element segment since its height is 1.
+        Some(unsafe { &segment.deref().elements[index & SEGMENT_MASK] })
+    }
+
+    /// Estimates the number of non-null slots by sampling `samples` random indices in the index
+    /// space covered by the current height of the tree (bounded by `max_index`), without
+    /// traversing the whole tree or allocating. Returns 0 if `samples` is 0.
+    ///
+    /// If the fraction of non-null slots is `p` and the index space has `n` slots, the estimate is
+    /// unbiased and its standard error is `n * sqrt(p * (1 - p) / samples)`. So the relative error
+    /// is large for a sparse array, e.g. a populated prefix much smaller than the index space.
+    pub fn estimate_len(&self, samples: usize, guard: &Guard) -> usize {
+        let Some(last) = self.last_addressable(guard) else {
+            return 0;
+        };
+        if samples == 0 {
+            return 0;
+        }
+
+        let mut rng = thread_rng();
+        let hits = (0..samples)
+            .filter(|_| {
+                self.get_allocated(rng.gen_range(0..=last), guard)
+                    .is_some_and(|slot| !slot.load(Acquire, guard).is_null())
+            })
+            .count();
+        let estimate = hits as f64 / samples as f64 * (last as f64 + 1.0);
+        estimate.round() as usize
+    }
+
+    /// Returns the largest index covered by the current height of the tree (bounded by
+    /// `max_index`), or `None` if the tree is empty.
+    fn last_addressable(&self, guard: &Guard) -> Option<usize> {
+        let height = self.root.load(Acquire, guard).tag();
+        if height == 0 {
+            return None;
+        }
+        let bits = (height * SEGMENT_LOGSIZE).min(usize::BITS as usize);
+        Some(match 1usize.checked_shl(bits as u32) {
+            Some(capacity) => (capacity - 1).min(self.max_index),
+            None => self.max_index,
+        })
+    }
+
+    /// Splits the index space covered by the current height of the tree into `n` contiguous
+    /// ranges of roughly equal length, e.g. to iterate the array in parallel by calling
+    /// [`iter_range`](Self::iter_range) on each range in a different thread.
+    ///
+    /// The ranges are in increasing order and partition `0..end`, where `end - 1` is the last index
+    /// at the current height (bounded by `max_index`). If the tree is empty, all the ranges are
+    /// empty. The slots allocated after this returns by growing the tree are not covered. Since a
+    /// `Range` can't contain `usize::MAX`, that index is not covered either.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is 0.
+    pub fn shard_ranges(&self, n: usize, guard: &Guard) -> Vec<Range<I>> {
+        assert!(n > 0, "shard_ranges requires at least one shard");
+        let end = self
+            .last_addressable(guard)
+            .map_or(0, |last| last.saturating_add(1));
+        // u128 so that `i * end` doesn't overflow
+        let bound = |i: usize| (i as u128 * end as u128 / n as u128) as usize;
+        (0..n)
+            .map(|i| I::from(bound(i))..I::from(bound(i + 1)))
+            .collect()
+    }
+
+    /// Returns an iterator over the populated slots whose index is in `range`, in the increasing
+    /// order of the index.
+    ///
+    /// Subtrees that don't intersect with `range` are not visited at all, so it is cheap to iterate
+    /// a narrow range of a large sparse array.
+    pub fn iter_range<'g>(
+        &'g self,
+        range: Range<I>,
+        guard: &'g Guard,
+    ) -> impl Iterator<Item = (I, &'g Atomic<T>)> + 'g {
+        let range = range.start.into()..range.end.into();
+        let root = self.root.load(Acquire, guard);
+        let mut stack = Vec::new();
+        if !root.is_null() && !range.is_empty() {
+            stack.push(Frame::new(root, root.tag(), 0, &range));
+        }
+        IterRange {
+            range,
+            stack,
+            guard,
+        }
+        .map(|(index, slot)| (I::from(index), slot))
+    }
+
+    /// Returns the smallest populated index that is not less than `start`, or `None` if there is
+    /// no such index.
+    ///
+    /// The tree is descended along the path to `start` and then to the right, and the subtrees
+    /// that are not allocated are skipped without being visited.
+    pub fn next_populated(&self, start: I, guard: &Guard) -> Option<I> {
+        let root = self.root.load(Acquire, guard);
+        if root.is_null() {
+            return None;
+        }
+        Self::next_populated_in(root, root.tag(), 0, start.into(), guard).map(I::from)
+    }
+
+    /// Returns the smallest populated index that is not less than `start` in the subtree of
+    /// `segment`, whose height is `height` and the smallest index is `base`.
+    fn next_populated_in(
+        segment: Shared<'_, Segment<T>>,
+        height: usize,
+        base: usize,
+        start: usize,
+        guard: &Guard,
+    ) -> Option<usize> {
+        let shift = (height - 1) * SEGMENT_LOGSIZE;
+        let first = (start.saturating_sub(base)).checked_shr(shift as u32)?;
+        for slot in first..=SEGMENT_MASK {
+            // 超出usize范围的slot之后都不可能有元素
+            let offset = slot
+                .checked_shl(shift as u32)
+                .filter(|o| o >> shift == slot)?;
+            let index = base | offset;
+            if height == 1 {
+                // SAFETY: `segment` is an element segment since its height is 1.
+                let element = unsafe { segment.deref().elements[slot].load(Acquire, guard) };
+                if !element.is_null() {
+                    return Some(index);
+                }
+                continue;
+            }
+            // SAFETY: `segment` is a children segment since its height is greater than 1.
+            let child = unsafe { segment.deref().children[slot].load(Acquire, guard) };
+            if !child.is_null() {
+                if let Some(index) = Self::next_populated_in(child, height - 1, index, start, guard)
+                {
+                    return Some(index);
+                }
+            }
+        }
+        None
+    }
+
+    /// Returns the largest populated index, or `None` if the array is empty, e.g. for scanning
+    /// backwards or deciding whether to trim the array.
+    ///
+    /// The tree is descended through the last non-null child at each level, and backtracks to the
+    /// previous child only if a subtree has no element.
+    pub fn last_populated(&self, guard: &Guard) -> Option<I> {
+        let root = self.root.load(Acquire, guard);
+        if root.is_null() {
+            return None;
+        }
+        Self::last_populated_in(root, root.tag(), 0, guard).map(I::from)
+    }
+
+    /// Returns the largest populated index in the subtree of `segment`, whose height is `height`
+    /// and the smallest index is `base`.
+    fn last_populated_in(
+        segment: Shared<'_, Segment<T>>,
+        height: usize,
+        base: usize,
+        guard: &Guard,
+    ) -> Option<usize> {
+        let shift = (height - 1) * SEGMENT_LOGSIZE;
+        for slot in (0..=SEGMENT_MASK).rev() {
+            // 超出usize范围的slot不可能有元素
+            let Some(offset) = slot
+                .checked_shl(shift as u32)
+                .filter(|o| o >> shift == slot)
+            else {
+                continue;
+            };
+            let index = base | offset;
+            if height == 1 {
+                // SAFETY: `segment` is an element segment since its height is 1.
+                let element = unsafe { segment.deref().elements[slot].load(Acquire, guard) };
+                if !element.is_null() {
+                    return Some(index);
+                }
+                continue;
+            }
+            // SAFETY: `segment` is a children segment since its height is greater than 1.
+            let child = unsafe { segment.deref().children[slot].load(Acquire, guard) };
+            if !child.is_null() {
+                if let Some(index) = Self::last_populated_in(child, height - 1, index, guard) {
+                    return Some(index);
+                }
+            }
+        }
+        None
+    }
+
+    /// Returns the populated slots as `(index, pointer)` pairs sorted by the index, e.g. for
+    /// dumping the array while debugging.
+    ///
+    /// The index of a slot is reconstructed from the path from the root to it, and the pairs are
+    /// sorted afterwards, so the result doesn't depend on the order of the traversal. Like
+    /// `iter_range`, slots populated concurrently may or may not be included.
+    pub fn to_sorted_vec<'g>(&'g self, guard: &'g Guard) -> Vec<(I, Shared<'g, T>)> {
+        let root = self.root.load(Acquire, guard);
+        let mut result = Vec::new();
+        if root.is_null() {
+            return Vec::new();
+        }
+
+        let mut stack = vec![(root, root.tag(), 0usize)];
+        while let Some((segment, height, base)) = stack.pop() {
+            let shift = (height - 1) * SEGMENT_LOGSIZE;
+            if height == 1 {
+                // SAFETY: `segment` is an element segment since its height is 1.
+                for (slot, element) in unsafe { segment.deref().elements.iter() }.enumerate() {
+                    let element = element.load(Acquire, guard);
+                    if !element.is_null() {
+                        result.push((base | slot, element));
+                    }
+                }
+                continue;
+            }
+            // SAFETY: `segment` is a children segment since its height is greater than 1.
+            for (slot, child) in unsafe { segment.deref().children.iter() }.enumerate() {
+                let child = child.load(Acquire, guard);
+                if !child.is_null() {
+                    stack.push((child, height - 1, base | (slot << shift)));
+                }
+            }
+        }
+        result.sort_unstable_by_key(|(index, _)| *index);
+        result
+            .into_iter()
+            .map(|(index, element)| (I::from(index), element))
+            .collect()
+    }
+
+    /// Returns the pointers in the slots of `range` as a dense vector, whose `i`-th pointer is the
+    /// one at `range.start + i` and is null for an empty slot, e.g. for indexing a snapshot in O(1)
+    /// during a read-heavy phase that follows a build phase.
+    ///
+    /// Like `iter_range`, the unallocated subtrees are skipped, and slots populated concurrently
+    /// may or may not be included. The vector has an entry for every index in `range`, so `range`
+    /// should be of a reasonable length even if the array is sparse.
+    pub fn to_dense_vec<'g>(&'g self, range: Range<usize>, guard: &'g Guard) -> Vec<Shared<'g, T>> {
+        let mut dense = vec![Shared::null(); range.len()];
+        for (index, slot) in self.iter_range(I::from(range.start)..I::from(range.end), guard) {
+            dense[index.into() - range.start] = slot.load(Acquire, guard);
+        }
+        dense
+    }
+
+    /// Moves the element at `from` to `to` if `to` is null. Returns whether the element is moved.
+    ///
+    /// Returns `false` if `from` is null, or if `to` is occupied, in which case the element is put
+    /// back to `from`. While being moved, the element is in neither of the slots. If both slots are
+    /// filled concurrently in the meantime, this retries until either of them becomes null, so the
+    /// element is never lost or duplicated.
+    pub fn move_slot(&self, from: I, to: I, guard: &Guard) -> bool {
+        let from = self.slot(from, guard);
+        let to = self.slot(to, guard);
+        let element = from.swap(Shared::null(), AcqRel, guard);
+        if element.is_null() {
+            return false;
+        }
+        loop {
+            if to
+                .compare_exchange(Shared::null(), element, AcqRel, Acquire, guard)
+                .is_ok()
+            {
+                return true;
+            }
+            if from
+                .compare_exchange(Shared::null(), element, AcqRel, Acquire, guard)
+                .is_ok()
+            {
+                return false;
+            }
+        }
+    }
+
+    /// Replaces the element at `index` with `new` if `pred` holds for the current element (`None`
+    /// if the slot is null). Returns the previous element on success, or `new` back if `pred` does
+    /// not hold.
+    ///
+    /// If the slot is changed concurrently, `pred` is evaluated again on the new element.
+    ///
+    /// Since `pred` reads the current element, the elements must be reclaimed only through the
+    /// epoch-based GC (e.g. `Guard::defer_destroy`) after they are unlinked from the array.
+    pub fn replace_if<'g>(
+        &self,
+        index: I,
+        pred: impl Fn(Option<&T>) -> bool,
+        new: Owned<T>,
+        guard: &'g Guard,
+    ) -> Result<Shared<'g, T>, Owned<T>> {
+        let slot = self.slot(index, guard);
+        let mut new = new;
+        let mut current = slot.load(Acquire, guard);
+        loop {
+            // SAFETY: The elements are not reclaimed while `guard` is alive.
+            if !pred(unsafe { current.as_ref() }) {
+                return Err(new);
+            }
+            match slot.compare_exchange(current, new, AcqRel, Acquire, guard) {
+                Ok(_) => return Ok(current),
+                Err(e) => {
+                    current = e.current;
+                    new = e.new;
+                }
+            }
+        }
+    }
+}
+
+impl<T, I> GrowableArray<T, I> {
+    /// Returns the number of the segments in the tree in O(1), without traversing the tree like
+    /// `path_stats`.
+    ///
+    /// The count is maintained when a segment is linked, so it never waits for and never blocks
+    /// the accesses growing the tree concurrently. It is exact once they are done, except for the
+    /// segments allocated concurrently with `clear`. The populated elements can't be counted this
+    /// way, as they are stored through the returned `Atomic`s without the array noticing.
+    pub fn segment_count(&self) -> usize {
+        self.segments.load(Relaxed)
+    }
+
+    /// Returns the statistics of the segments, e.g. to choose `SEGMENT_LOGSIZE` for the
+    /// distribution of the indices. This traverses the whole tree once.
+    ///
+    /// Like `to_sorted_vec`, slots populated concurrently may or may not be counted.
+    pub fn path_stats(&self, guard: &Guard) -> PathStats {
+        let root = self.root.load(Acquire, guard);
+        let height = root.tag();
+        let mut segments = vec![0; height];
+        let mut filled = vec![0; height];
+
+        let mut stack = Vec::new();
+        if !root.is_null() {
+            stack.push((root.with_tag(0), height));
+        }
+        while let Some((segment, height)) = stack.pop() {
+            segments[height - 1] += 1;
+            if height == 1 {
+                // SAFETY: `segment` is an element segment since its height is 1.
+                filled[0] += unsafe { segment.deref().elements.iter() }
+                    .filter(|element| !element.load(Acquire, guard).is_null())
+                    .count();
+                continue;
+            }
+            // SAFETY: `segment` is a children segment since its height is greater than 1.
+            for child in unsafe { segment.deref().children.iter() } {
+                let child = child.load(Acquire, guard);
+                if !child.is_null() {
+                    filled[height - 1] += 1;
+                    stack.push((child, height - 1));
+                }
+            }
+        }
+
+        let fill_ratios = segments
+            .iter()
+            .zip(&filled)
+            .map(|(&segments, &filled)| filled as f64 / (segments << SEGMENT_LOGSIZE) as f64)
+            .collect();
+        PathStats {
+            allocated_bytes: segments.iter().sum::<usize>() * mem::size_of::<Segment<T>>(),
+            segments,
+            fill_ratios,
+        }
+    }
+
+    /// Flushes the garbage deferred through `guard` (e.g. the segments and elements unlinked by a
+    /// bulk removal) to the global queue and tries to advance the epoch, so that it can be
+    /// reclaimed sooner.
+    ///
+    /// This is only a hint: the garbage is still not reclaimed while another thread is pinned in an
+    /// old epoch, and `guard` itself keeps the current epoch from being reclaimed.
+    pub fn reclaim_now(&self, guard: &Guard) {
+        guard.flush();
+    }
+
+    /// Resets the array to an empty tree by detaching the whole tree at once, so that the array is
+    /// immediately reusable. The segments of the old tree are deallocated once the current epoch
+    /// is over, as in `reclaim_now`.
+    ///
+    /// The elements are not dropped, just like `Drop`, so the caller must reclaim them. A
+    /// concurrent access that loaded the old root may still access the old tree, and its update
+    /// is lost.
+    pub fn clear(&self, guard: &Guard) {
+        self.debug_check_guard(guard);
+        let root = self.root.swap(Shared::null(), AcqRel, guard);
+        if root.is_null() {
+            return;
+        }
+        // 和并发的更新一样，并发分配的segment可能被计入旧树
+        self.segments.store(0, Relaxed);
+        // SAFETY: The tree is unreachable from the array, and the threads that may still access
+        // it are pinned before the swap, so it is deallocated after they are done.
+        unsafe { guard.defer_unchecked(move || Segment::dealloc(root, root.tag())) };
+    }
+
+    /// Checks the structural invariants of the tree, returning the first violation found.
+    ///
+    /// - The root is null iff the height (root's tag) is 0, and the height is at most the height
+    ///   needed for `usize::MAX`.
+    /// - Pointers to segments are not tagged.
+    /// - Every segment is reachable through exactly one path, i.e. no cycle or shared subtree.
+    /// - No segment is linked at a slot outside of the index space.
+    ///
+    /// Whether a segment is a children segment or an element segment is determined solely by its
+    /// depth, so a segment reachable through a single path is always at the correct depth.
+    #[cfg(test)]
+    pub fn validate(&self, guard: &Guard) -> Result<(), String> {
+        use std::collections::HashSet;
+
+        let root = self.root.load(Acquire, guard);
+        let height = root.tag();
+        if root.is_null() {
+            return match height {
+                0 => Ok(()),
+                _ => Err(format!("null root has height {height}")),
+            };
+        }
+        if height == 0 || height > Self::height_for(usize::MAX) {
+            return Err(format!("root has invalid height {height}"));
+        }
+
+        let mut visited = HashSet::new();
+        let mut stack = vec![(root, height)];
+        while let Some((segment, height)) = stack.pop() {
+            if !visited.insert(segment.as_raw()) {
+                return Err(format!(
+                    "segment {:p} at height {height} is reachable more than once",
+                    segment.as_raw()
+                ));
+            }
+            if height == 1 {
+                continue;
+            }
+            let shift = (height - 1) * SEGMENT_LOGSIZE;
+            // SAFETY: `segment` is a children segment since its height is greater than 1.
+            for (slot, child) in unsafe { segment.deref().children.iter() }.enumerate() {
+                let child = child.load(Acquire, guard);
+                if child.tag() != 0 {
+                    return Err(format!(
+                        "child pointer at height {height} slot {slot} has tag {}",
+                        child.tag()
+                    ));
+                }
+                if child.is_null() {
+                    continue;
+                }
+                if slot.checked_shl(shift as u32).map(|i| i >> shift) != Some(slot) {
+                    return Err(format!(
+                        "segment at height {height} slot {slot} is out of the index space"
+                    ));
+                }
+                stack.push((child, height - 1));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns an accessor that performs every operation with `guard`.
+    ///
+    /// Pinning is not free, so it is better to reuse a single guard across many operations rather
+    /// than pinning for each of them. The `Batch` borrows both the array and the guard, so the
+    /// references and pointers it returns are valid as long as the guard is alive.
+    pub fn batch<'g>(&'g self, guard: &'g Guard) -> Batch<'g, T, I> {
+        Batch { array: self, guard }
+    }
+
+    /// Freezes the array into an immutable [`FrozenArray`], e.g. for the reads after a build
+    /// phase.
+    ///
+    /// The empty segments are deallocated, and the root is lowered while only its first child is
+    /// populated, so the frozen tree is no taller than the largest populated index needs. The
+    /// elements are kept as-is, and must still be reclaimed by the caller.
+    pub fn freeze(self, guard: &Guard) -> FrozenArray<T, I> {
+        self.debug_check_guard(guard);
+        let root = self.root.swap(Shared::null(), Relaxed, guard);
+        let mut height = root.tag();
+        let mut root = root.with_tag(0);
+        // SAFETY: We own the array, so no one else accesses the tree.
+        if !root.is_null() && unsafe { Segment::compact(root, height) } {
+            unsafe { Segment::dealloc(root, height) };
+            root = Shared::null();
+            height = 0;
+        }
+
+        // 只有第0个child时，去掉root，把child提升为新的root
+        while height > 1 {
+            // SAFETY: `root` is a children segment since its height is greater than 1.
+            let children = unsafe { &root.deref().children };
+            if children[1..]
+                .iter()
+                .any(|child| !child.load(Relaxed, guard).is_null())
+            {
+                break;
+            }
+            // The root is not empty after compaction, so its first child is not null.
+            let child = children[0].load(Relaxed, guard);
+            // SAFETY: Dropping a segment doesn't drop its children.
+            drop(unsafe { root.into_owned() });
+            root = child;
+            height -= 1;
+        }
+        FrozenArray {
+            root: Atomic::from(root.with_tag(height)),
+            _index: PhantomData,
+        }
+    }
+}
+
+impl<I: Into<usize> + From<usize>> GrowableArray<AtomicU64, I> {
+    /// Adds `delta` to the counter at `index`, and returns the previous value. A null slot is
+    /// initialized to a zero counter first, so the array can be used as a sparse set of counters.
+    ///
+    /// Like the other elements, the counters are not dropped with the array, and must be
+    /// reclaimed by the caller.
+    pub fn increment(&self, index: I, delta: u64, guard: &Guard) -> u64 {
+        let slot = self.slot(index, guard);
+        let mut counter = slot.load(Acquire, guard);
+        if counter.is_null() {
+            // 初始化失败说明别的线程已经装好了计数器
+            counter = match slot.compare_exchange(
+                Shared::null(),
+                Owned::new(AtomicU64::new(0)),
+                AcqRel,
+                Acquire,
+                guard,
+            ) {
+                Ok(counter) => counter,
+                Err(e) => e.current,
+            };
+        }
+        // SAFETY: The counter is non-null, and the counters are removed only by the caller.
+        unsafe { counter.deref() }.fetch_add(delta, Relaxed)
+    }
+}
+
+/// Immutable, compacted snapshot of a `GrowableArray`. See `GrowableArray::freeze`.
+///
+/// The tree never changes, so a read is a plain descent without the growth and the CAS of
+/// `GrowableArray::get`, and needs no guard. Like `GrowableArray`, the elements are not dropped
+/// with the array.
+#[derive(Debug)]
+pub struct FrozenArray<T, I = usize> {
+    /// Root segment, tagged with the height of the tree (0 if the root is null).
+    root: Atomic<Segment<T>>,
+    _index: PhantomData<fn(I) -> I>,
+}
+
+impl<T, I: Into<usize>> FrozenArray<T, I> {
+    /// Returns the pointer at `index`, which is null if the slot is empty.
+    pub fn get_frozen(&self, index: I) -> Shared<'_, T> {
+        let index = index.into();
+        // SAFETY: The segments are not deallocated until the array is dropped.
+        let guard = unsafe { unprotected() };
+        let root = self.root.load(Acquire, guard);
+        if root.tag() < GrowableArray::<T>::height_for(index) {
+            return Shared::null();
+        }
+        let mut segment = root;
+        for level in (2..=root.tag()).rev() {
+            let slot = (index >> ((level - 1) * SEGMENT_LOGSIZE)) & SEGMENT_MASK;
+            // SAFETY: `segment` is a children segment since its height is `level > 1`.
+            segment = unsafe { segment.deref().children[slot].load(Acquire, guard) };
+            if segment.is_null() {
+                return Shared::null();
+            }
+        }
+        // SAFETY: `segment` is an element segment since its height is 1.
+        unsafe { segment.deref().elements[index & SEGMENT_MASK].load(Acquire, guard) }
+    }
+}
+
+impl<T, I> Drop for FrozenArray<T, I> {
+    /// Deallocate segments, but not the individual elements.
+    fn drop(&mut self) {
+        let root = self.root.load(Relaxed, unsafe { unprotected() });
+        if !root.is_null() {
+            // SAFETY: We have the exclusive access to the whole tree.
+            unsafe { Segment::dealloc(root, root.tag()) };
+        }
+    }
+}
+
+/// Statistics of the segments of a `GrowableArray`. See `GrowableArray::path_stats`.
+///
+/// The vectors are indexed by the height of the segments minus 1, i.e. the element segments
+/// first and the root last.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathStats {
+    /// Number of the segments at each height.
+    pub segments: Vec<usize>,
+    /// Average fraction of the non-null slots in the segments at each height.
+    pub fill_ratios: Vec<f64>,
+    /// Total size of the segments in bytes.
+    pub allocated_bytes: usize,
+}
+
+/// Cache of the element segment of the last access to a `GrowableArray`. See
+/// `GrowableArray::cached_get`.
+///
+/// Once used, the cursor borrows the array and the guard, so the cached segment outlives it.
+#[derive(Debug)]
+pub struct GrowableArrayCursor<'g, T, I = usize> {
+    /// Root of the tree when the segment was cached.
+    root: Shared<'g, Segment<T>>,
+    /// Index of the cached segment, i.e. the index without the bits for the slot.
+    prefix: usize,
+    /// The cached element segment, or null if nothing is cached.
+    leaf: Shared<'g, Segment<T>>,
+    _index: PhantomData<fn(I) -> I>,
+}
+
+impl<T, I> GrowableArrayCursor<'_, T, I> {
+    /// Creates an empty cursor.
+    pub fn new() -> Self {
+        Self {
+            root: Shared::null(),
+            prefix: 0,
+            leaf: Shared::null(),
+            _index: PhantomData,
+        }
+    }
+}
+
+impl<T, I> Default for GrowableArrayCursor<'_, T, I> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Accessor to a `GrowableArray` that reuses a single guard. See `GrowableArray::batch`.
+#[derive(Debug)]
+pub struct Batch<'g, T, I = usize> {
+    array: &'g GrowableArray<T, I>,
+    guard: &'g Guard,
+}
+
+impl<'g, T, I: Into<usize> + From<usize>> Batch<'g, T, I> {
+    /// Returns the guard that this accessor uses.
+    pub fn guard(&self) -> &'g Guard {
+        self.guard
+    }
+
+    /// Returns the reference to the `Atomic` pointer at `index`. See `GrowableArray::try_get`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is greater than `max_index`. Aborts if a segment can't be allocated.
+    pub fn get(&self, index: I) -> &'g Atomic<T> {
+        self.array.slot(index, self.guard)
+    }
+
+    /// Loads the pointer at `index`.
+    pub fn load(&self, index: I, ord: Ordering) -> Shared<'g, T> {
+        self.get(index).load(ord, self.guard)
+    }
+
+    /// Stores `new` at `index`, without reclaiming the previous element.
+    pub fn store<P: Pointer<T>>(&self, index: I, new: P, ord: Ordering) {
+        self.get(index).store(new, ord);
+    }
+}
+
+/// Builder for `GrowableArray`. See `GrowableArray::builder`.
+#[derive(Debug)]
+pub struct GrowableArrayBuilder<T, I = usize> {
+    max_index: usize,
+    initial_height: usize,
+    collector: Option<Collector>,
+    _marker: PhantomData<T>,
+    _index: PhantomData<fn(I) -> I>,
+}
+
+impl<T, I> GrowableArrayBuilder<T, I> {
+    /// Sets the largest index that can be accessed. See `GrowableArray::bounded`.
+    pub fn max_index(mut self, max_index: usize) -> Self {
+        self.max_index = max_index;
+        self
+    }
+
+    /// Sets the height of the tree to pre-allocate. Defaults to 0, i.e. nothing is allocated.
+    ///
+    /// The array is built with the segments on the path to index 0 down to this height, so that
+    /// accesses to the indices below `2^(height * SEGMENT_LOGSIZE)` never grow the tree. This is
+    /// useful when the approximate magnitude of the indices is known in advance.
+    pub fn initial_height(mut self, initial_height: usize) -> Self {
+        self.initial_height = initial_height;
+        self
+    }
+
+    /// Sets the collector whose guards are used to access the array. See
+    /// `GrowableArray::with_collector`.
+    pub fn collector(mut self, collector: &Collector) -> Self {
+        self.collector = Some(collector.clone());
+        self
+    }
+
+    /// Sets the type of the indices of the array. See the typed indices of `GrowableArray`.
+    pub fn index<I2>(self) -> GrowableArrayBuilder<T, I2> {
+        GrowableArrayBuilder {
+            max_index: self.max_index,
+            initial_height: self.initial_height,
+            collector: self.collector,
+            _marker: PhantomData,
+            _index: PhantomData,
+        }
+    }
+
+    /// Builds the array.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the initial height is larger than the height needed for `usize::MAX`. Aborts if a
+    /// segment can't be allocated.
+    pub fn build(self) -> GrowableArray<T, I> {
+        let height = self.initial_height;
+        assert!(
+            height <= GrowableArray::<T>::height_for(usize::MAX),
+            "initial height {height} is larger than needed for usize::MAX"
+        );
+
+        // 从叶子往上构造一条只有第0个child的链
+        let mut spine: Option<Owned<Segment<T>>> = None;
+        for _ in 0..height {
+            let segment =
+                Segment::new().unwrap_or_else(|_| handle_alloc_error(Layout::new::<Segment<T>>()));
+            if let Some(child) = spine {
+                // SAFETY: `segment` is a children segment since it is above another segment.
+                unsafe { segment.children[0].store(child, Relaxed) };
+            }
+            spine = Some(segment);
+        }
+        GrowableArray {
+            root: spine.map_or_else(Atomic::null, |root| Atomic::from(root.with_tag(height))),
+            height: 0,
+            max_index: self.max_index,
+            collector: self.collector,
+            segments: AtomicUsize::new(height),
+            _index: PhantomData,
+        }
+    }
+}
+
+/// A segment being visited by `IterRange`.
+struct Frame<'g, T> {
+    segment: Shared<'g, Segment<T>>,
+    height: usize,
+    /// The smallest index covered by the segment.
+    base: usize,
+    /// The next slot to visit.
+    slot: usize,
+    /// The last slot that intersects with the range.
+    last: usize,
+}
+
+impl<'g, T> Frame<'g, T> {
+    /// Creates a frame for the segment that intersects with `range`, visiting only the slots that
+    /// intersect with `range`.
+    fn new(
+        segment: Shared<'g, Segment<T>>,
+        height: usize,
+        base: usize,
+        range: &Range<usize>,
+    ) -> Self {
+        let shift = (height - 1) * SEGMENT_LOGSIZE;
+        Self {
+            segment,
+            height,
+            base,
+            // If this is greater than `SEGMENT_MASK`, the range is out of the root segment.
+            slot: range.start.saturating_sub(base) >> shift,
+            last: ((range.end - 1 - base) >> shift).min(SEGMENT_MASK),
+        }
+    }
+}
+
+/// Iterator returned by `GrowableArray::iter_range`. Traverses the tree in depth-first order.
+struct IterRange<'g, T> {
+    range: Range<usize>,
+    stack: Vec<Frame<'g, T>>,
+    guard: &'g Guard,
+}
+
+impl<'g, T> Iterator for IterRange<'g, T> {
+    type Item = (usize, &'g Atomic<T>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let frame = self.stack.last_mut()?;
+            if frame.slot > frame.last {
+                let _ = self.stack.pop();
+                continue;
+            }
+            let slot = frame.slot;
+            frame.slot += 1;
+            let index = frame.base + (slot << ((frame.height - 1) * SEGMENT_LOGSIZE));
+
+            if frame.height == 1 {
+                // SAFETY: `frame.segment` is an element segment since its height is 1.
+                let element = unsafe { &frame.segment.deref().elements[slot] };
+                if !element.load(Acquire, self.guard).is_null() {
+                    return Some((index, element));
+                }
+            } else {
+                // SAFETY: `frame.segment` is a children segment since its height is greater than 1.
+                let child =
+                    unsafe { frame.segment.deref().children[slot].load(Acquire, self.guard) };
+                if !child.is_null() {
+                    let frame = Frame::new(child, frame.height - 1, index, &self.range);
+                    self.stack.push(frame);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crossbeam_epoch::pin;
+
+    use super::*;
+
+    /// `Segment::new` relies on the allocator to zero the memory. Also run this under Miri, which
+    /// reports reading uninitialized memory.
+    #[test]
+    fn new_segment_is_null() {
+        let segment = Segment::<usize>::new().unwrap();
+        let guard = unsafe { unprotected() };
+        unsafe {
+            assert!(segment
+                .elements
+                .iter()
+                .all(|e| e.load(Relaxed, guard).is_null()));
+            assert!(segment
+                .children
+                .iter()
+                .all(|c| c.load(Relaxed, guard).is_null()));
+        }
+    }
+
+    /// Also run this under Miri, which reports the leaked segments.
+    #[test]
+    #[ignore = "the GrowableArray homework (`get` and `Drop`) is not done yet"]
+    fn drop_frees_synchronously() {
+        let live = || LIVE_SEGMENTS.with(|live| live.get());
+        let before = live();
+        let array = GrowableArray::new();
+        let guard = pin();
+        for index in [0, 1 << 10, 1 << 20, 1 << 40, usize::MAX] {
+            array.slot(index, &guard).store(Owned::new(index), Relaxed);
+        }
+        assert!(live() > before);
+        let elements = array
+            .to_sorted_vec(&guard)
+            .into_iter()
+            .map(|(_, element)| element.as_raw())
+            .collect::<Vec<_>>();
+
+        // Still pinned, so anything deferred couldn't have run yet.
+        drop(array);
+        assert_eq!(live(), before);
+
+        for element in elements {
+            drop(unsafe { Owned::from_raw(element.cast_mut()) });
+        }
+    }
+
+    #[test]
+    #[ignore = "the GrowableArray homework (`get` and `Drop`) is not done yet"]
+    fn freeze_compacts() {
+        let array = GrowableArray::<usize>::new();
+        let guard = pin();
+        // Allocates the segments on the path to a far index, which stay empty.
+        let _ = array.slot(1 << 40, &guard);
+        array.slot(3, &guard).store(Owned::new(3), Relaxed);
+
+        let frozen = array.freeze(&guard);
+        let root = frozen.root.load(Relaxed, &guard);
+        assert_eq!(root.tag(), 1);
+        let element = frozen.get_frozen(3);
+        assert_eq!(unsafe { *element.deref() }, 3);
+        assert!(frozen.get_frozen(1 << 40).is_null());
+        drop(unsafe { element.into_owned() });
+
+        let frozen = GrowableArray::<usize>::builder()
+            .initial_height(3)
+            .build()
+            .freeze(&guard);
+        assert!(frozen.root.load(Relaxed, &guard).is_null());
+        assert!(frozen.get_frozen(0).is_null());
+    }
+
+    #[test]
+    #[ignore = "the GrowableArray homework (`get` and `Drop`) is not done yet"]
+    fn validate_valid() {
+        let array = GrowableArray::<usize>::new();
+        let guard = pin();
+        assert_eq!(array.validate(&guard), Ok(()));
+        for index in [0, 1, 1 << 10, 1 << 30, usize::MAX] {
+            let _ = array.slot(index, &guard);
+            assert_eq!(array.validate(&guard), Ok(()));
+        }
+    }
+
+    #[test]
+    #[ignore = "the GrowableArray homework (`get` and `Drop`) is not done yet"]
+    fn validate_corrupted() {
+        let array = GrowableArray::<usize>::new();
+        let guard = pin();
+        let _ = array.slot(1 << SEGMENT_LOGSIZE, &guard);
+        let root = array.root.load(Relaxed, &guard);
+        assert_eq!(root.tag(), 2);
+        let slot = unsafe { &root.deref().children[2] };
+
+        // cycle
+        slot.store(root.with_tag(0), Relaxed);
+        assert!(array
+            .validate(&guard)
+            .unwrap_err()
+            .contains("reachable more than once"));
+
+        // tagged child
+        slot.store(Shared::null().with_tag(1), Relaxed);
+        assert_eq!(
+            array.validate(&guard),
+            Err("child pointer at height 2 slot 2 has tag 1".to_string())
+        );
+
+        // wrong height
+        slot.store(Shared::null(), Relaxed);
+        array.root.store(root.with_tag(0), Relaxed);
+        assert_eq!(
+            array.validate(&guard),
+            Err("root has invalid height 0".to_string())
+        );
+
+        array.root.store(root, Relaxed);
+        assert_eq!(array.validate(&guard), Ok(()));
+    }
+
+    #[test]
+    #[ignore = "the GrowableArray homework (`get` and `Drop`) is not done yet"]
+    fn builder_initial_height() {
+        let guard = pin();
+        let array = GrowableArray::<usize>::builder().build();
+        assert!(array.root.load(Relaxed, &guard).is_null());
+
+        let array = GrowableArray::<usize>::builder()
+            .initial_height(2)
+            .max_index(1 << 30)
+            .build();
+        assert_eq!(array.max_index(), 1 << 30);
+        assert_eq!(array.validate(&guard), Ok(()));
+        let root = array.root.load(Relaxed, &guard);
+        assert_eq!(root.tag(), 2);
+
+        // Accesses within the pre-allocated height don't grow the tree.
+        for index in [
+            0,
+            1,
+            SEGMENT_MASK,
+            SEGMENT_MASK + 1,
+            (1 << (2 * SEGMENT_LOGSIZE)) - 1,
+        ] {
+            let _ = array.slot(index, &guard);
+            assert_eq!(array.root.load(Relaxed, &guard), root);
+        }
+        let _ = array.slot(1 << (2 * SEGMENT_LOGSIZE), &guard);
+        assert_eq!(array.root.load(Relaxed, &guard).tag(), 3);
+        assert_eq!(array.validate(&guard), Ok(()));
+    }
+
+    #[test]
+    #[ignore = "the GrowableArray homework (`get` and `Drop`) is not done yet"]
+    fn bounded_rejects_without_alloc() {
+        let array = GrowableArray::<usize>::bounded(SEGMENT_MASK);
+        let guard = pin();
+        assert_eq!(
+            array.try_get(SEGMENT_MASK + 1, &guard).err(),
+            Some(ConcurrencyError::IndexOutOfBounds)
+        );
+        assert!(array.root.load(Relaxed, &guard).is_null());
+
+        let _ = array.try_get(SEGMENT_MASK, &guard).unwrap();
+        assert_eq!(
+            array.try_get(usize::MAX, &guard).err(),
+            Some(ConcurrencyError::IndexOutOfBounds)
+        );
+        assert_eq!(array.root.load(Relaxed, &guard).tag(), 1);
+    }
+
+    #[test]
+    #[ignore = "the GrowableArray homework (`get` and `Drop`) is not done yet"]
+    fn fill_batch() {
+        let traversals = || TRAVERSALS.with(|traversals| traversals.get());
+        let array = GrowableArray::new();
+        let guard = pin();
+        array
+            .slot(10, &guard)
+            .store(Owned::new(usize::MAX), Relaxed);
+
+        let ranges = [0..100, 3050..3060, 50..150, 140..2048, 7..7, 3000..3100];
+        let mut installed = Vec::new();
+        let before = traversals();
+        array.fill_batch(
+            &ranges,
+            |index| {
+                installed.push(index);
+                Owned::new(index)
+            },
+            &guard,
+        );
+        // The element segments of 0..2048 and 3000..3100 are 0, 1, 2 and 3.
+        assert_eq!(traversals() - before, 4);
+
+        let expected = (0..2048).chain(3000..3100).filter(|index| *index != 10);
+        assert!(installed.iter().copied().eq(expected));
+        let values = array.to_sorted_vec(&guard);
+        assert_eq!(values.len(), 2048 + 100);
+        for (index, value) in values {
+            let value = unsafe { value.into_owned() };
+            assert_eq!(*value, if index == 10 { usize::MAX } else { index });
+        }
+    }
+}
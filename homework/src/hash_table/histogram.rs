@@ -0,0 +1,51 @@
+//! Concurrent histogram with sparse integer buckets.
+
+use core::sync::atomic::AtomicU64;
+use core::sync::atomic::Ordering::*;
+
+use crossbeam_epoch::{pin, unprotected};
+
+use super::GrowableArray;
+
+/// Histogram whose counters can be incremented concurrently.
+///
+/// The counters are stored in a [`GrowableArray`] indexed by the bucket, so the buckets can be
+/// arbitrary (sparse) integers, and only the segments for the recorded buckets are allocated. The
+/// counter of a bucket is allocated on its first record, and never removed until the histogram is
+/// dropped.
+#[derive(Debug, Default)]
+pub struct ConcurrentHistogram {
+    counters: GrowableArray<AtomicU64>,
+}
+
+impl ConcurrentHistogram {
+    /// Creates an empty histogram.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Increments the counter of `bucket`.
+    pub fn record(&self, bucket: usize) {
+        let _ = self.counters.increment(bucket, 1, &pin());
+    }
+
+    /// Returns the counter of `bucket`. Doesn't allocate anything for a bucket never recorded.
+    pub fn count(&self, bucket: usize) -> u64 {
+        let guard = pin();
+        let Some(slot) = self.counters.get_allocated(bucket, &guard) else {
+            return 0;
+        };
+        // SAFETY: The counters are never removed while the histogram is alive.
+        unsafe { slot.load(Acquire, &guard).as_ref() }.map_or(0, |counter| counter.load(Relaxed))
+    }
+}
+
+impl Drop for ConcurrentHistogram {
+    fn drop(&mut self) {
+        // SAFETY: We have the exclusive access to the counters.
+        let guard = unsafe { unprotected() };
+        for (_, counter) in self.counters.to_sorted_vec(guard) {
+            drop(unsafe { counter.into_owned() });
+        }
+    }
+}
@@ -0,0 +1,316 @@
+//! Concurrent LRU cache.
+
+use core::hash::{BuildHasher, Hash};
+use core::sync::atomic::Ordering::*;
+use core::sync::atomic::{AtomicBool, AtomicUsize};
+use core::{fmt, ptr};
+use std::collections::hash_map::{Entry, RandomState};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::thread;
+
+use crossbeam_epoch::{pin, Guard};
+
+/// Number of the shards of the index.
+const SHARDS: usize = 16;
+
+/// Node of the recency list.
+struct Node<K, V> {
+    /// `None` for the sentinels.
+    entry: Option<(K, V)>,
+    links: Mutex<Links<K, V>>,
+    /// Set by the thread that removes the entry from the cache, so that only one thread removes
+    /// it from the index.
+    claimed: AtomicBool,
+}
+
+/// Links of a node, protected by the lock of the node.
+struct Links<K, V> {
+    prev: *const Node<K, V>,
+    next: *const Node<K, V>,
+    /// Whether the node is in the recency list. Always `true` for the sentinels.
+    linked: bool,
+    /// Whether the entry is removed from the cache, so that the node must not be linked again.
+    removed: bool,
+}
+
+impl<K, V> Node<K, V> {
+    fn new(entry: Option<(K, V)>) -> *mut Self {
+        Box::into_raw(Box::new(Self {
+            links: Mutex::new(Links {
+                prev: ptr::null(),
+                next: ptr::null(),
+                linked: entry.is_none(),
+                removed: false,
+            }),
+            entry,
+            claimed: AtomicBool::new(false),
+        }))
+    }
+
+    /// Returns the key of a regular node.
+    fn key(&self) -> &K {
+        &self.entry.as_ref().unwrap().0
+    }
+
+    /// Claims the removal of the entry. Returns `false` if another thread already claimed it.
+    fn claim(&self) -> bool {
+        !self.claimed.swap(true, AcqRel)
+    }
+}
+
+/// Concurrent cache that evicts the least recently used entry when it is full.
+///
+/// The entries are indexed by a hash map split into shards, each behind its own lock, and are
+/// kept in a doubly linked list in the order of recency, from the most recently used (front) to
+/// the least (back). `get` moves the entry to the front, and `put` inserts a new entry at the
+/// front and evicts entries from the back while the cache holds more than `capacity` entries.
+///
+/// Each node of the recency list has its own lock, which protects its links. The locks are
+/// always taken in the order of the list, from the front to the back, so that moving different
+/// entries doesn't deadlock. Every move to the front locks the front sentinel, so the recency
+/// updates are serialized there, but the lookups in the index and the evictions from the back
+/// run concurrently with them. A shard of the index is locked only for a single lookup, insertion
+/// or removal, never together with the locks of the nodes. The removed nodes are reclaimed with the
+/// epoch-based GC, as a concurrent `get` may still be reading them.
+pub struct ConcurrentLru<K, V> {
+    /// Maps the keys to their nodes, sharded by the hash of the keys.
+    index: [Mutex<HashMap<K, *const Node<K, V>>>; SHARDS],
+    hasher: RandomState,
+    /// Sentinel before the most recently used entry.
+    head: *const Node<K, V>,
+    /// Sentinel after the least recently used entry.
+    tail: *const Node<K, V>,
+    capacity: usize,
+    /// Number of the entries, including the ones being inserted.
+    len: AtomicUsize,
+}
+
+unsafe impl<K: Send + Sync, V: Send + Sync> Send for ConcurrentLru<K, V> {}
+unsafe impl<K: Send + Sync, V: Send + Sync> Sync for ConcurrentLru<K, V> {}
+
+impl<K, V> ConcurrentLru<K, V> {
+    /// Creates an empty cache that holds at most `capacity` entries.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is 0.
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "ConcurrentLru requires a nonzero capacity");
+        let head = Node::new(None);
+        let tail = Node::new(None);
+        // SAFETY: The sentinels are not shared yet.
+        unsafe {
+            (*head).links.get_mut().unwrap().next = tail;
+            (*tail).links.get_mut().unwrap().prev = head;
+        }
+        Self {
+            index: Default::default(),
+            hasher: RandomState::new(),
+            head,
+            tail,
+            capacity,
+            len: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the maximum number of the entries.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of the entries. It may exceed `capacity` while the entries inserted
+    /// concurrently are being evicted.
+    pub fn len(&self) -> usize {
+        self.len.load(Relaxed)
+    }
+
+    /// Returns `true` if the cache has no entry.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn head(&self) -> &Node<K, V> {
+        // SAFETY: The sentinels are freed only when the cache is dropped.
+        unsafe { &*self.head }
+    }
+
+    fn tail(&self) -> &Node<K, V> {
+        // SAFETY: The sentinels are freed only when the cache is dropped.
+        unsafe { &*self.tail }
+    }
+
+    /// Links `node` at the front of the recency list, unless it is removed.
+    fn push_front(&self, node: &Node<K, V>) {
+        let head = self.head();
+        let mut head_links = head.links.lock().unwrap();
+        // SAFETY: The nodes in the list are not reclaimed while the caller is pinned.
+        let first = unsafe { &*head_links.next };
+        let mut first_links = first.links.lock().unwrap();
+        // node不在链表中，只有单独锁它的线程会持有它的锁，不会死锁
+        let mut links = node.links.lock().unwrap();
+        if links.removed {
+            return;
+        }
+        debug_assert!(!links.linked);
+        links.prev = head;
+        links.next = first;
+        links.linked = true;
+        head_links.next = node;
+        first_links.prev = node;
+    }
+
+    /// Unlinks `node` from the recency list. Returns `false` if it is not linked, e.g. because
+    /// another thread is moving it.
+    ///
+    /// If `remove` is set, the node is also marked as removed, even if it is not linked.
+    fn unlink(&self, node: &Node<K, V>, remove: bool) -> bool {
+        loop {
+            let prev = {
+                let mut links = node.links.lock().unwrap();
+                if !links.linked {
+                    links.removed |= remove;
+                    return false;
+                }
+                links.prev
+            };
+
+            // 按链表顺序加锁：先锁前驱，再锁node，最后锁后继
+            // SAFETY: The nodes are not reclaimed while the caller is pinned.
+            let prev = unsafe { &*prev };
+            let mut prev_links = prev.links.lock().unwrap();
+            if !prev_links.linked || !ptr::eq(prev_links.next, node) {
+                // 前驱已经变了，重新读
+                continue;
+            }
+            // While `prev` is locked, `node` can't be unlinked by others.
+            let mut links = node.links.lock().unwrap();
+            // SAFETY: The successor of a linked node is not reclaimed while it is linked.
+            let next = unsafe { &*links.next };
+            let mut next_links = next.links.lock().unwrap();
+            prev_links.next = next;
+            next_links.prev = prev;
+            links.linked = false;
+            links.removed |= remove;
+            return true;
+        }
+    }
+
+    /// Returns the shard of the index that `key` belongs to.
+    fn shard(&self, key: &K) -> &Mutex<HashMap<K, *const Node<K, V>>>
+    where
+        K: Hash,
+    {
+        &self.index[self.hasher.hash_one(key) as usize % SHARDS]
+    }
+
+    /// Removes the entry of `node` claimed by this thread from the index and the recency list,
+    /// and reclaims the node.
+    fn remove_claimed(&self, node: &Node<K, V>, guard: &Guard)
+    where
+        K: Hash + Eq,
+    {
+        // 只有claim成功的线程删除index中的entry，所以index中的key一定还指向node
+        let removed = self.shard(node.key()).lock().unwrap().remove(node.key());
+        debug_assert!(removed.is_some_and(|removed| ptr::eq(removed, node)));
+        let _ = self.unlink(node, true);
+        let _ = self.len.fetch_sub(1, Relaxed);
+        let node = node as *const Node<K, V> as *mut Node<K, V>;
+        // SAFETY: The node is unreachable from the index and the list, and the threads that may
+        // still access it are pinned.
+        unsafe { guard.defer_unchecked(move || drop(Box::from_raw(node))) };
+    }
+
+    /// Removes the least recently used entry. Returns `false` if there is no linked entry.
+    fn evict_one(&self, guard: &Guard) -> bool
+    where
+        K: Hash + Eq,
+    {
+        loop {
+            let last = self.tail().links.lock().unwrap().prev;
+            if ptr::eq(last, self.head) {
+                return false;
+            }
+            // SAFETY: The nodes are not reclaimed while `guard` is alive.
+            let last = unsafe { &*last };
+            if last.claim() {
+                self.remove_claimed(last, guard);
+                return true;
+            }
+            // 其他线程正在删除它，等它从链表中摘除
+            thread::yield_now();
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> ConcurrentLru<K, V> {
+    /// Returns the value of `key` and marks it as the most recently used, or `None` if it is not
+    /// in the cache.
+    pub fn get(&self, key: &K) -> Option<V> {
+        let guard = &pin();
+        let node = *self.shard(key).lock().unwrap().get(key)?;
+        // SAFETY: The node is not reclaimed while `guard` is alive.
+        let node = unsafe { &*node };
+        let value = node.entry.as_ref().unwrap().1.clone();
+        // 正在被删除的节点不需要移动
+        if !node.claimed.load(Acquire) && self.unlink(node, false) {
+            self.push_front(node);
+        }
+        Some(value)
+    }
+
+    /// Inserts the entry as the most recently used, replacing the previous value of `key`. Then
+    /// evicts the least recently used entries while the cache holds more than `capacity` entries.
+    pub fn put(&self, key: K, value: V) {
+        let guard = &pin();
+        let shard = self.shard(&key);
+        let node = Node::new(Some((key.clone(), value)));
+        let _ = self.len.fetch_add(1, Relaxed);
+        loop {
+            let old = match shard.lock().unwrap().entry(key.clone()) {
+                Entry::Vacant(entry) => {
+                    let _ = entry.insert(node);
+                    break;
+                }
+                Entry::Occupied(entry) => *entry.get(),
+            };
+            // 先删除旧的entry再重试
+            // SAFETY: The node is not reclaimed while `guard` is alive.
+            let old = unsafe { &*old };
+            if old.claim() {
+                self.remove_claimed(old, guard);
+            } else {
+                thread::yield_now();
+            }
+        }
+        // SAFETY: The node is not reclaimed while `guard` is alive.
+        self.push_front(unsafe { &*node });
+
+        while self.len.load(Relaxed) > self.capacity {
+            if !self.evict_one(guard) {
+                break;
+            }
+        }
+    }
+}
+
+impl<K, V> fmt::Debug for ConcurrentLru<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConcurrentLru")
+            .field("capacity", &self.capacity)
+            .field("len", &self.len)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<K, V> Drop for ConcurrentLru<K, V> {
+    fn drop(&mut self) {
+        // 没有进行中的操作，index中的节点都在链表中
+        let mut node = self.head.cast_mut();
+        while !node.is_null() {
+            // SAFETY: We have the exclusive access to the nodes.
+            let mut node_box = unsafe { Box::from_raw(node) };
+            node = node_box.links.get_mut().unwrap().next.cast_mut();
+        }
+    }
+}
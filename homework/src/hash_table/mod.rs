@@ -1,7 +1,19 @@
 //! Lock-free hash table based on <https://dl.acm.org/doi/abs/10.1145/1147954.1147958>
 
+mod bitmap;
 mod growable_array;
+mod histogram;
+mod lru;
+mod signed_growable_array;
+mod slab;
 mod split_ordered_list;
 
-pub use growable_array::GrowableArray;
+pub use bitmap::ConcurrentBitmap;
+pub use growable_array::{
+    Batch, FrozenArray, GrowableArray, GrowableArrayBuilder, GrowableArrayCursor, PathStats,
+};
+pub use histogram::ConcurrentHistogram;
+pub use lru::ConcurrentLru;
+pub use signed_growable_array::SignedGrowableArray;
+pub use slab::Slab;
 pub use split_ordered_list::SplitOrderedList;
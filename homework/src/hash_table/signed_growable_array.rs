@@ -0,0 +1,65 @@
+//! Growable array indexed by signed integers.
+
+use crossbeam_epoch::{Atomic, Guard};
+
+use super::GrowableArray;
+use crate::ConcurrencyError;
+
+/// [`GrowableArray`] indexed by `isize`, e.g. by signed offsets from a base.
+///
+/// The indices are mapped to `usize` with the zig-zag encoding (`0 -> 0`, `-1 -> 1`, `1 -> 2`,
+/// `-2 -> 3`, ...), so the indices of small magnitude stay small whatever their sign, and the
+/// negative and the positive indices around 0 share the same few segments instead of being
+/// `2^63` slots apart.
+#[derive(Debug, Default)]
+pub struct SignedGrowableArray<T> {
+    inner: GrowableArray<T>,
+}
+
+/// Maps a signed index to the index of the inner array.
+fn zigzag(index: isize) -> usize {
+    ((index << 1) ^ (index >> (isize::BITS - 1))) as usize
+}
+
+impl<T> SignedGrowableArray<T> {
+    /// Creates a new signed growable array.
+    pub fn new() -> Self {
+        Self {
+            inner: GrowableArray::new(),
+        }
+    }
+
+    /// Returns the reference to the `Atomic` pointer at `index`. Allocates new segments if
+    /// necessary. See `GrowableArray::get`.
+    pub fn get<'g>(&self, index: isize, guard: &'g Guard) -> &'g Atomic<T> {
+        self.inner.slot(zigzag(index), guard)
+    }
+
+    /// Returns the reference to the `Atomic` pointer at `index`, or `Err(AllocError)` if a segment
+    /// can't be allocated. See `GrowableArray::try_get`.
+    pub fn try_get<'g>(
+        &self,
+        index: isize,
+        guard: &'g Guard,
+    ) -> Result<&'g Atomic<T>, ConcurrencyError> {
+        self.inner.try_get(zigzag(index), guard)
+    }
+
+    /// Returns the inner array, indexed by the zig-zag encoded indices.
+    pub fn as_unsigned(&self) -> &GrowableArray<T> {
+        &self.inner
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn zigzag_order() {
+        let encoded = [0, -1, 1, -2, 2].map(zigzag);
+        assert_eq!(encoded, [0, 1, 2, 3, 4]);
+        assert_eq!(zigzag(isize::MAX), usize::MAX - 1);
+        assert_eq!(zigzag(isize::MIN), usize::MAX);
+    }
+}
@@ -0,0 +1,75 @@
+//! Concurrent slab of reference-counted values.
+
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering::*;
+
+use crossbeam_epoch::{pin, unprotected, Owned, Shared};
+
+use super::GrowableArray;
+use crate::Arc;
+
+/// Indexed store of shared values, where each inserted value gets a new index.
+///
+/// The values are stored as [`Arc`]s in a [`GrowableArray`], at the index taken from a counter,
+/// so the indices are never reused. `get` returns a clone of the `Arc`, which keeps the value
+/// alive even after it is removed from the slab. The slot of a removed value is reclaimed with the
+/// epoch-based GC, as a concurrent `get` may still be cloning it.
+#[derive(Debug, Default)]
+pub struct Slab<T> {
+    values: GrowableArray<Arc<T>>,
+    /// The index of the next inserted value.
+    next: AtomicUsize,
+}
+
+impl<T> Slab<T> {
+    /// Creates an empty slab.
+    pub fn new() -> Self {
+        Self {
+            values: GrowableArray::new(),
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// Inserts `value` and returns its index.
+    pub fn insert(&self, value: T) -> usize {
+        let index = self.next.fetch_add(1, Relaxed);
+        let guard = pin();
+        self.values
+            .slot(index, &guard)
+            .store(Owned::new(Arc::new(value)), Release);
+        index
+    }
+
+    /// Returns the value at `index`, or `None` if it is removed or never inserted.
+    pub fn get(&self, index: usize) -> Option<Arc<T>> {
+        let guard = pin();
+        let slot = self.values.get_allocated(index, &guard)?;
+        // SAFETY: A removed `Arc` is destroyed only after the current epoch.
+        unsafe { slot.load(Acquire, &guard).as_ref() }.cloned()
+    }
+
+    /// Removes the value at `index` and returns it, or `None` if it is already removed or never
+    /// inserted.
+    pub fn remove(&self, index: usize) -> Option<Arc<T>> {
+        let guard = pin();
+        let slot = self.values.get_allocated(index, &guard)?;
+        let value = slot.swap(Shared::null(), AcqRel, &guard);
+        // SAFETY: The `Arc` is unlinked, so only the concurrent `get`s that loaded it before the
+        // swap may access it, and it is destroyed after they are done.
+        let arc = unsafe { value.as_ref() }.cloned();
+        if !value.is_null() {
+            unsafe { guard.defer_destroy(value) };
+        }
+        arc
+    }
+}
+
+impl<T> Drop for Slab<T> {
+    fn drop(&mut self) {
+        // SAFETY: We have the exclusive access to the values.
+        let guard = unsafe { unprotected() };
+        for (_, value) in self.values.to_sorted_vec(guard) {
+            drop(unsafe { value.into_owned() });
+        }
+    }
+}
@@ -1,7 +1,12 @@
 //! Split-ordered linked list.
 
+use core::iter;
 use core::mem::{self, MaybeUninit};
-use core::sync::atomic::{AtomicUsize, Ordering::*};
+use core::ops::Range;
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering::*;
+use std::thread;
+
 use crossbeam_epoch::{Guard, Owned};
 use cs431::lockfree::list::{Cursor, List, Node};
 
@@ -23,6 +28,45 @@ pub struct SplitOrderedList<V> {
     size: AtomicUsize,
     /// Number of items.
     count: AtomicUsize,
+    /// Whether the buckets are initialized before `size` covers them. See `eager_buckets`.
+    eager: bool,
+    /// `size` is doubled when the load factor `count / size` exceeds this.
+    grow_at: f64,
+    /// `size` is halved when the load factor drops below this. See `with_load_factors`.
+    shrink_at: f64,
+}
+
+/// Reverses the bits of `x` over the full width of `usize`, i.e. the bit `i` moves to the bit
+/// `usize::BITS - 1 - i`.
+///
+/// The split order of a key is the reversal of its bits, so the most significant bit of the
+/// bucket index must land on the least significant bit of the split-order key, whatever the width
+/// of `usize` is. Otherwise, the buckets split when the number of buckets doubles would not sit
+/// between their parents' sentinels. The reversal swaps adjacent halves of halving sizes, so it
+/// covers every bit of any power-of-two width and never shifts by the full width.
+pub(crate) const fn reverse_bits(x: usize) -> usize {
+    let mut x = x;
+    let mut shift = usize::BITS / 2;
+    // 每轮交换相邻的两块，mask选中每对中的低位块
+    let mut mask = usize::MAX;
+    while shift > 0 {
+        mask ^= mask << shift;
+        x = ((x >> shift) & mask) | ((x & mask) << shift);
+        shift /= 2;
+    }
+    x
+}
+
+/// Returns whether `so_key` is the split-order key of a sentinel node, i.e. `reverse_bits(bucket)`.
+/// The split-order key of a regular node is `reverse_bits(key | 1 << 63)`, which is odd.
+fn is_sentinel(so_key: usize) -> bool {
+    so_key & 1 == 0
+}
+
+/// Returns the key of the regular node of the split-order key `so_key`.
+fn regular_key(so_key: usize) -> usize {
+    debug_assert!(!is_sentinel(so_key));
+    reverse_bits(so_key) & !(1 << (usize::BITS - 1))
 }
 
 impl<V> Default for SplitOrderedList<V> {
@@ -32,16 +76,78 @@ impl<V> Default for SplitOrderedList<V> {
 }
 
 impl<V> SplitOrderedList<V> {
-    /// `size` is doubled when `count > size * LOAD_FACTOR`.
+    /// Default high watermark of the load factor, at which `size` is doubled.
     const LOAD_FACTOR: usize = 2;
 
+    /// Initial number of the buckets, below which `size` is never halved.
+    const MIN_SIZE: usize = 2;
+
     /// Creates a new split ordered list.
     pub fn new() -> Self {
+        Self::with_eager(false)
+    }
+
+    /// Creates a new split ordered list that initializes the buckets eagerly.
+    ///
+    /// By default, the sentinel node of a bucket is inserted on the first access to the bucket,
+    /// which may recursively initialize its parent buckets. In this mode, all the buckets up to
+    /// the current number of buckets are initialized upfront, and the thread that doubles the
+    /// number of buckets initializes the new buckets before publishing the new number. So the
+    /// operations never initialize a bucket, at the cost of the sentinels of the buckets that are
+    /// never used.
+    pub fn eager_buckets() -> Self {
+        let list = Self::with_eager(true);
+        let guard = &crossbeam_epoch::pin();
+        list.init_buckets(0..list.size.load(Relaxed), guard);
+        list
+    }
+
+    /// Creates a new split ordered list that doubles the number of the buckets when the load
+    /// factor (the number of the elements per bucket) exceeds `grow_at`, and halves it when the
+    /// load factor drops below `shrink_at`, e.g. for a map that shrinks after a burst. By default,
+    /// `grow_at` is 2 and the buckets never shrink.
+    ///
+    /// Halving the buckets at most doubles the load factor, which then stays below `grow_at`, and
+    /// doubling them at most halves it, which then stays above `shrink_at`. So the number of the
+    /// buckets doesn't oscillate when the number of the elements moves back and forth around a
+    /// watermark.
+    ///
+    /// Only the number of the buckets in use shrinks. The sentinels of the unused buckets stay in
+    /// the list, since a concurrent operation may be traversing from them, and are used again when
+    /// the buckets grow back.
+    ///
+    /// # Panics
+    ///
+    /// Panics unless `grow_at` is positive and `shrink_at` is at most half of it.
+    pub fn with_load_factors(grow_at: f64, shrink_at: f64) -> Self {
+        assert!(grow_at > 0.0, "grow_at must be positive");
+        assert!(
+            shrink_at <= grow_at / 2.0,
+            "shrink_at must be at most half of grow_at"
+        );
+        let mut list = Self::new();
+        list.grow_at = grow_at;
+        list.shrink_at = shrink_at;
+        list
+    }
+
+    fn with_eager(eager: bool) -> Self {
         Self {
             list: List::new(),
             buckets: GrowableArray::new(),
-            size: AtomicUsize::new(2),
+            size: AtomicUsize::new(Self::MIN_SIZE),
             count: AtomicUsize::new(0),
+            eager,
+            grow_at: Self::LOAD_FACTOR as f64,
+            shrink_at: 0.0,
+        }
+    }
+
+    /// Initializes the buckets in `range`, in ascending order so that the parent of each bucket is
+    /// already initialized.
+    fn init_buckets(&self, range: Range<usize>, guard: &Guard) {
+        for index in range {
+            let _ = self.lookup_bucket(index, guard);
         }
     }
 
@@ -65,9 +171,162 @@ impl<V> SplitOrderedList<V> {
         todo!()
     }
 
+    /// Counts an element inserted to the bucket array of `size` buckets, and doubles the number of
+    /// the buckets if the load factor exceeds `grow_at`. `insert` should call this after inserting
+    /// the node, with the `size` returned by `find`.
+    fn inserted(&self, size: usize, guard: &Guard) {
+        // 元素太多时把bucket数量翻倍，新的bucket在第一次访问时初始化
+        let count = self.count.fetch_add(1, AcqRel) + 1;
+        if count as f64 > size as f64 * self.grow_at {
+            // eager模式下先初始化新的bucket再发布size，新bucket的父bucket都小于size，已经初始化
+            if self.eager {
+                self.init_buckets(size..size * 2, guard);
+            }
+            let _ = self.size.compare_exchange(size, size * 2, AcqRel, Acquire);
+        }
+    }
+
+    /// Uncounts an element deleted from the bucket array of `size` buckets, and halves the number
+    /// of the buckets if the load factor drops below `shrink_at`. `delete` should call this after
+    /// deleting the node, with the `size` returned by `find`.
+    fn deleted(&self, size: usize) {
+        // 元素太少时把bucket数量减半，多出的bucket的sentinel留在链表中
+        let count = self.count.fetch_sub(1, AcqRel) - 1;
+        if size > Self::MIN_SIZE && (count as f64) < size as f64 * self.shrink_at {
+            let _ = self.size.compare_exchange(size, size / 2, AcqRel, Acquire);
+        }
+    }
+
+    /// Returns an iterator over the split-order keys and the values of all the nodes including the
+    /// sentinels, in the split order. The logically deleted nodes are skipped.
+    fn entries<'g>(
+        &'g self,
+        guard: &'g Guard,
+    ) -> impl Iterator<Item = (usize, &'g MaybeUninit<V>)> + 'g {
+        self.entries_in(self.list.head(guard), 0, usize::BITS, guard)
+    }
+
+    /// Returns an iterator over the nodes like `entries`, but only those whose split-order key is
+    /// in the `2^bits` keys from `start`. `cursor` should be at the first node not less than
+    /// `start`.
+    ///
+    /// The key of a node is not accessible through the list's API, so this searches the list for
+    /// the last key of the range: if the cursor doesn't move, the range has at most that key.
+    /// Otherwise, the halves of the range are searched in turn. Each search passes only the nodes
+    /// in its range, so a node is passed at most `bits + 1` times.
+    fn entries_in<'g>(
+        &'g self,
+        cursor: Cursor<'g, usize, MaybeUninit<V>>,
+        start: usize,
+        bits: u32,
+        guard: &'g Guard,
+    ) -> impl Iterator<Item = (usize, &'g MaybeUninit<V>)> + 'g {
+        let mut ranges = vec![(cursor, start, bits)];
+        iter::from_fn(move || loop {
+            let (cursor, start, bits) = ranges.pop()?;
+            let last = start | usize::MAX.checked_shr(usize::BITS - bits).unwrap_or(0);
+            let mut probe = cursor.clone();
+            // 只读查找不会失败，返回值表示找到了未删除的节点
+            let found = probe.find_harris_herlihy_shavit(&last, guard) == Ok(true);
+            if probe.curr() == cursor.curr() {
+                if found {
+                    return Some((last, cursor.lookup()));
+                }
+                continue;
+            }
+            // 先压右半再压左半，按split order弹出
+            let half = 1 << (bits - 1);
+            let mut right = cursor.clone();
+            let _ = right.find_harris_herlihy_shavit(&(start + half), guard);
+            ranges.push((right, start + half, bits - 1));
+            ranges.push((cursor, start, bits - 1));
+        })
+    }
+
+    /// Returns an iterator over the values, in the split order of their keys (not the order of the
+    /// keys).
+    ///
+    /// The nodes are protected by `guard`, so they are not freed during the iteration even if they
+    /// are deleted concurrently. The values deleted before the iterator reaches them are skipped.
+    ///
+    /// The iterator moves forward in the split order, and the nodes never move in it, even when the
+    /// table grows: a resize only adds sentinels. So each value present throughout the iteration
+    /// is yielded exactly once, and a value inserted or deleted concurrently is yielded at most
+    /// once.
+    pub fn iter<'g>(&'g self, guard: &'g Guard) -> impl Iterator<Item = &'g V> + 'g {
+        self.entries(guard)
+            .filter(|(so_key, _)| !is_sentinel(*so_key))
+            // SAFETY: The value of a regular node is initialized.
+            .map(|(_, value)| unsafe { value.assume_init_ref() })
+    }
+
+    /// Calls `f` on each value from `threads` worker threads, e.g. to aggregate the values of a
+    /// large map in parallel.
+    ///
+    /// The list is partitioned into contiguous parts of the split order, each of which starts at
+    /// the sentinel of a bucket, so that the workers start scanning their parts without
+    /// traversing the others'. The number of the parts is the smallest power of two that is at
+    /// least `threads`, but at most the number of buckets, and the parts are dealt to the workers
+    /// in turn. `guard` initializes the sentinels at which the parts start, and each worker pins
+    /// its own guard.
+    ///
+    /// Each value is visited at most once. The values inserted or deleted concurrently may or may
+    /// not be visited.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `threads` is 0.
+    pub fn parallel_scan<F: Fn(&V) + Sync>(&self, threads: usize, f: F, guard: &Guard)
+    where
+        V: Send + Sync,
+    {
+        assert!(threads > 0, "parallel_scan needs at least one thread");
+        let size = self.size.load(Acquire);
+        let bits = threads.next_power_of_two().min(size).trailing_zeros();
+        let parts = 1 << bits;
+        // 第j段的split-order key最高bits位是j，起点是这个前缀对应的bucket的sentinel
+        let start = |part: usize| part.checked_shl(usize::BITS - bits).unwrap_or(0);
+        for part in 0..parts {
+            let _ = self.lookup_bucket(reverse_bits(start(part)), guard);
+        }
+
+        let f = &f;
+        thread::scope(|s| {
+            for worker in 0..threads.min(parts) {
+                let _ = s.spawn(move || {
+                    let guard = &crossbeam_epoch::pin();
+                    for part in (worker..parts).step_by(threads) {
+                        let cursor = self.lookup_bucket(reverse_bits(start(part)), guard);
+                        self.entries_in(cursor, start(part), usize::BITS - bits, guard)
+                            .filter(|(so_key, _)| !is_sentinel(*so_key))
+                            // SAFETY: The value of a regular node is initialized.
+                            .for_each(|(_, value)| f(unsafe { value.assume_init_ref() }));
+                    }
+                });
+            }
+        });
+    }
+
+    /// Returns the number of elements whose key is less than `key`.
+    ///
+    /// The split order is not the order of the keys, so this scans the whole list and takes O(n)
+    /// time. The count is not a snapshot if the map is modified concurrently.
+    pub fn count_less_than(&self, key: &usize) -> usize {
+        let guard = crossbeam_epoch::pin();
+        self.entries(&guard)
+            .filter(|(so_key, _)| !is_sentinel(*so_key) && regular_key(*so_key) < *key)
+            .count()
+    }
+
     fn assert_valid_key(key: usize) {
         assert!(key.leading_zeros() != 0);
     }
+
+    /// Returns the bucket array, so that the tests can inspect its structure.
+    #[cfg(test)]
+    fn buckets(&self) -> &GrowableArray<Node<usize, MaybeUninit<V>>> {
+        &self.buckets
+    }
 }
 
 impl<V> ConcurrentMap<usize, V> for SplitOrderedList<V> {
@@ -86,3 +345,181 @@ impl<V> ConcurrentMap<usize, V> for SplitOrderedList<V> {
         todo!()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crossbeam_epoch::pin;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    #[ignore = "the SplitOrderedList homework is not done yet"]
+    fn buckets_grow() {
+        let map = SplitOrderedList::new();
+        let guard = pin();
+        let initial = map.size.load(Relaxed);
+        for key in 0..initial * SplitOrderedList::<usize>::LOAD_FACTOR {
+            assert_eq!(map.insert(key, key, &guard), Ok(()));
+        }
+        assert_eq!(map.size.load(Relaxed), initial);
+        let populated = map.buckets().to_sorted_vec(&guard);
+        assert!(populated.iter().all(|(index, _)| *index < initial));
+
+        // Crossing the load factor doubles the size, and the new buckets are initialized lazily.
+        for key in initial * SplitOrderedList::<usize>::LOAD_FACTOR..64 {
+            assert_eq!(map.insert(key, key, &guard), Ok(()));
+        }
+        let size = map.size.load(Relaxed);
+        assert!(size > initial);
+        for key in 0..64 {
+            assert_eq!(map.lookup(&key, &guard), Some(&key));
+        }
+        let populated = map.buckets().to_sorted_vec(&guard);
+        assert!(populated.len() > initial);
+        assert!(populated.iter().all(|(index, _)| *index < size));
+        assert_eq!(map.buckets().validate(&guard), Ok(()));
+    }
+
+    #[test]
+    #[ignore = "the SplitOrderedList homework is not done yet"]
+    fn load_factors() {
+        let map = SplitOrderedList::with_load_factors(2.0, 0.5);
+        let guard = pin();
+        let size = || map.size.load(Relaxed);
+
+        // Grows once the load factor exceeds 2.
+        for key in 0..=4 {
+            assert_eq!(map.insert(key, key, &guard), Ok(()));
+        }
+        assert_eq!(size(), 4);
+        for key in 5..=16 {
+            assert_eq!(map.insert(key, key, &guard), Ok(()));
+        }
+        assert_eq!(size(), 16);
+
+        // Moving back and forth around the high watermark doesn't shrink the buckets back.
+        for _ in 0..100 {
+            assert_eq!(map.delete(&16, &guard), Ok(&16));
+            assert_eq!(map.insert(16, 16, &guard), Ok(()));
+        }
+        assert_eq!(size(), 16);
+
+        // Shrinks once the load factor drops below 0.5, i.e. fewer than 8 elements in 16 buckets.
+        for key in (8..=16).rev() {
+            assert_eq!(map.delete(&key, &guard), Ok(&key));
+        }
+        assert_eq!(size(), 16);
+        assert_eq!(map.delete(&7, &guard), Ok(&7));
+        assert_eq!(size(), 8);
+        // Likewise around the low watermark.
+        for _ in 0..100 {
+            assert_eq!(map.insert(7, 7, &guard), Ok(()));
+            assert_eq!(map.delete(&7, &guard), Ok(&7));
+        }
+        assert_eq!(size(), 8);
+
+        for key in 0..7 {
+            assert_eq!(map.delete(&key, &guard), Ok(&key));
+        }
+        assert_eq!(size(), SplitOrderedList::<usize>::MIN_SIZE);
+        for key in 0..64 {
+            assert_eq!(map.insert(key, key, &guard), Ok(()));
+        }
+        for key in 0..64 {
+            assert_eq!(map.lookup(&key, &guard), Some(&key));
+        }
+    }
+
+    #[test]
+    #[ignore = "the SplitOrderedList homework is not done yet"]
+    fn eager_buckets() {
+        let map = SplitOrderedList::eager_buckets();
+        let guard = pin();
+        let sentinels = |map: &SplitOrderedList<usize>| {
+            map.entries(&guard)
+                .filter(|(so_key, _)| is_sentinel(*so_key))
+                .count()
+        };
+        let size = map.size.load(Relaxed);
+        assert_eq!(map.buckets().to_sorted_vec(&guard).len(), size);
+
+        // A lookup in a bucket never accessed before doesn't insert its sentinel.
+        assert_eq!(sentinels(&map), size);
+        assert_eq!(map.lookup(&(size - 1), &guard), None);
+        assert_eq!(sentinels(&map), size);
+
+        for key in 0..64 {
+            assert_eq!(map.insert(key, key, &guard), Ok(()));
+        }
+        let size = map.size.load(Relaxed);
+        assert!(size > 2);
+        assert_eq!(sentinels(&map), size);
+        for key in 0..128 {
+            assert_eq!(map.lookup(&key, &guard), (key < 64).then_some(&key));
+        }
+        assert_eq!(sentinels(&map), size);
+        assert_eq!(map.buckets().validate(&guard), Ok(()));
+    }
+
+    #[test]
+    fn reverse_bits_full_width() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let edges = [
+            0,
+            1,
+            2,
+            3,
+            usize::MAX,
+            usize::MAX >> 1,
+            1 << (usize::BITS - 1),
+        ];
+        let random = iter::repeat_with(|| rng.gen::<usize>()).take(1000);
+        let bits = (0..usize::BITS).map(|i| 1 << i);
+        for x in edges.into_iter().chain(random).chain(bits) {
+            assert_eq!(reverse_bits(x), x.reverse_bits(), "{x:#x}");
+        }
+    }
+
+    #[test]
+    #[ignore = "the SplitOrderedList homework is not done yet"]
+    fn sentinels_in_split_order() {
+        let map = SplitOrderedList::new();
+        let guard = pin();
+        let mut key = 0;
+        for log_size in 1..6 {
+            // Fill the map until the number of buckets reaches `2^log_size`, and initialize all the
+            // buckets.
+            let size = 1 << log_size;
+            while map.size.load(Relaxed) < size {
+                assert_eq!(map.insert(key, key, &guard), Ok(()));
+                key += 1;
+            }
+            map.init_buckets(0..size, &guard);
+
+            // Each bucket `i + size / 2` split from the bucket `i` sits right after it, e.g.
+            // 0, 4, 2, 6, 1, 5, 3, 7 for 8 buckets.
+            let mut expected = vec![0];
+            for half in (0..log_size).map(|l| 1 << l) {
+                expected = expected.into_iter().flat_map(|i| [i, i + half]).collect();
+            }
+            let sentinels = map
+                .entries(&guard)
+                .filter(|(so_key, _)| is_sentinel(*so_key))
+                .map(|(so_key, _)| reverse_bits(so_key))
+                .collect::<Vec<_>>();
+            assert_eq!(sentinels, expected);
+
+            // Each key is in the segment of the list after the sentinel of its bucket.
+            let mut bucket = 0;
+            for (so_key, _) in map.entries(&guard) {
+                if is_sentinel(so_key) {
+                    bucket = reverse_bits(so_key);
+                } else {
+                    assert_eq!(regular_key(so_key) % size, bucket);
+                }
+            }
+        }
+    }
+}
@@ -1,27 +1,712 @@
 //! Thread-safe key/value cache.
 
-use std::collections::hash_map::{Entry, HashMap};
-use std::hash::Hash;
-use std::sync::{Arc, Mutex, RwLock};
-use std::thread;
+use std::borrow::Borrow;
+use std::collections::hash_map::{Entry, HashMap, RandomState};
+use std::collections::VecDeque;
+use std::hash::{BuildHasher, BuildHasherDefault, Hash, Hasher};
+use std::marker::PhantomData;
+use std::num::NonZeroUsize;
+use std::ops::{Deref, DerefMut};
+use std::panic::{self, AssertUnwindSafe, RefUnwindSafe};
+use std::sync::atomic::Ordering::{Relaxed, SeqCst};
+use std::sync::atomic::{AtomicBool, AtomicUsize};
+use std::sync::PoisonError;
+#[cfg(not(feature = "check-loom"))]
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, RwLock};
+use std::time::{Duration, Instant};
+use std::{fmt, mem, thread};
 
-/// Cache that remembers the result for each key.
+#[cfg(feature = "check-loom")]
+use loom::sync::{Arc, Condvar, Mutex, MutexGuard, RwLock};
+
+/// Map of a shard from each key to the slot for its value, which dereferences to the map.
+///
+/// For a cache with a capacity, it also keeps the keys in the order their slots are inserted, so
+/// that `make_room` finds the oldest entries without scanning the whole shard.
+#[derive(Debug)]
+struct ShardMap<K, V> {
+    slots: HashMap<Hashed<K>, Arc<Slot<V>>, BuildHasherDefault<PrehashedHasher>>,
+    /// Keys of the inserted slots, oldest first, with when the slot was created. An entry whose
+    /// key no longer maps to a slot created at that time is stale, and is dropped lazily.
+    order: VecDeque<(Hashed<K>, Instant)>,
+}
+
+impl<K, V> Default for ShardMap<K, V> {
+    fn default() -> Self {
+        Self {
+            slots: HashMap::default(),
+            order: VecDeque::new(),
+        }
+    }
+}
+
+impl<K, V> Deref for ShardMap<K, V> {
+    type Target = HashMap<Hashed<K>, Arc<Slot<V>>, BuildHasherDefault<PrehashedHasher>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.slots
+    }
+}
+
+impl<K, V> DerefMut for ShardMap<K, V> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.slots
+    }
+}
+
+impl<K: Eq, V> ShardMap<K, V> {
+    /// Returns whether `key` still maps to the slot created at `created`.
+    fn is_live(&self, key: &Hashed<K>, created: Instant) -> bool {
+        self.slots
+            .get(key)
+            .is_some_and(|slot| slot.created == created)
+    }
+
+    /// Records that the slot of `key` created at `created` was inserted.
+    fn push_order(&mut self, key: Hashed<K>, created: Instant) {
+        self.order.push_back((key, created));
+        // 删除和替换留下的过期记录太多时整理一次，均摊O(1)
+        if self.order.len() > 2 * self.slots.len() + 16 {
+            let mut order = mem::take(&mut self.order);
+            order.retain(|(key, created)| self.is_live(key, *created));
+            self.order = order;
+        }
+    }
+}
+
+/// A shard of the cache, mapping each key to the slot for its value.
+type Shard<K, V> = RwLock<ShardMap<K, V>>;
+
+/// Callback called with each entry evicted from the cache.
+type EvictCallback<K, V> = Box<dyn Fn(&K, &V) + Send + Sync + RefUnwindSafe>;
+
+/// Key of a shard, with its hash computed by the hasher of the cache.
+///
+/// The hash is computed once per access and reused for choosing the shard and for every lookup in
+/// the shard, instead of hashing the key again, e.g. for the read lookup and then for the write
+/// `entry` on a miss. This matters for the keys that are expensive to hash, such as `String`.
+#[derive(Debug, Clone)]
+struct Hashed<K> {
+    hash: u64,
+    key: K,
+}
+
+/// Borrowed form of [`Hashed`], so that a shard can be looked up without cloning the key.
+trait HashedRef<K> {
+    fn hash_value(&self) -> u64;
+    fn key(&self) -> &K;
+}
+
+impl<K> Hashed<K> {
+    fn new(hash: u64, key: K) -> Self {
+        Self { hash, key }
+    }
+}
+
+impl<K> HashedRef<K> for Hashed<K> {
+    fn hash_value(&self) -> u64 {
+        self.hash
+    }
+
+    fn key(&self) -> &K {
+        &self.key
+    }
+}
+
+impl<K> HashedRef<K> for (u64, &K) {
+    fn hash_value(&self) -> u64 {
+        self.0
+    }
+
+    fn key(&self) -> &K {
+        self.1
+    }
+}
+
+impl<'a, K: 'a> Borrow<dyn HashedRef<K> + 'a> for Hashed<K> {
+    fn borrow(&self) -> &(dyn HashedRef<K> + 'a) {
+        self
+    }
+}
+
+// `Hashed`和`dyn HashedRef`的Hash/Eq必须一致，`Borrow`才是正确的
+impl<K: Eq> PartialEq for dyn HashedRef<K> + '_ {
+    fn eq(&self, other: &Self) -> bool {
+        self.hash_value() == other.hash_value() && self.key() == other.key()
+    }
+}
+
+impl<K: Eq> Eq for dyn HashedRef<K> + '_ {}
+
+impl<K> Hash for dyn HashedRef<K> + '_ {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.hash_value());
+    }
+}
+
+impl<K: Eq> PartialEq for Hashed<K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.hash == other.hash && self.key == other.key
+    }
+}
+
+impl<K: Eq> Eq for Hashed<K> {}
+
+impl<K> Hash for Hashed<K> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.hash);
+    }
+}
+
+/// Hasher of the shards, which passes through the hash of a [`Hashed`] key.
+#[derive(Debug, Default)]
+struct PrehashedHasher(u64);
+
+impl Hasher for PrehashedHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, _: &[u8]) {
+        unreachable!("only the hash of a `Hashed` key is written")
+    }
+
+    fn write_u64(&mut self, hash: u64) {
+        self.0 = hash;
+    }
+}
+
+/// Slot for the value of a key, which the waiters wait on until the computation finishes.
 #[derive(Debug)]
-pub struct Cache<K, V> {
+struct Slot<V> {
+    state: Mutex<SlotState<V>>,
+    ready: Condvar,
+    /// When the slot is inserted to the cache.
+    created: Instant,
+    /// Whether the entry is excluded from the eviction for the capacity. Accessed while holding
+    /// the lock of the shard, which orders the accesses.
+    pinned: AtomicBool,
+    /// Number of the times the waiters woke up from `ready`.
+    #[cfg(test)]
+    wakeups: AtomicUsize,
+    /// Number of the wakeups that found the value not computed yet.
+    #[cfg(test)]
+    early_wakeups: AtomicUsize,
+}
+
+#[derive(Debug)]
+enum SlotState<V> {
+    Computing,
+    Ready(V),
+    /// The computation panicked and the slot is removed from the cache.
+    Abandoned,
+}
+
+impl<V> Slot<V> {
+    fn new() -> Self {
+        Self {
+            state: Mutex::new(SlotState::Computing),
+            ready: Condvar::new(),
+            created: Instant::now(),
+            pinned: AtomicBool::new(false),
+            #[cfg(test)]
+            wakeups: AtomicUsize::new(0),
+            #[cfg(test)]
+            early_wakeups: AtomicUsize::new(0),
+        }
+    }
+
+    /// Creates a slot whose value is already computed.
+    fn ready(value: V) -> Self {
+        Self {
+            state: Mutex::new(SlotState::Ready(value)),
+            ready: Condvar::new(),
+            created: Instant::now(),
+            pinned: AtomicBool::new(false),
+            #[cfg(test)]
+            wakeups: AtomicUsize::new(0),
+            #[cfg(test)]
+            early_wakeups: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the computed value, or `None` if the computation didn't complete.
+    fn into_value(self) -> Option<V> {
+        match self
+            .state
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner)
+        {
+            SlotState::Ready(value) => Some(value),
+            SlotState::Computing | SlotState::Abandoned => None,
+        }
+    }
+
+    /// Locks the state, ignoring the poison. The state is replaced only as a whole, so it is
+    /// consistent even if a thread panicked while holding the lock.
+    fn lock_state(&self) -> MutexGuard<'_, SlotState<V>> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Returns `true` if the value is computed and the slot is older than `ttl`.
+    fn is_expired(&self, ttl: Option<Duration>) -> bool {
+        ttl.is_some_and(|ttl| self.created.elapsed() >= ttl)
+            && matches!(*self.lock_state(), SlotState::Ready(_))
+    }
+
+    /// Returns the value if it is computed and the slot is not older than `ttl`, without waiting
+    /// for the computation.
+    fn peek(&self, ttl: Option<Duration>) -> Option<V>
+    where
+        V: Clone,
+    {
+        if ttl.is_some_and(|ttl| self.created.elapsed() >= ttl) {
+            return None;
+        }
+        match &*self.lock_state() {
+            SlotState::Ready(value) => Some(value.clone()),
+            SlotState::Computing | SlotState::Abandoned => None,
+        }
+    }
+
+    /// Sets the result of the computation, and wakes up a waiter.
+    fn publish(&self, state: SlotState<V>) {
+        *self.lock_state() = state;
+        self.ready.notify_one();
+    }
+}
+
+/// Wakes up the next waiter of a slot when dropped, even if the current waiter panics.
+struct WakeNext<'s>(&'s Condvar);
+
+impl Drop for WakeNext<'_> {
+    fn drop(&mut self) {
+        self.0.notify_one();
+    }
+}
+
+impl<V: Clone> Slot<V> {
+    /// Waits until the value is computed and returns it. Returns `None` if the computation
+    /// panicked, or if the lock is poisoned because cloning the value panicked. In either case, the
+    /// caller should remove the slot and retry with a new slot.
+    ///
+    /// The computing thread wakes up only one waiter, and each waiter wakes up the next one after
+    /// reading the value. So the waiters take the lock one by one instead of all waking up at once
+    /// and contending for it.
+    fn wait(&self) -> Option<V> {
+        // 即使clone panic也要唤醒下一个等待者，否则它们会一直睡眠
+        let _wake_next = WakeNext(&self.ready);
+        let mut state = self.state.lock().ok()?;
+        while matches!(*state, SlotState::Computing) {
+            state = self.ready.wait(state).ok()?;
+            #[cfg(test)]
+            {
+                let _ = self.wakeups.fetch_add(1, Relaxed);
+                if matches!(*state, SlotState::Computing) {
+                    let _ = self.early_wakeups.fetch_add(1, Relaxed);
+                }
+            }
+        }
+        match &*state {
+            SlotState::Ready(value) => Some(value.clone()),
+            SlotState::Computing | SlotState::Abandoned => None,
+        }
+    }
+}
+
+/// Cache that remembers the result for each key.
+///
+/// The keys are partitioned into shards by their hash, each protected by its own lock, so that
+/// accesses to keys in different shards don't contend with each other.
+///
+/// A cache built with [`CacheBuilder`] may also bound the number of entries and their lifetime.
+pub struct Cache<K, V, S = RandomState> {
     // todo! This is an example cache type. Build your own cache type that satisfies the
     // specification for `get_or_insert_with`.
-    inner: RwLock<HashMap<K, Arc<Mutex<Option<V>>>>>,
+    shards: Box<[Shard<K, V>]>,
+    hasher: S,
+    /// Maximum number of the entries in each shard.
+    shard_capacity: Option<usize>,
+    ttl: Option<Duration>,
+    on_evict: Option<EvictCallback<K, V>>,
+    /// Number of the computations in flight.
+    in_flight: AtomicUsize,
+    /// Number of the `quiesce` callers waiting for `in_flight` to become 0. The computations take
+    /// `idle_lock` and notify `idle` only if this is not 0.
+    quiescers: AtomicUsize,
+    idle_lock: Mutex<()>,
+    /// Notified when `in_flight` becomes 0.
+    idle: Condvar,
 }
 
 impl<K, V> Default for Cache<K, V> {
     fn default() -> Self {
-        Self {
-            inner: RwLock::new(HashMap::new()),
+        Self::with_shards(1)
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug, S> fmt::Debug for Cache<K, V, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Cache")
+            .field("shards", &self.shards)
+            .field("shard_capacity", &self.shard_capacity)
+            .field("ttl", &self.ttl)
+            .field("in_flight", &self.in_flight)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<K, V> Cache<K, V> {
+    /// Shard count used by `auto_sharded` when the available parallelism can't be queried.
+    const FALLBACK_SHARDS: usize = 16;
+
+    /// Returns a builder to configure a new cache.
+    pub fn builder() -> CacheBuilder<K, V> {
+        CacheBuilder::new()
+    }
+
+    /// Creates an empty cache with `shards` shards.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `shards` is 0.
+    pub fn with_shards(shards: usize) -> Self {
+        Self::builder().shards(shards).build()
+    }
+
+    /// Creates an empty cache whose shard count is the available parallelism of the machine,
+    /// rounded up to the next power of two. Falls back to 16 shards if the available parallelism
+    /// can't be queried.
+    pub fn auto_sharded() -> Self {
+        let shards = thread::available_parallelism()
+            .map(NonZeroUsize::get)
+            .unwrap_or(Self::FALLBACK_SHARDS)
+            .next_power_of_two();
+        Self::with_shards(shards)
+    }
+}
+
+impl<K, V, S> Cache<K, V, S> {
+    /// Returns the number of shards.
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Returns the number of entries the cache can hold without reallocating, summed over the
+    /// shards.
+    pub fn capacity(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| {
+                shard
+                    .read()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .capacity()
+            })
+            .sum()
+    }
+
+    /// Blocks until no computation is in flight, e.g. to let the pending computations finish before
+    /// the server exits.
+    ///
+    /// Computations that start after this returns are not waited for, so the callers must stop
+    /// issuing new requests first to drain the cache.
+    pub fn quiesce(&self) {
+        if self.in_flight.load(SeqCst) == 0 {
+            return;
+        }
+        let mut idle = self
+            .idle_lock
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        // 先登记再检查in_flight，和finish_computation的先减再检查配对，二者至少有一方看到对方
+        let _ = self.quiescers.fetch_add(1, SeqCst);
+        while self.in_flight.load(SeqCst) > 0 {
+            idle = self.idle.wait(idle).unwrap_or_else(PoisonError::into_inner);
         }
+        let _ = self.quiescers.fetch_sub(1, SeqCst);
+    }
+
+    /// Runs `f` in a [`thread::scope`], passing the scope and a reference to the cache, so that the
+    /// scoped threads share the cache by reference instead of an `Arc`, e.g. a cache on the stack
+    /// of the caller. Returns the result of `f` after all the scoped threads are joined.
+    ///
+    /// ```
+    /// use cs431_homework::hello_server::Cache;
+    ///
+    /// let cache = Cache::default();
+    /// cache.scope(|s, cache| {
+    ///     for _ in 0..4 {
+    ///         let _ = s.spawn(move || cache.get_or_insert_with(1, |k| k * 10));
+    ///     }
+    /// });
+    /// assert_eq!(cache.get_or_insert_with(1, |_| unreachable!()), 10);
+    /// ```
+    pub fn scope<'env, F, R>(&'env self, f: F) -> R
+    where
+        F: for<'scope> FnOnce(&'scope thread::Scope<'scope, 'env>, &'env Self) -> R,
+    {
+        thread::scope(|s| f(s, self))
+    }
+
+    /// Marks a computation as finished, and wakes up the `quiesce`rs if it was the last one.
+    fn finish_computation(&self) {
+        if self.in_flight.fetch_sub(1, SeqCst) == 1 && self.quiescers.load(SeqCst) > 0 {
+            // 等待者在持锁时检查in_flight，先拿一次锁，保证它已经在condvar上睡眠或者能看到0
+            drop(
+                self.idle_lock
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner),
+            );
+            self.idle.notify_all();
+        }
+    }
+
+    /// Calls `f` with each key and its computed value, without cloning the values. The entries
+    /// whose values are still being computed are skipped.
+    ///
+    /// Each shard is read-locked while its entries are visited, and `f` is called while the entry
+    /// is locked. So `f` must not call the methods of the cache that write to the same shard, e.g.
+    /// `get_or_insert_with` for a missing key, or it deadlocks.
+    pub fn for_each<F: FnMut(&K, &V)>(&self, mut f: F) {
+        for shard in self.shards.iter() {
+            for (key, slot) in shard.read().unwrap_or_else(PoisonError::into_inner).iter() {
+                if let SlotState::Ready(value) = &*slot.lock_state() {
+                    f(&key.key, value);
+                }
+            }
+        }
+    }
+
+    /// Consumes the cache and returns its entries, so that the caller controls the order in which
+    /// the values are dropped. The value of a key is `None` if its computation didn't complete.
+    pub fn shutdown(self) -> impl Iterator<Item = (K, Option<V>)> {
+        self.shards
+            .into_vec()
+            .into_iter()
+            .flat_map(|shard| {
+                shard
+                    .into_inner()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .slots
+            })
+            .map(|(key, slot)| {
+                // `self` is consumed, so no invocation can still hold the slot.
+                let slot =
+                    Arc::try_unwrap(slot).unwrap_or_else(|_| panic!("cache slot is still shared"));
+                (key.key, slot.into_value())
+            })
+    }
+}
+
+impl<K: Clone, V, S> Cache<K, V, S> {
+    /// Returns the keys whose values are being computed, e.g. for debugging a stampede of
+    /// expensive computations.
+    ///
+    /// Each shard is read-locked while its slots are probed with `try_lock`, so this never waits
+    /// for a computation. A slot that is locked at the moment (e.g. by a waiter reading its value)
+    /// is skipped.
+    pub fn in_flight_keys(&self) -> Vec<K> {
+        let mut keys = Vec::new();
+        for shard in self.shards.iter() {
+            keys.extend(
+                shard
+                    .read()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .iter()
+                    .filter(|(_, slot)| {
+                        matches!(slot.state.try_lock().as_deref(), Ok(SlotState::Computing))
+                    })
+                    .map(|(key, _)| key.key.clone()),
+            );
+        }
+        keys
+    }
+}
+
+impl<K: Clone, V: Clone, S> Cache<K, V, S> {
+    /// Returns a copy of the entries, e.g. for exporting metrics. The value of a key is `None` if
+    /// it is still being computed.
+    ///
+    /// Each shard is read-locked only while its slots are collected, and then each slot is probed
+    /// with `try_lock`, so this never waits for a computation. A slot that is locked at the moment
+    /// is also reported as `None`. The shards are copied one by one, so the snapshot is not atomic
+    /// across the shards.
+    pub fn export_snapshot(&self) -> Vec<(K, Option<V>)> {
+        let mut snapshot = Vec::new();
+        for shard in self.shards.iter() {
+            let slots = shard
+                .read()
+                .unwrap_or_else(PoisonError::into_inner)
+                .iter()
+                .map(|(key, slot)| (key.key.clone(), Arc::clone(slot)))
+                .collect::<Vec<_>>();
+            snapshot.extend(slots.into_iter().map(|(key, slot)| {
+                let value = match slot.state.try_lock().as_deref() {
+                    Ok(SlotState::Ready(value)) => Some(value.clone()),
+                    _ => None,
+                };
+                (key, value)
+            }));
+        }
+        snapshot
+    }
+}
+
+impl<K: Hash, V, S: BuildHasher> Cache<K, V, S> {
+    /// Returns the hash of `key`, which is reused for all the accesses to the shard of `key`.
+    fn hash_key(&self, key: &K) -> u64 {
+        self.hasher.hash_one(key)
+    }
+}
+
+impl<K, V, S> Cache<K, V, S> {
+    /// Returns the index of the shard that the key with `hash` belongs to.
+    fn shard_index(&self, hash: u64) -> usize {
+        // 分片用高位，低位留给分片内的HashMap，否则同一分片的key低位都相同
+        (hash >> 32) as usize % self.shards.len()
+    }
+
+    /// Returns the shard that the key with `hash` belongs to.
+    fn shard(&self, hash: u64) -> &Shard<K, V> {
+        &self.shards[self.shard_index(hash)]
+    }
+}
+
+impl<K: Eq + Hash, V> Cache<K, V> {
+    /// Creates a cache with `shards` shards, pre-populated with the entries of `init(i)` for each
+    /// shard index `i`, e.g. the partitions of a cache saved on disk. The cache is returned only
+    /// after all the entries are installed.
+    ///
+    /// The keys are hashed with a random state, so an entry of `init(i)` is installed to the shard
+    /// its key belongs to, which is not necessarily the `i`-th shard. If a key appears in more than
+    /// one map, the entry from the map with the largest index wins.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `shards` is 0.
+    pub fn with_shards_init(shards: usize, init: impl Fn(usize) -> HashMap<K, V>) -> Self {
+        // 没有容量上限，不需要为make_room记录插入顺序
+        let mut cache = Self::with_shards(shards);
+        for i in 0..shards {
+            for (key, value) in init(i) {
+                let hash = cache.hash_key(&key);
+                let index = cache.shard_index(hash);
+                let _ = cache.shards[index]
+                    .get_mut()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .insert(Hashed { hash, key }, Arc::new(Slot::ready(value)));
+            }
+        }
+        cache
     }
 }
 
-impl<K: Eq + Hash + Clone, V: Clone> Cache<K, V> {
+impl<K: Eq + Hash, V, S: BuildHasher> Cache<K, V, S> {
+    /// Reserves capacity for at least `additional` more entries, e.g. before warming up the cache
+    /// with many keys, so that the shards don't rehash while being filled.
+    ///
+    /// The capacity is reserved evenly across the shards, so a shard may still grow if the keys
+    /// are not evenly distributed.
+    pub fn reserve(&self, additional: usize) {
+        let per_shard = additional.div_ceil(self.shards.len());
+        for shard in self.shards.iter() {
+            shard
+                .write()
+                .unwrap_or_else(PoisonError::into_inner)
+                .reserve(per_shard);
+        }
+    }
+
+    /// Removes the entry for `key`, so that the next `get_or_insert_with` recomputes it. Returns
+    /// whether the entry was present. The eviction callback is not called for the removed entry.
+    ///
+    /// A concurrent invocation that is computing the value for `key` still returns its result, but
+    /// the result is not cached.
+    pub fn remove(&self, key: &K) -> bool {
+        let hash = self.hash_key(key);
+        self.shard(hash)
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&(hash, key) as &dyn HashedRef<K>)
+            .is_some()
+    }
+
+    /// Removes the entry for `key` if its value is computed and `remove` returns `true` for it,
+    /// e.g. to drop a stale value without racing with a concurrent invocation that replaces it.
+    /// Returns whether the entry was removed.
+    pub(super) fn remove_if(&self, key: &K, remove: impl FnOnce(&V) -> bool) -> bool {
+        let hash = self.hash_key(key);
+        let mut shard = self
+            .shard(hash)
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        let Some(slot) = shard.get(&(hash, key) as &dyn HashedRef<K>) else {
+            return false;
+        };
+        if !matches!(&*slot.lock_state(), SlotState::Ready(value) if remove(value)) {
+            return false;
+        }
+        shard.remove(&(hash, key) as &dyn HashedRef<K>).is_some()
+    }
+
+    /// Pins the entry for `key`, so that it is never evicted to make room for a new entry, e.g. to
+    /// keep a hot key cached. Returns whether the entry was present; an absent key is not pinned.
+    ///
+    /// The pin only affects the eviction for the capacity. An expired entry is still recomputed,
+    /// and the new entry stays pinned. If all the computed entries of a shard are pinned, nothing
+    /// is evicted and the shard exceeds its capacity, so pinning too many keys defeats the
+    /// capacity.
+    pub fn pin(&self, key: &K) -> bool {
+        self.set_pinned(key, true)
+    }
+
+    /// Unpins the entry for `key`, so that it may be evicted again. Returns whether the entry was
+    /// present.
+    pub fn unpin(&self, key: &K) -> bool {
+        self.set_pinned(key, false)
+    }
+
+    /// Calls `f` with the computed value of `key`, or `None` if the entry is absent, still being
+    /// computed or expired, and returns its result. Unlike `get_or_insert_with`, the value is not
+    /// cloned, e.g. to check a field of a large value.
+    ///
+    /// The value is locked while `f` runs, so `f` must not access the entry of `key` itself, e.g.
+    /// with `get_or_insert_with`, or it deadlocks. The shard is not locked meanwhile.
+    pub fn check<R>(&self, key: &K, f: impl FnOnce(Option<&V>) -> R) -> R {
+        let hash = self.hash_key(key);
+        let slot = self
+            .shard(hash)
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&(hash, key) as &dyn HashedRef<K>)
+            .cloned();
+        let Some(slot) = slot.filter(|slot| !slot.is_expired(self.ttl)) else {
+            return f(None);
+        };
+        let state = slot.lock_state();
+        match &*state {
+            SlotState::Ready(value) => f(Some(value)),
+            SlotState::Computing | SlotState::Abandoned => f(None),
+        }
+    }
+
+    /// Sets whether the entry for `key` is pinned, returning whether it was present.
+    fn set_pinned(&self, key: &K, pinned: bool) -> bool {
+        let hash = self.hash_key(key);
+        let shard = self
+            .shard(hash)
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        let Some(slot) = shard.get(&(hash, key) as &dyn HashedRef<K>) else {
+            return false;
+        };
+        slot.pinned.store(pinned, Relaxed);
+        true
+    }
+}
+
+impl<K: Eq + Hash + Clone, V: Clone, S: BuildHasher> Cache<K, V, S> {
     /// Retrieve the value or insert a new one created by `f`.
     ///
     /// An invocation to this function should not block another invocation with a different key. For
@@ -33,43 +718,443 @@ impl<K: Eq + Hash + Clone, V: Clone> Cache<K, V> {
     /// duplicate the work. That is, `f` should be run only once for each key. Specifically, even
     /// for concurrent invocations of `get_or_insert_with(key, f)`, `f` is called only once per key.
     ///
+    /// If the cache has a capacity, the oldest computed entries of the shard of `key` are evicted
+    /// to make room for a new entry, except the pinned ones (see `pin`). If it has a TTL, an
+    /// expired entry is evicted and recomputed.
+    ///
+    /// If `f` panics, the entry for `key` is removed and the panic is propagated to the caller. The
+    /// concurrent invocations waiting for the value retry, so one of them calls its own `f`.
+    ///
+    /// `f` runs without holding any lock of the cache, so it may call `get_or_insert_with` for a
+    /// different key, e.g. to compute a dependency. Calling it for `key` itself deadlocks, as it
+    /// waits for its own value (see `Memoize` for cycle detection).
+    ///
+    /// The locks of the cache recover from poisoning. If cloning a cached value panics while its
+    /// slot is locked, the slot is treated as absent and the value is recomputed on the next
+    /// access.
+    ///
     /// Hint: the [`Entry`] API may be useful in implementing this function.
     ///
     /// [`Entry`]: https://doc.rust-lang.org/stable/std/collections/hash_map/struct.HashMap.html#method.entry
     pub fn get_or_insert_with<F: FnOnce(K) -> V>(&self, key: K, f: F) -> V {
+        self.get_or_insert_with_if(key, f, |_| true)
+    }
+
+    /// Like `get_or_insert_with`, but the computed value is cached only if `keep` returns `true`
+    /// for it. Otherwise, the value is still returned to the concurrent invocations waiting for
+    /// it, but the entry is removed before they are woken up, so the next invocation computes the
+    /// value again.
+    pub(super) fn get_or_insert_with_if<F: FnOnce(K) -> V>(
+        &self,
+        key: K,
+        f: F,
+        keep: impl FnOnce(&V) -> bool,
+    ) -> V {
+        #[cfg(feature = "debug-trace")]
         let current_thread_id = thread::current().id();
-        println!("thread_id: {:?} acquiring read lock", current_thread_id);
-        let inner_read = self.inner.read().unwrap();
-        if let Some(value) = inner_read.get(&key) {
-            let vc = value.clone();
-            drop(inner_read);
-            let v = vc.lock().unwrap();
-            if let Some(vv) = v.as_ref() {
-                println!("thread_id: {:?} dropping read lock", current_thread_id);
-                return vv.clone();
+        let hash = self.hash_key(&key);
+        let shard = self.shard(hash);
+        // 返回的值要么是找到的已计算的值，要么是本次调用发布到slot里的值，ready只赋值一次
+        #[cfg(debug_assertions)]
+        let ready;
+        let value = 'get: {
+            let slot = loop {
+                trace!("thread_id: {:?} acquiring read lock", current_thread_id);
+                let slot = shard
+                    .read()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .get(&(hash, &key) as &dyn HashedRef<K>)
+                    .cloned();
+                trace!("thread_id: {:?} dropping read lock", current_thread_id);
+                if let Some(slot) = slot.filter(|slot| !slot.is_expired(self.ttl)) {
+                    match slot.wait() {
+                        Some(value) => {
+                            #[cfg(debug_assertions)]
+                            {
+                                ready = true;
+                            }
+                            break 'get value;
+                        }
+                        None => {
+                            // 中止或者被poison的slot当作不存在，重新计算
+                            Self::remove_slot(shard, Hashed::new(hash, key.clone()), &slot);
+                            continue;
+                        }
+                    }
+                }
+                trace!("thread_id: {:?} acquiring write lock", current_thread_id);
+                let mut inner_write = shard.write().unwrap_or_else(PoisonError::into_inner);
+                let mut evicted = Vec::new();
+                if !inner_write.contains_key(&(hash, &key) as &dyn HashedRef<K>) {
+                    self.make_room(&mut inner_write, &mut evicted);
+                }
+                let slot = match inner_write.entry(Hashed::new(hash, key.clone())) {
+                    Entry::Occupied(entry) if !entry.get().is_expired(self.ttl) => {
+                        let slot = Arc::clone(entry.get());
+                        drop(inner_write);
+                        trace!("thread_id: {:?} dropping write lock", current_thread_id);
+                        if let Some(value) = slot.wait() {
+                            #[cfg(debug_assertions)]
+                            {
+                                ready = true;
+                            }
+                            break 'get value;
+                        }
+                        Self::remove_slot(shard, Hashed::new(hash, key.clone()), &slot);
+                        continue;
+                    }
+                    Entry::Occupied(mut entry) => {
+                        let slot = Arc::new(Slot::new());
+                        // 过期重新计算的entry保留pin
+                        slot.pinned.store(entry.get().pinned.load(Relaxed), Relaxed);
+                        evicted.push((key.clone(), entry.insert(Arc::clone(&slot))));
+                        slot
+                    }
+                    Entry::Vacant(entry) => Arc::clone(entry.insert(Arc::new(Slot::new()))),
+                };
+                self.track(&mut inner_write, Hashed::new(hash, key.clone()), &slot);
+                let _ = self.in_flight.fetch_add(1, SeqCst);
+                drop(inner_write);
+                trace!("thread_id: {:?} dropping write lock", current_thread_id);
+                // 在锁外调用回调，回调里也可以访问cache
+                self.notify_evicted(evicted);
+                break slot;
+            };
+
+            // 计算期间不持有slot的锁，等待的线程在condvar上睡眠
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                let value = f(key.clone());
+                let cached = value.clone();
+                (value, cached)
+            }));
+            match result {
+                Ok((value, cached)) => {
+                    if !keep(&cached) {
+                        Self::remove_slot(shard, Hashed::new(hash, key), &slot);
+                    }
+                    slot.publish(SlotState::Ready(cached));
+                    #[cfg(debug_assertions)]
+                    {
+                        ready = true;
+                    }
+                    self.finish_computation();
+                    value
+                }
+                Err(payload) => {
+                    // 先删除entry再唤醒等待的线程，它们重试时不会再看到这个slot
+                    Self::remove_slot(shard, Hashed::new(hash, key), &slot);
+                    slot.publish(SlotState::Abandoned);
+                    self.finish_computation();
+                    panic::resume_unwind(payload)
+                }
             }
+        };
+        #[cfg(debug_assertions)]
+        debug_assert!(
+            ready,
+            "returning a value that is neither found nor published"
+        );
+        value
+    }
+
+    /// Retrieves the value or inserts a new one created by `f`, like `get_or_insert_with`, but
+    /// without the guarantee that `f` is called only once per key.
+    ///
+    /// On a miss, `f` is called without claiming the key, so the concurrent invocations for the
+    /// same key may all call their own `f` at the same time. The first result to be installed is
+    /// cached, and every invocation that finds it installed, including the ones whose result lost
+    /// the race, returns the cached value. So `f` should be idempotent and cheap, e.g. when the
+    /// contention on the key costs more than duplicating the computation.
+    ///
+    /// If a `get_or_insert_with` is computing the value of the key, the result of `f` is returned
+    /// without being cached, instead of waiting for the computation. Also, the computations of
+    /// this method are not waited for by `quiesce`. If `f` panics, nothing is cached and the panic
+    /// is propagated to the caller.
+    pub fn optimistic_get_or_insert_with<F: FnOnce(K) -> V>(&self, key: K, f: F) -> V {
+        let hash = self.hash_key(&key);
+        let shard = self.shard(hash);
+        let slot = shard
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&(hash, &key) as &dyn HashedRef<K>)
+            .cloned();
+        if let Some(value) = slot.and_then(|slot| slot.peek(self.ttl)) {
+            return value;
         }
-        else {
-            drop(inner_read);
-        }
-        println!("thread_id: {:?} dropping read lock", current_thread_id);
-        println!("thread_id: {:?} acquiring write lock", current_thread_id);
-        let mut inner_write = self.inner.write().unwrap();
-        if let Entry::Occupied(entry) = inner_write.entry(key.clone()) {
-            let value_lock = entry.get().clone();
-            let mut vl_guard = value_lock.lock().unwrap();
-            if let Some(vv) = vl_guard.as_ref() {
-                println!("thread_id: {:?} dropping write lock", current_thread_id);
-                return vv.clone();
-            }
+
+        // 不占用key，并发的调用可能各自计算，先安装的结果生效
+        let value = f(key.clone());
+        let mut inner_write = shard.write().unwrap_or_else(PoisonError::into_inner);
+        let mut evicted = Vec::new();
+        if !inner_write.contains_key(&(hash, &key) as &dyn HashedRef<K>) {
+            self.make_room(&mut inner_write, &mut evicted);
         }
-        let value_lock = Arc::new(Mutex::new(None));
-        inner_write.insert(key.clone(), Arc::clone(&value_lock));
-        let mut vl_guard = value_lock.lock().unwrap();
+        let value = match inner_write.entry(Hashed::new(hash, key.clone())) {
+            Entry::Occupied(mut entry) => {
+                if let Some(cached) = entry.get().peek(self.ttl) {
+                    cached
+                } else if matches!(*entry.get().lock_state(), SlotState::Computing) {
+                    // 正在计算的slot留给计算它的线程
+                    value
+                } else {
+                    let slot = Arc::new(Slot::ready(value.clone()));
+                    slot.pinned.store(entry.get().pinned.load(Relaxed), Relaxed);
+                    evicted.push((key.clone(), entry.insert(Arc::clone(&slot))));
+                    self.track(&mut inner_write, Hashed::new(hash, key), &slot);
+                    value
+                }
+            }
+            Entry::Vacant(entry) => {
+                let slot = Arc::clone(entry.insert(Arc::new(Slot::ready(value.clone()))));
+                self.track(&mut inner_write, Hashed::new(hash, key), &slot);
+                value
+            }
+        };
         drop(inner_write);
-        println!("thread_id: {:?} dropping write lock", current_thread_id);
-        let value = f(key.clone());
-        *vl_guard = Some(value.clone());
+        self.notify_evicted(evicted);
         value
     }
+
+    /// Removes the entry for `key` from `shard` if it is still `slot`.
+    fn remove_slot(shard: &Shard<K, V>, key: Hashed<K>, slot: &Arc<Slot<V>>) {
+        let mut inner_write = shard.write().unwrap_or_else(PoisonError::into_inner);
+        if let Entry::Occupied(entry) = inner_write.entry(key) {
+            if Arc::ptr_eq(entry.get(), slot) {
+                let _ = entry.remove();
+            }
+        }
+    }
+
+    /// Removes the oldest computed entries of `shard` until it has room for a new entry, and
+    /// pushes them to `evicted`. The entries still being computed and the pinned entries are not
+    /// evicted, so the shard may exceed its capacity if none of its entries can be evicted.
+    ///
+    /// The entries are visited in the insertion order kept by `track`, so an eviction usually pops
+    /// the front of the order, and only the entries that can't be evicted are skipped over.
+    fn make_room(&self, shard: &mut ShardMap<K, V>, evicted: &mut Vec<(K, Arc<Slot<V>>)>) {
+        let Some(capacity) = self.shard_capacity else {
+            return;
+        };
+        let mut i = 0;
+        while shard.len() >= capacity {
+            let Some((key, created)) = shard.order.get(i) else {
+                break;
+            };
+            if !shard.is_live(key, *created) {
+                // 已经删除或者替换的entry
+                let _ = shard.order.remove(i);
+                continue;
+            }
+            let slot = &shard.slots[key];
+            if slot.pinned.load(Relaxed) || !matches!(*slot.lock_state(), SlotState::Ready(_)) {
+                i += 1;
+                continue;
+            }
+            let (key, _) = shard.order.remove(i).unwrap();
+            let slot = shard.slots.remove(&key).unwrap();
+            evicted.push((key.key, slot));
+        }
+    }
+
+    /// Records the insertion of `slot` for `key` in `shard` for `make_room`, if the cache has a
+    /// capacity.
+    fn track(&self, shard: &mut ShardMap<K, V>, key: Hashed<K>, slot: &Slot<V>) {
+        if self.shard_capacity.is_some() {
+            shard.push_order(key, slot.created);
+        }
+    }
+}
+
+impl<K, V, S> Cache<K, V, S> {
+    /// Calls the eviction callback with the computed value of each evicted entry.
+    fn notify_evicted(&self, evicted: Vec<(K, Arc<Slot<V>>)>) {
+        let Some(on_evict) = &self.on_evict else {
+            return;
+        };
+        for (key, slot) in evicted {
+            if let SlotState::Ready(value) = &*slot.lock_state() {
+                on_evict(&key, value);
+            }
+        }
+    }
+}
+
+/// Builder for a [`Cache`] with a custom configuration.
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use cs431_homework::hello_server::Cache;
+///
+/// let cache = Cache::builder()
+///     .shards(4)
+///     .capacity(1024)
+///     .ttl(Duration::from_secs(60))
+///     .on_evict(|key: &u32, _: &String| println!("evicted {key}"))
+///     .build();
+/// assert_eq!(cache.get_or_insert_with(1, |key| key.to_string()), "1");
+/// ```
+pub struct CacheBuilder<K, V, S = RandomState> {
+    shards: usize,
+    capacity: Option<usize>,
+    ttl: Option<Duration>,
+    hasher: S,
+    on_evict: Option<EvictCallback<K, V>>,
+    _marker: PhantomData<(K, V)>,
+}
+
+impl<K, V> CacheBuilder<K, V> {
+    /// Creates a builder for an unbounded cache with one shard and a random hasher, which is the
+    /// configuration of `Cache::default()`.
+    pub fn new() -> Self {
+        Self {
+            shards: 1,
+            capacity: None,
+            ttl: None,
+            hasher: RandomState::new(),
+            on_evict: None,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<K, V> Default for CacheBuilder<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V, S> CacheBuilder<K, V, S> {
+    /// Sets the maximum number of entries. The capacity is split evenly across the shards, rounded
+    /// up, and the oldest entry of a full shard is evicted when a new key is inserted to it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is 0.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        assert!(capacity > 0, "Cache capacity must be positive");
+        self.capacity = Some(capacity);
+        self
+    }
+
+    /// Sets the time to live of an entry, counted from when its computation started. An expired
+    /// entry is evicted and recomputed when it is next requested.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Sets the number of shards.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `shards` is 0.
+    pub fn shards(mut self, shards: usize) -> Self {
+        assert!(shards > 0, "Cache must have at least one shard");
+        self.shards = shards;
+        self
+    }
+
+    /// Sets the hasher that partitions the keys into the shards.
+    pub fn hasher<S2: BuildHasher>(self, hasher: S2) -> CacheBuilder<K, V, S2> {
+        CacheBuilder {
+            shards: self.shards,
+            capacity: self.capacity,
+            ttl: self.ttl,
+            hasher,
+            on_evict: self.on_evict,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Sets the callback called with each entry evicted by the capacity or the TTL. It's not
+    /// called for the entries removed by `Cache::remove`.
+    ///
+    /// The callback is called without holding any lock of the cache.
+    pub fn on_evict(
+        mut self,
+        on_evict: impl Fn(&K, &V) + Send + Sync + RefUnwindSafe + 'static,
+    ) -> Self {
+        self.on_evict = Some(Box::new(on_evict));
+        self
+    }
+
+    /// Builds the configured cache.
+    pub fn build(self) -> Cache<K, V, S> {
+        Cache {
+            shards: (0..self.shards)
+                .map(|_| RwLock::new(ShardMap::default()))
+                .collect(),
+            hasher: self.hasher,
+            shard_capacity: self.capacity.map(|capacity| capacity.div_ceil(self.shards)),
+            ttl: self.ttl,
+            on_evict: self.on_evict,
+            in_flight: AtomicUsize::new(0),
+            quiescers: AtomicUsize::new(0),
+            idle_lock: Mutex::new(()),
+            idle: Condvar::new(),
+        }
+    }
+}
+
+impl<K, V, S> fmt::Debug for CacheBuilder<K, V, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CacheBuilder")
+            .field("shards", &self.shards)
+            .field("capacity", &self.capacity)
+            .field("ttl", &self.ttl)
+            .field("on_evict", &self.on_evict.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(all(test, not(feature = "check-loom")))]
+mod test {
+    use std::sync::mpsc::sync_channel;
+    use std::thread::scope;
+
+    use super::*;
+
+    #[test]
+    fn waiters_wake_once() {
+        const WAITERS: usize = 32;
+
+        let cache = Cache::<usize, usize>::default();
+        let hash = cache.hash_key(&1);
+        let (started_sender, started_receiver) = sync_channel(0);
+        let (quit_sender, quit_receiver) = sync_channel::<()>(0);
+        scope(|s| {
+            let cache = &cache;
+            let _ = s.spawn(move || {
+                cache.get_or_insert_with(1, |k| {
+                    started_sender.send(()).unwrap();
+                    quit_receiver.recv().unwrap();
+                    k * 10
+                })
+            });
+            started_receiver.recv().unwrap();
+            let slot = Arc::clone(
+                cache
+                    .shard(hash)
+                    .read()
+                    .unwrap()
+                    .get(&(hash, &1usize) as &dyn HashedRef<usize>)
+                    .unwrap(),
+            );
+
+            let waiters = (0..WAITERS)
+                .map(|_| s.spawn(move || cache.get_or_insert_with(1, |_| panic!())))
+                .collect::<Vec<_>>();
+            // 让等待者都阻塞在slot上
+            thread::sleep(Duration::from_millis(100));
+            quit_sender.send(()).unwrap();
+
+            for waiter in waiters {
+                assert_eq!(waiter.join().unwrap(), 10);
+            }
+            // 每个等待者最多被唤醒一次，且不会在值计算完之前被唤醒
+            assert!(slot.wakeups.load(Relaxed) <= WAITERS);
+            assert_eq!(slot.early_wakeups.load(Relaxed), 0);
+        });
+    }
 }
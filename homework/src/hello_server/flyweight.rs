@@ -0,0 +1,59 @@
+//! Factory of shared values.
+
+use std::hash::Hash;
+
+use super::cache::Cache;
+use crate::{Arc, Weak};
+
+/// Factory that shares a single value among all the users of equal keys, e.g. interned strings or
+/// the glyphs of a font.
+///
+/// The values are cached as [`Weak`] pointers, so a value is dropped as soon as no [`Arc`] to it
+/// remains outside the factory, and is created again on the next request. Only the entry for the
+/// key, with the allocation of the value, is kept until then.
+///
+/// ```
+/// use cs431_homework::hello_server::Flyweight;
+/// use cs431_homework::Arc;
+///
+/// let glyphs = Flyweight::default();
+/// let a = glyphs.get('a', |c| c.to_string());
+/// assert!(Arc::ptr_eq(&a, &glyphs.get('a', |c| c.to_string())));
+/// ```
+#[derive(Debug)]
+pub struct Flyweight<K, V> {
+    cache: Cache<K, Weak<V>>,
+}
+
+impl<K, V> Default for Flyweight<K, V> {
+    fn default() -> Self {
+        Self {
+            cache: Cache::default(),
+        }
+    }
+}
+
+impl<K: Eq + Hash + Clone, V> Flyweight<K, V> {
+    /// Returns the shared value for `key`, or creates it with `create` if no `Arc` to it remains.
+    ///
+    /// Like [`Cache::get_or_insert_with`], the concurrent invocations for `key` wait for a single
+    /// call to `create`, and all of them get the same value.
+    pub fn get(&self, key: K, create: impl FnOnce(&K) -> V) -> Arc<V> {
+        let mut create = Some(create);
+        loop {
+            let mut created = None;
+            let value = self.cache.get_or_insert_with(key.clone(), |key| {
+                // 在这次调用返回之前一直持有Arc，等待的线程一定能upgrade
+                let value = Arc::new(create.take().unwrap()(&key));
+                let weak = Arc::downgrade(&value);
+                created = Some(value);
+                weak
+            });
+            if let Some(value) = created.or_else(|| value.upgrade()) {
+                return value;
+            }
+            // 只删除已经失效的entry，其他线程可能已经换成了新的值
+            let _ = self.cache.remove_if(&key, |weak| weak.strong_count() == 0);
+        }
+    }
+}
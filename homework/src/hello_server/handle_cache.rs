@@ -0,0 +1,40 @@
+//! Cache of shared handles.
+
+use std::hash::Hash;
+
+use super::cache::Cache;
+use crate::Arc;
+
+/// Cache of reference-counted handles, e.g. open files or connections.
+///
+/// All the callers of [`get_or_open`](HandleCache::get_or_open) with the same key share the same
+/// handle, which is opened at most once until it is [`close`](HandleCache::close)d.
+#[derive(Debug)]
+pub struct HandleCache<K, V> {
+    cache: Cache<K, Arc<V>>,
+}
+
+impl<K, V> Default for HandleCache<K, V> {
+    fn default() -> Self {
+        Self {
+            cache: Cache::default(),
+        }
+    }
+}
+
+impl<K: Eq + Hash + Clone, V> HandleCache<K, V> {
+    /// Returns the handle for `key`, opening it with `open` if it is not open yet.
+    ///
+    /// Like [`Cache::get_or_insert_with`], `open` is called at most once per key even for
+    /// concurrent invocations.
+    pub fn get_or_open<F: FnOnce(&K) -> V>(&self, key: K, open: F) -> Arc<V> {
+        self.cache
+            .get_or_insert_with(key, |key| Arc::new(open(&key)))
+    }
+
+    /// Removes the handle for `key` from the cache. The handle itself is dropped once all the
+    /// `Arc`s returned by `get_or_open` are dropped. Returns whether the handle was open.
+    pub fn close(&self, key: &K) -> bool {
+        self.cache.remove(key)
+    }
+}
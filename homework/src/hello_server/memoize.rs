@@ -0,0 +1,136 @@
+//! Memoization of recursive functions.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::hash::Hash;
+#[cfg(not(feature = "check-loom"))]
+use std::sync::Mutex;
+use std::thread::{self, ThreadId};
+
+#[cfg(feature = "check-loom")]
+use loom::sync::Mutex;
+
+use super::cache::Cache;
+
+/// Error returned by [`Memoize::get`] when the value of a key depends on itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CycleError<K> {
+    /// The key that was requested again while its value was being computed.
+    pub key: K,
+}
+
+impl<K: fmt::Debug> fmt::Display for CycleError<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "cyclic dependency on key {:?}", self.key)
+    }
+}
+
+impl<K: fmt::Debug> Error for CycleError<K> {}
+
+/// Result of a memoized function.
+type MemoResult<K, V> = Result<V, CycleError<K>>;
+
+/// Recursive function memoized by [`Memoize`].
+type MemoFn<K, V> = Box<dyn Fn(&Memoize<K, V>, K) -> MemoResult<K, V> + Send + Sync>;
+
+/// Thread-safe memoization of a recursive function, whose value for a key is computed by calling
+/// [`get`](Memoize::get) for the other keys.
+///
+/// The values are stored in a [`Cache`], so the value of each key is computed only once even if
+/// it is requested by many threads concurrently. But [`Cache::get_or_insert_with`] waits for the
+/// value being computed, so a computation that requests its own key would wait for itself
+/// forever. To prevent this, each thread's keys in progress are tracked, and requesting one of
+/// them fails with a [`CycleError`]. The error is cached like a value, as the same cycle is found
+/// again on recomputation.
+///
+/// A cycle that spans multiple threads (e.g. a thread computing `a` requests `b`, while another
+/// thread computing `b` requests `a`) is not detected and deadlocks.
+///
+/// # Examples
+///
+/// ```
+/// use cs431_homework::hello_server::Memoize;
+///
+/// let fib = Memoize::new(|fib, n: u64| {
+///     Ok(if n < 2 { n } else { fib.get(n - 1)? + fib.get(n - 2)? })
+/// });
+/// assert_eq!(fib.get(90), Ok(2880067194370816120));
+/// ```
+pub struct Memoize<K, V> {
+    cache: Cache<K, MemoResult<K, V>>,
+    f: MemoFn<K, V>,
+    /// Keys being computed by each thread, from the outermost to the innermost.
+    in_progress: Mutex<HashMap<ThreadId, Vec<K>>>,
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for Memoize<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Memoize")
+            .field("cache", &self.cache)
+            .field("in_progress", &self.in_progress)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> Memoize<K, V> {
+    /// Memoizes `f`, which computes the value of a key, calling `get` on its first argument for
+    /// the values of the other keys it depends on.
+    pub fn new(f: impl Fn(&Self, K) -> MemoResult<K, V> + Send + Sync + 'static) -> Self {
+        Self {
+            cache: Cache::default(),
+            f: Box::new(f),
+            in_progress: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the value of `key`, computing it if it is not computed yet. Returns `Err` if `key`
+    /// is already being computed by the current thread, or its value depends on such a key.
+    pub fn get(&self, key: K) -> MemoResult<K, V> {
+        let thread = thread::current().id();
+        if self
+            .in_progress
+            .lock()
+            .unwrap()
+            .get(&thread)
+            .is_some_and(|keys| keys.contains(&key))
+        {
+            return Err(CycleError { key });
+        }
+        self.cache.get_or_insert_with(key, |key| {
+            let _frame = Frame::push(self, thread, key.clone());
+            (self.f)(self, key)
+        })
+    }
+}
+
+/// Marks a key as in progress in the current thread until dropped, even if the computation
+/// panics.
+struct Frame<'m, K: Eq + Hash, V> {
+    memoize: &'m Memoize<K, V>,
+    thread: ThreadId,
+}
+
+impl<'m, K: Eq + Hash, V> Frame<'m, K, V> {
+    fn push(memoize: &'m Memoize<K, V>, thread: ThreadId, key: K) -> Self {
+        memoize
+            .in_progress
+            .lock()
+            .unwrap()
+            .entry(thread)
+            .or_default()
+            .push(key);
+        Frame { memoize, thread }
+    }
+}
+
+impl<K: Eq + Hash, V> Drop for Frame<'_, K, V> {
+    fn drop(&mut self) {
+        let mut in_progress = self.memoize.in_progress.lock().unwrap();
+        let keys = in_progress.get_mut(&self.thread).unwrap();
+        let _ = keys.pop();
+        if keys.is_empty() {
+            let _ = in_progress.remove(&self.thread);
+        }
+    }
+}
@@ -2,13 +2,21 @@
 #![deny(unsafe_code)]
 
 mod cache;
+mod flyweight;
+mod handle_cache;
 mod handler;
+mod memoize;
+mod result_cache;
 mod statistics;
 mod tcp;
 mod thread_pool;
 
-pub use cache::Cache;
+pub use cache::{Cache, CacheBuilder};
+pub use flyweight::Flyweight;
+pub use handle_cache::HandleCache;
 pub use handler::Handler;
+pub use memoize::{CycleError, Memoize};
+pub use result_cache::ResultCache;
 pub use statistics::{Report, Statistics};
 pub use tcp::CancellableTcpListener;
 pub use thread_pool::ThreadPool;
@@ -0,0 +1,40 @@
+//! Cache of fallible computations.
+
+use std::hash::Hash;
+
+use super::cache::Cache;
+
+/// Cache that remembers only the successful results of a fallible computation, e.g. a fetch from
+/// a remote server.
+///
+/// Like [`Cache`], the computation runs at most once at a time for each key, and the concurrent
+/// invocations for the key wait for it. If it fails, all of them get the error, but the error is
+/// not cached, so the next invocation retries.
+#[derive(Debug)]
+pub struct ResultCache<K, V, E> {
+    cache: Cache<K, Result<V, E>>,
+}
+
+impl<K, V, E> Default for ResultCache<K, V, E> {
+    fn default() -> Self {
+        Self {
+            cache: Cache::default(),
+        }
+    }
+}
+
+impl<K: Eq + Hash + Clone, V: Clone, E: Clone> ResultCache<K, V, E> {
+    /// Retrieves the value or computes it with `f`. `Ok` results are cached, and `Err` results are
+    /// returned to the invocations waiting for the same computation but not cached.
+    ///
+    /// See [`Cache::get_or_insert_with`].
+    pub fn get_or_insert_with<F: FnOnce(K) -> Result<V, E>>(&self, key: K, f: F) -> Result<V, E> {
+        self.cache.get_or_insert_with_if(key, f, Result::is_ok)
+    }
+
+    /// Removes the cached value for `key`, so that the next `get_or_insert_with` recomputes it.
+    /// Returns whether the value was cached.
+    pub fn remove(&self, key: &K) -> bool {
+        self.cache.remove(key)
+    }
+}
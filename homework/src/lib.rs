@@ -6,10 +6,14 @@
 #![allow(dead_code, unused_variables, unused_imports, unused_mut)]
 #![deny(unsafe_op_in_unsafe_fn, warnings)]
 
+#[macro_use]
+mod trace;
+
 mod adt;
 mod arc;
 pub mod boc;
 mod elim_stack;
+mod error;
 mod hash_table;
 pub mod hazard_pointer;
 pub mod hello_server;
@@ -19,9 +23,26 @@ mod list_set;
 pub mod test;
 
 pub use adt::{ConcurrentMap, ConcurrentSet};
-pub use arc::Arc;
+pub use arc::{
+    Arc, ArcLinkedList, ArcListNode, ArcStack, AtomicArc, CancellationToken, Observer,
+    ObserverRegistry, SharedLatest, Weak,
+};
 pub use boc::CownPtr;
 pub use elim_stack::ElimStack;
-pub use hash_table::{GrowableArray, SplitOrderedList};
+pub use error::ConcurrencyError;
+pub use hash_table::{
+    Batch, ConcurrentBitmap, ConcurrentHistogram, ConcurrentLru, FrozenArray, GrowableArray,
+    GrowableArrayBuilder, GrowableArrayCursor, PathStats, SignedGrowableArray, Slab,
+    SplitOrderedList,
+};
 pub use linked_list::LinkedList;
-pub use list_set::{FineGrainedListSet, OptimisticFineGrainedListSet};
+#[cfg(feature = "lock-metrics")]
+pub use list_set::LockWaitStats;
+#[cfg(feature = "op-log")]
+pub use list_set::{dump_op_log, OpRecord};
+pub use list_set::{
+    BlockingSortedQueue, Decode, Encode, FineGrainedCursor, FineGrainedListSet,
+    FineGrainedTransaction, OptimisticFineGrainedListSet, OwningIter, ReadOnlyView,
+    SequentialListSet, SkipListSet, SlidingWindowLimiter, SortedSetExt, UnrolledListSet,
+    VersionedListSet,
+};
@@ -0,0 +1,107 @@
+use std::cmp::Ordering::*;
+use std::ops::RangeBounds;
+use std::ptr;
+
+use super::FineGrainedListSet;
+
+/// Operations on sorted sets that exploit the order of the elements.
+///
+/// Each set operation is a single merge pass over consistent snapshots of the two sets, and
+/// returns the result in ascending order.
+///
+/// # Examples
+///
+/// ```
+/// use cs431_homework::{ConcurrentSet, FineGrainedListSet, SortedSetExt};
+///
+/// let a = FineGrainedListSet::new();
+/// let b = FineGrainedListSet::new();
+/// for i in [1, 2, 3] {
+///     a.insert(i);
+/// }
+/// for i in [2, 3, 4] {
+///     b.insert(i);
+/// }
+/// assert_eq!(a.intersection(&b), [2, 3]);
+/// assert_eq!(a.symmetric_difference(&b), [1, 4]);
+/// ```
+pub trait SortedSetExt<T> {
+    /// Returns the elements in both `self` and `other`.
+    fn intersection(&self, other: &Self) -> Vec<T>;
+
+    /// Returns the elements in exactly one of `self` and `other`.
+    fn symmetric_difference(&self, other: &Self) -> Vec<T>;
+
+    /// Removes all the elements in `range` in a single pass, and returns the number of the removed
+    /// elements.
+    ///
+    /// The predecessor of the range is locked until all the elements in the range are unlinked, so
+    /// an element inserted concurrently just outside of the range is never lost, and no element is
+    /// inserted into the range meanwhile.
+    fn remove_range<R: RangeBounds<T>>(&self, range: R) -> usize;
+}
+
+impl<T: Ord + Clone> FineGrainedListSet<T> {
+    /// Calls `merge` with the snapshots of `self` and `other`, which are taken at the same time.
+    fn with_snapshots<R>(&self, other: &Self, merge: impl FnOnce(&[&T], &[&T]) -> R) -> R {
+        if ptr::eq(self, other) {
+            return self.with_snapshot(|elements| merge(elements, elements));
+        }
+        // 按地址顺序加锁，两个线程以相反的顺序对同一对集合调用时不会死锁
+        if (self as *const Self) < (other as *const Self) {
+            self.with_snapshot(|xs| other.with_snapshot(|ys| merge(xs, ys)))
+        } else {
+            other.with_snapshot(|ys| self.with_snapshot(|xs| merge(xs, ys)))
+        }
+    }
+}
+
+impl<T: Ord + Clone> SortedSetExt<T> for FineGrainedListSet<T> {
+    fn intersection(&self, other: &Self) -> Vec<T> {
+        self.with_snapshots(other, |xs, ys| {
+            let (mut i, mut j) = (0, 0);
+            let mut result = Vec::new();
+            while i < xs.len() && j < ys.len() {
+                match xs[i].cmp(ys[j]) {
+                    Less => i += 1,
+                    Greater => j += 1,
+                    Equal => {
+                        result.push(xs[i].clone());
+                        i += 1;
+                        j += 1;
+                    }
+                }
+            }
+            result
+        })
+    }
+
+    fn symmetric_difference(&self, other: &Self) -> Vec<T> {
+        self.with_snapshots(other, |xs, ys| {
+            let (mut i, mut j) = (0, 0);
+            let mut result = Vec::new();
+            while i < xs.len() && j < ys.len() {
+                match xs[i].cmp(ys[j]) {
+                    Less => {
+                        result.push(xs[i].clone());
+                        i += 1;
+                    }
+                    Greater => {
+                        result.push(ys[j].clone());
+                        j += 1;
+                    }
+                    Equal => {
+                        i += 1;
+                        j += 1;
+                    }
+                }
+            }
+            result.extend(xs[i..].iter().chain(&ys[j..]).map(|&x| x.clone()));
+            result
+        })
+    }
+
+    fn remove_range<R: RangeBounds<T>>(&self, range: R) -> usize {
+        FineGrainedListSet::remove_range(self, &range)
+    }
+}
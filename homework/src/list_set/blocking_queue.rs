@@ -0,0 +1,142 @@
+//! Blocking priority queue on top of `FineGrainedListSet`.
+
+use std::sync::{Condvar, Mutex};
+use std::time::Duration;
+
+use super::FineGrainedListSet;
+use crate::{ConcurrencyError, ConcurrentSet};
+
+#[derive(Debug)]
+struct State {
+    /// Number of elements that are inserted but not yet claimed by a consumer.
+    len: usize,
+    /// Number of pushes that are inserting into the set, counted against the capacity.
+    pending: usize,
+    capacity: usize,
+    closed: bool,
+}
+
+/// Concurrent priority queue whose `pop_min_blocking` blocks until an element is available.
+///
+/// Elements are kept in a [`FineGrainedListSet`], so duplicate elements are rejected. A queue
+/// created with `bounded` rejects pushes beyond its capacity.
+#[derive(Debug)]
+pub struct BlockingSortedQueue<T> {
+    set: FineGrainedListSet<T>,
+    state: Mutex<State>,
+    not_empty: Condvar,
+}
+
+impl<T> Default for BlockingSortedQueue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> BlockingSortedQueue<T> {
+    /// Creates a new empty queue.
+    pub fn new() -> Self {
+        Self::bounded(usize::MAX)
+    }
+
+    /// Creates a new empty queue that holds at most `capacity` elements.
+    pub fn bounded(capacity: usize) -> Self {
+        Self {
+            set: FineGrainedListSet::new(),
+            state: Mutex::new(State {
+                len: 0,
+                pending: 0,
+                capacity,
+                closed: false,
+            }),
+            not_empty: Condvar::new(),
+        }
+    }
+
+    /// Closes the queue. All the waiters in `pop_min_blocking` are woken up, and it fails once the
+    /// remaining elements are drained.
+    pub fn close(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.closed = true;
+        self.not_empty.notify_all();
+    }
+
+    /// Removes the smallest element, blocking until one is available.
+    ///
+    /// Returns `Err(())` if the queue is closed and empty.
+    pub fn pop_min_blocking(&self) -> Result<T, ()> {
+        let state = self.state.lock().unwrap();
+        // `wait_while` re-checks the condition on spurious wakeups.
+        let mut state = self
+            .not_empty
+            .wait_while(state, |state| state.len == 0 && !state.closed)
+            .unwrap();
+        if state.len == 0 {
+            return Err(());
+        }
+        // Claim an element so that no other consumer takes it.
+        state.len -= 1;
+        drop(state);
+        Ok(self
+            .set
+            .pop_min()
+            .expect("a claimed element must be in the set"))
+    }
+
+    /// Removes the smallest element, blocking until one is available or `timeout` expires.
+    ///
+    /// Returns `Ok(None)` if the queue is closed and empty, and `Err(Timeout)` if no element
+    /// became available in time.
+    pub fn pop_min_timeout(&self, timeout: Duration) -> Result<Option<T>, ConcurrencyError> {
+        let state = self.state.lock().unwrap();
+        let (mut state, result) = self
+            .not_empty
+            .wait_timeout_while(state, timeout, |state| state.len == 0 && !state.closed)
+            .unwrap();
+        if result.timed_out() {
+            return Err(ConcurrencyError::Timeout);
+        }
+        if state.len == 0 {
+            return Ok(None);
+        }
+        state.len -= 1;
+        drop(state);
+        Ok(Some(
+            self.set
+                .pop_min()
+                .expect("a claimed element must be in the set"),
+        ))
+    }
+}
+
+impl<T: Ord> BlockingSortedQueue<T> {
+    /// Pushes the value, waking up a waiter. Returns `false` if the value is already in the queue,
+    /// the queue is closed, or the queue is full.
+    pub fn push(&self, value: T) -> bool {
+        self.try_push(value).unwrap_or(false)
+    }
+
+    /// Pushes the value, waking up a waiter. Returns `Ok(false)` if the value is already in the
+    /// queue or the queue is closed, and `Err(Full)` if the queue is at its capacity.
+    pub fn try_push(&self, value: T) -> Result<bool, ConcurrencyError> {
+        {
+            let mut state = self.state.lock().unwrap();
+            if state.closed {
+                return Ok(false);
+            }
+            // 先占一个位置，避免并发的push一起超过容量
+            if state.len + state.pending >= state.capacity {
+                return Err(ConcurrencyError::Full);
+            }
+            state.pending += 1;
+        }
+        let inserted = self.set.insert(value);
+        let mut state = self.state.lock().unwrap();
+        state.pending -= 1;
+        if inserted {
+            state.len += 1;
+            self.not_empty.notify_one();
+        }
+        Ok(inserted)
+    }
+}
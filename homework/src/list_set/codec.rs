@@ -0,0 +1,40 @@
+//! Minimal binary encoding for the elements of list sets.
+
+use core::mem::size_of;
+
+/// Types that can be encoded into a flat buffer.
+pub trait Encode {
+    /// Appends the encoding of `self` to `buf`.
+    fn encode(&self, buf: &mut Vec<u8>);
+}
+
+/// Types that can be decoded from a flat buffer.
+pub trait Decode: Sized {
+    /// Decodes a value from the front of `buf`, and returns it with the rest of the buffer.
+    ///
+    /// Returns `Err(())` if `buf` doesn't start with a valid encoding.
+    fn decode(buf: &[u8]) -> Result<(Self, &[u8]), ()>;
+}
+
+/// Integers are encoded in little endian.
+macro_rules! impl_codec_for_int {
+    ($($t:ty),*) => {$(
+        impl Encode for $t {
+            fn encode(&self, buf: &mut Vec<u8>) {
+                buf.extend_from_slice(&self.to_le_bytes());
+            }
+        }
+
+        impl Decode for $t {
+            fn decode(buf: &[u8]) -> Result<(Self, &[u8]), ()> {
+                if buf.len() < size_of::<$t>() {
+                    return Err(());
+                }
+                let (bytes, rest) = buf.split_at(size_of::<$t>());
+                Ok((<$t>::from_le_bytes(bytes.try_into().unwrap()), rest))
+            }
+        }
+    )*};
+}
+
+impl_codec_for_int!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
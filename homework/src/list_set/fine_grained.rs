@@ -1,19 +1,47 @@
 use std::cmp::Ordering::*;
-use std::sync::{Mutex, MutexGuard};
+use std::fmt::{self, Debug};
+use std::ops::{Bound, RangeBounds};
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::Relaxed;
+use std::sync::{Mutex, PoisonError, TryLockError};
 use std::{mem, ptr};
 
-use crate::ConcurrentSet;
+use super::codec::{Decode, Encode};
+use super::lock_metrics::{HeldLock, LockLeakCheck, Op, Recorder};
+#[cfg(feature = "lock-metrics")]
+use super::lock_metrics::{LockMetrics, LockWaitStats};
+#[cfg(feature = "op-log")]
+use super::op_log;
+use super::{SequentialListSet, SkipListSet};
+use crate::{ConcurrencyError, ConcurrentSet};
 
+/// Node of `FineGrainedListSet`. With the `padded-nodes` feature, each node is aligned to a cache
+/// line, so that the lock of a node doesn't share a cache line with an adjacent allocation, e.g. a
+/// node locked by another thread.
 #[derive(Debug)]
-struct Node<T> {
-    data: T,
-    next: Mutex<*mut Node<T>>,
+#[cfg_attr(feature = "padded-nodes", repr(align(64)))]
+pub(super) struct Node<T> {
+    pub(super) data: T,
+    pub(super) next: Mutex<*mut Node<T>>,
 }
 
 /// Concurrent sorted singly linked list using fine-grained lock-coupling.
 #[derive(Debug)]
 pub struct FineGrainedListSet<T> {
     head: Mutex<*mut Node<T>>,
+    /// Number of the linked nodes, counted to enforce `max_len`. Incremented before a node is
+    /// linked and decremented after a node is unlinked, so it is never less than the actual
+    /// length. Not counted if the set is unbounded, see `is_bounded`.
+    len: AtomicUsize,
+    max_len: usize,
+    /// Held by the running transaction, so that the transactions run one at a time. See
+    /// `transaction`.
+    transactions: Mutex<()>,
+    #[cfg(feature = "lock-metrics")]
+    metrics: LockMetrics,
+    /// Formats the keys for the op log, or `None` if the operations are not logged.
+    #[cfg(feature = "op-log")]
+    key_fmt: Option<fn(&T) -> String>,
 }
 
 unsafe impl<T: Send> Send for FineGrainedListSet<T> {}
@@ -27,13 +55,13 @@ unsafe impl<T: Send> Sync for FineGrainedListSet<T> {}
 /// head -> 1 -> 2 -> 3 -> null
 /// ```
 ///
-/// If `cursor` is currently at node 2, then `cursor.0` should be the `MutexGuard` obtained from the
-/// `next` of node 1. In particular, `cursor.0.as_ref().unwrap()` creates a shared reference to node
-/// 2.
-struct Cursor<'l, T>(MutexGuard<'l, *mut Node<T>>);
+/// If `cursor` is currently at node 2, then `cursor.0` should be the guard of the lock obtained
+/// from the `next` of node 1. In particular, `cursor.0.as_ref().unwrap()` creates a shared
+/// reference to node 2.
+struct Cursor<'l, T>(HeldLock<'l, *mut Node<T>>);
 
 impl<T> Node<T> {
-    fn new(data: T, next: *mut Self) -> *mut Self {
+    pub(super) fn new(data: T, next: *mut Self) -> *mut Self {
         Box::into_raw(Box::new(Self {
             data,
             next: Mutex::new(next),
@@ -41,101 +69,831 @@ impl<T> Node<T> {
     }
 }
 
+impl<'l, T> Cursor<'l, T> {
+    /// Creates a cursor at the first node of the list for the operation `op`.
+    fn head(set: &'l FineGrainedListSet<T>, op: Op) -> Self {
+        Self(set.recorder(op).lock(&set.head))
+    }
+}
+
 impl<T: Ord> Cursor<'_, T> {
     /// Moves the cursor to the position of key in the sorted list.
     /// Returns whether the value was found.
     fn find(&mut self, key: &T) -> bool {
+        self.find_predecessor(key, ptr::null()).0
+    }
+
+    /// Moves the cursor like `find`, and also returns the data of the node whose `next` the cursor
+    /// locks, i.e. the predecessor of the position, or `prev` if the cursor didn't move. The node
+    /// is not removed while its `next` is locked, so the data is valid as long as the cursor stays
+    /// there.
+    fn find_predecessor(&mut self, key: &T, mut prev: *const T) -> (bool, *const T) {
         // 返回最后一个小于等于key的节点
         while let Some(mut next_node) = unsafe { self.0.as_mut() } {
             let mut next_key = &next_node.data;
             match next_key.cmp(key) {
                 Equal => {
-                    return true;
+                    return (true, prev);
                 }
                 Less => {
-                    self.0 = next_node.next.lock().unwrap();
+                    self.0 = self.0.lock_next(&next_node.next);
+                    prev = next_key;
                 }
                 Greater => {
                     break;
                 }
             }
         }
-        false
+        (false, prev)
+    }
+
+    /// Inserts the key at the current position of the cursor, and moves the cursor to the inserted
+    /// node.
+    fn insert(&mut self, key: T) {
+        *self.0 = Node::new(key, *self.0);
     }
 }
 
 impl<T> FineGrainedListSet<T> {
     /// Creates a new list.
     pub fn new() -> Self {
+        Self::with_max_len(usize::MAX)
+    }
+
+    /// Creates a new list that holds at most `max_len` elements. Once the list is full, the
+    /// insertions of new keys fail until an element is removed.
+    pub fn with_max_len(max_len: usize) -> Self {
         Self {
             head: Mutex::new(ptr::null_mut()),
+            len: AtomicUsize::new(0),
+            max_len,
+            transactions: Mutex::new(()),
+            #[cfg(feature = "lock-metrics")]
+            metrics: LockMetrics::new(),
+            #[cfg(feature = "op-log")]
+            key_fmt: None,
+        }
+    }
+
+    /// Records the operations on a key (e.g. `insert`) of the set in the op log, which is dumped
+    /// with [`dump_op_log`](crate::dump_op_log).
+    #[cfg(feature = "op-log")]
+    pub fn log_ops(mut self) -> Self
+    where
+        T: Debug,
+    {
+        self.key_fmt = Some(op_log::format_key::<T>);
+        self
+    }
+
+    /// Formats `key` for the op log, or returns `None` if the operations are not logged.
+    fn op_key(&self, key: &T) -> Option<String> {
+        #[cfg(feature = "op-log")]
+        return self.key_fmt.map(|key_fmt| key_fmt(key));
+        #[cfg(not(feature = "op-log"))]
+        None
+    }
+
+    /// Records an operation on the key formatted by `op_key` in the op log, and returns `result`.
+    ///
+    /// Called before the operation releases its locks, so that the operations conflicting on the
+    /// same nodes are recorded in the order they took effect.
+    fn log_op(op: &'static str, key: Option<String>, result: bool) -> bool {
+        #[cfg(feature = "op-log")]
+        if let Some(key) = key {
+            op_log::record(op, key, result);
+        }
+        result
+    }
+
+    /// Returns the recorder of the lock waits of the operation `op`.
+    fn recorder(&self, op: Op) -> Recorder<'_> {
+        #[cfg(feature = "lock-metrics")]
+        let metrics = &self.metrics;
+        #[cfg(not(feature = "lock-metrics"))]
+        let metrics = &();
+        Recorder::new(metrics, op)
+    }
+
+    /// Returns the histograms of the time the operations waited for the locks so far.
+    #[cfg(feature = "lock-metrics")]
+    pub fn lock_wait_stats(&self) -> LockWaitStats {
+        self.metrics.stats()
+    }
+
+    /// Returns whether the set is bounded by `max_len`. Only then `len` is counted, so that the
+    /// operations on an unbounded set don't contend on the shared counter.
+    fn is_bounded(&self) -> bool {
+        self.max_len != usize::MAX
+    }
+
+    /// Uncounts `n` unlinked nodes from `len`.
+    fn uncount(&self, n: usize) {
+        if self.is_bounded() {
+            let _ = self.len.fetch_sub(n, Relaxed);
+        }
+    }
+
+    /// Unlinks and returns the node at the cursor, which must not be at the end of the list.
+    fn unlink(&self, cursor: &mut Cursor<'_, T>) -> Box<Node<T>> {
+        // 持有前一个节点的锁，等经过目标节点的线程离开后再摘除
+        let node = unsafe { Box::from_raw(*cursor.0) };
+        *cursor.0 = *cursor.0.lock_next(&node.next);
+        self.uncount(1);
+        node
+    }
+}
+
+impl<T: Ord> FineGrainedListSet<T> {
+    /// Inserts the key at the cursor unless the list is full, and returns whether it was inserted.
+    fn link(&self, cursor: &mut Cursor<'_, T>, key: T) -> bool {
+        // 先占一个名额再插入，并发插入不会超过上限
+        if self.is_bounded()
+            && self
+                .len
+                .fetch_update(Relaxed, Relaxed, |len| {
+                    (len < self.max_len).then_some(len + 1)
+                })
+                .is_err()
+        {
+            return false;
+        }
+        cursor.insert(key);
+        true
+    }
+}
+
+impl<T> FineGrainedListSet<T> {
+    /// Retains only the elements satisfying `pred`, in a single pass from the head.
+    ///
+    /// `pred` is called on each element in ascending order while its predecessor is locked, so the
+    /// elements inserted concurrently behind the pass are not visited.
+    pub fn retain(&self, pred: impl FnMut(&T) -> bool) {
+        let _ = self.retain_count(pred);
+    }
+
+    /// Retains only the elements satisfying `pred` like `retain`, and returns the number of the
+    /// removed elements, e.g. to detect a retain that removed nothing.
+    pub fn retain_count(&self, mut pred: impl FnMut(&T) -> bool) -> usize {
+        let _check = LockLeakCheck::new();
+        let mut cur = Cursor::head(self, Op::Remove);
+        let mut removed = 0;
+        while let Some(node) = unsafe { cur.0.as_ref() } {
+            if pred(&node.data) {
+                cur.0 = cur.0.lock_next(&node.next);
+            } else {
+                drop(self.unlink(&mut cur));
+                removed += 1;
+            }
+        }
+        removed
+    }
+}
+
+impl<T> FineGrainedListSet<T> {
+    /// Removes and returns the smallest element, or `None` if the list is empty.
+    pub fn pop_min(&self) -> Option<T> {
+        let _check = LockLeakCheck::new();
+        let mut head = Cursor::head(self, Op::Remove);
+        if head.0.is_null() {
+            return None;
         }
+        // 持有head的锁时，其他线程只能在第一个节点之后，等它们离开后再摘除第一个节点
+        Some(self.unlink(&mut head).data)
+    }
+}
+
+impl<T: Ord> FineGrainedListSet<T> {
+    /// Inserts `value` at the front if it is smaller than all the elements, e.g. for inserting
+    /// keys in descending order. Returns whether it was inserted.
+    ///
+    /// Only `head` is locked, without searching for the position. Returns `false` if `value` is
+    /// not smaller than the current smallest element, including when it is already in the set,
+    /// or if the set is full.
+    pub fn push_front(&self, value: T) -> bool {
+        let _check = LockLeakCheck::new();
+        let logged = self.op_key(&value);
+        let mut head = Cursor::head(self, Op::Insert);
+        // head被锁住时第一个节点不会被删除
+        let inserted = !unsafe { head.0.as_ref() }.is_some_and(|first| value >= first.data)
+            && self.link(&mut head, value);
+        Self::log_op("push_front", logged, inserted)
     }
 }
 
 impl<T: Ord> FineGrainedListSet<T> {
-    fn find(&self, key: &T) -> (bool, Cursor<'_, T>) {
-        let mut c = Cursor(self.head.lock().unwrap());
+    fn find(&self, key: &T, op: Op) -> (bool, Cursor<'_, T>) {
+        let mut c = Cursor::head(self, op);
         let found = c.find(key);
         (found, c)
     }
+
+    /// Removes the node equal to `key` if its data satisfies `pred`. Returns whether the node was
+    /// removed, and the cursor at its position with the data of its predecessor as in
+    /// `Cursor::find_predecessor`.
+    fn remove_at(&self, key: &T, pred: impl FnOnce(&T) -> bool) -> (bool, Cursor<'_, T>, *const T) {
+        let mut cur = Cursor::head(self, Op::Remove);
+        let (found, prev) = cur.find_predecessor(key, ptr::null());
+        // 找到时cur.0始终存在
+        let removed = found && pred(unsafe { &(**cur.0).data });
+        if removed {
+            // 释放目标节点的内存
+            drop(self.unlink(&mut cur));
+        }
+        (removed, cur, prev)
+    }
+
+    /// Removes the key like `remove`, and if it was removed, returns the cursor positioned at its
+    /// predecessor, so that subsequent insertions near the key don't traverse the list from the
+    /// head again.
+    ///
+    /// The cursor keeps the predecessor locked, so the other operations that pass through it block
+    /// until the cursor is dropped.
+    pub fn remove_returning_predecessor(
+        &self,
+        key: &T,
+    ) -> (bool, Option<FineGrainedCursor<'_, T>>) {
+        let (removed, cursor, predecessor) = self.remove_at(key, |_| true);
+        if !removed {
+            return (false, None);
+        }
+        let cursor = FineGrainedCursor {
+            cursor,
+            predecessor,
+            set: self,
+        };
+        (true, Some(cursor))
+    }
+
+    /// Removes the node equal to `key` only if its data satisfies `pred`. Returns whether the node
+    /// was removed.
+    ///
+    /// `pred` is called while the predecessor of the node is locked, so the node can't be removed
+    /// or replaced between the check and the removal. This is useful when `T` is ordered by only a
+    /// part of it, e.g. a key with a version, to remove only a specific version.
+    pub fn remove_if(&self, key: &T, pred: impl FnOnce(&T) -> bool) -> bool {
+        let _check = LockLeakCheck::new();
+        let logged = self.op_key(key);
+        let (removed, ..) = self.remove_at(key, pred);
+        Self::log_op("remove_if", logged, removed)
+    }
+}
+
+impl<T: Ord> FineGrainedListSet<T> {
+    /// Returns whether the set contains the key, like `contains`.
+    ///
+    /// Instead of waiting for a lock held by another thread (e.g. by an `iter` in progress), fails
+    /// with `Err(WouldBlock)`.
+    pub fn try_contains(&self, key: &T) -> Result<bool, ConcurrencyError> {
+        fn try_lock<'l, U>(
+            mutex: &'l Mutex<U>,
+            recorder: Recorder<'l>,
+        ) -> Result<HeldLock<'l, U>, ConcurrencyError> {
+            match mutex.try_lock() {
+                Ok(guard) => Ok(HeldLock::new(guard, recorder)),
+                Err(TryLockError::WouldBlock) => Err(ConcurrencyError::WouldBlock),
+                // 节点的链接在持锁期间不会处于中间状态，poison可以忽略
+                Err(TryLockError::Poisoned(e)) => Ok(HeldLock::new(e.into_inner(), recorder)),
+            }
+        }
+
+        let _check = LockLeakCheck::new();
+        let recorder = self.recorder(Op::Contains);
+        let mut cursor = try_lock(&self.head, recorder)?;
+        while let Some(node) = unsafe { cursor.as_ref() } {
+            match node.data.cmp(key) {
+                Equal => return Ok(true),
+                Less => cursor = try_lock(&node.next, recorder)?,
+                Greater => break,
+            }
+        }
+        Ok(false)
+    }
+}
+
+impl<T: Ord> FineGrainedListSet<T> {
+    /// Creates a list from the elements of `iter`, without searching for the position of each
+    /// element.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the elements are not in strictly ascending order.
+    pub fn from_sorted_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut set = Self::new();
+        let mut len = 0;
+        let mut prev: Option<&T> = None;
+        // 新的链表还没有共享，不需要加锁，直接接在尾部
+        let mut tail = set.head.get_mut().unwrap();
+        for data in iter {
+            if let Some(prev) = prev {
+                assert!(*prev < data, "elements must be in strictly ascending order");
+            }
+            let node = unsafe { &mut *Node::new(data, ptr::null_mut()) };
+            *tail = node;
+            prev = Some(&node.data);
+            tail = node.next.get_mut().unwrap();
+            len += 1;
+        }
+        *set.len.get_mut() = len;
+        set
+    }
+}
+
+impl<T: Ord> FineGrainedListSet<T> {
+    /// Replaces all the elements with the elements of `iter` at once, e.g. to publish a new
+    /// version of a configuration. The duplicates in `iter` are dropped.
+    ///
+    /// The new list is built without locking, and swapped in while `head` is locked. So an
+    /// operation that passed `head` before the swap sees only the old elements, and one that
+    /// passes it after sees only the new ones, never a mix of them. The old nodes are then freed
+    /// from the front with lock-coupling, behind the operations still traversing them. The new
+    /// elements are not bounded by `max_len`, but the insertions fail until the set shrinks below
+    /// it.
+    pub fn replace_all<I: IntoIterator<Item = T>>(&self, iter: I) {
+        let _check = LockLeakCheck::new();
+        let mut elements = iter.into_iter().collect::<Vec<_>>();
+        elements.sort_unstable();
+        elements.dedup();
+        let mut new = Self::from_sorted_iter(elements);
+        let first = mem::replace(new.head.get_mut().unwrap(), ptr::null_mut());
+
+        let recorder = self.recorder(Op::Remove);
+        let mut head = recorder.lock(&self.head);
+        if self.is_bounded() {
+            let _ = self.len.fetch_add(*new.len.get_mut(), Relaxed);
+        }
+        let mut next = mem::replace(&mut *head, first);
+        drop(head);
+        // 之后的操作都进入新链表，还在旧链表中的线程都在前面，锁上next等它们离开后再释放
+        while !next.is_null() {
+            // SAFETY: `next` is unreachable from the new list, and we have locked the link to it
+            // after the old head was replaced. By lock coupling, a traverser locks a link before
+            // unlocking the previous one, so every traverser still in the old list already holds
+            // the lock on `node.next` or is ahead of it. The node is freed only after we lock
+            // `node.next`, i.e. after the last one of them has moved on, and none can come back.
+            let node = unsafe { Box::from_raw(next) };
+            next = *recorder.lock(&node.next);
+            self.uncount(1);
+        }
+    }
+}
+
+impl<T> FineGrainedListSet<T> {
+    /// Converts the set into a [`SequentialListSet`] with the same elements, for single-threaded
+    /// use without the overhead of the per-node locks, e.g. after joining all the threads.
+    ///
+    /// The nodes are moved over as they are, without reallocating them.
+    pub fn into_sequential(mut self) -> SequentialListSet<T> {
+        let head = self.head.get_mut().unwrap_or_else(PoisonError::into_inner);
+        // head置空，之后Drop不会再访问交给新链表的节点
+        SequentialListSet::from_head(mem::replace(head, ptr::null_mut()))
+    }
+
+    /// Converts the set into a [`SkipListSet`] with the same elements, e.g. once the set has grown
+    /// large enough that the O(n) searches of the list dominate.
+    pub fn into_skip_list(self) -> SkipListSet<T>
+    where
+        T: Ord,
+    {
+        let set = SkipListSet::new();
+        self.drain_into(|data| {
+            let _ = set.insert(data);
+        });
+        set
+    }
+
+    /// Consumes the set and feeds the elements to `sink` in ascending order, freeing each node
+    /// before its element is fed.
+    ///
+    /// As the set is owned, no lock is taken.
+    pub fn drain_into<S: FnMut(T)>(self, sink: S) {
+        self.owning_iter().for_each(sink);
+    }
+
+    /// Consumes the set and returns an iterator that moves out the elements in ascending order,
+    /// freeing each node as its element is yielded, e.g. to feed a slow consumer without
+    /// collecting the elements first.
+    ///
+    /// As the set is owned, no lock is taken. The elements not yielded are dropped with the
+    /// iterator.
+    pub fn owning_iter(mut self) -> OwningIter<T> {
+        let head = self.head.get_mut().unwrap_or_else(PoisonError::into_inner);
+        // head置空，之后Drop不会再访问交给迭代器的节点
+        OwningIter {
+            next: mem::replace(head, ptr::null_mut()),
+        }
+    }
+
+    /// Consumes the set and builds a new set of `f` applied to each element in ascending order.
+    ///
+    /// If the mapped elements stay in strictly ascending order, they are linked in order as in
+    /// `from_sorted_iter`. Otherwise, they are sorted first, and the duplicates are dropped.
+    pub fn drain_map<U: Ord, F: FnMut(T) -> U>(self, mut f: F) -> FineGrainedListSet<U> {
+        let mut mapped = Vec::new();
+        let mut sorted = true;
+        self.drain_into(|data| {
+            let data = f(data);
+            sorted = sorted && !mapped.last().is_some_and(|last| *last >= data);
+            mapped.push(data);
+        });
+        if !sorted {
+            mapped.sort_unstable();
+            mapped.dedup();
+        }
+        FineGrainedListSet::from_sorted_iter(mapped)
+    }
+}
+
+/// Iterator that moves out the elements of a [`FineGrainedListSet`] in ascending order. See
+/// [`FineGrainedListSet::owning_iter`].
+#[derive(Debug)]
+pub struct OwningIter<T> {
+    /// The first node not yielded yet, owned by the iterator.
+    next: *mut Node<T>,
+}
+
+unsafe impl<T: Send> Send for OwningIter<T> {}
+unsafe impl<T: Sync> Sync for OwningIter<T> {}
+
+impl<T> Iterator for OwningIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.next.is_null() {
+            return None;
+        }
+        let Node { data, next } = *unsafe { Box::from_raw(self.next) };
+        self.next = next.into_inner().unwrap_or_else(PoisonError::into_inner);
+        Some(data)
+    }
+}
+
+impl<T> Drop for OwningIter<T> {
+    fn drop(&mut self) {
+        for _ in self.by_ref() {}
+    }
+}
+
+impl<T> FineGrainedListSet<T> {
+    /// Calls `f` with the elements in ascending order while all the nodes are locked, so that the
+    /// elements are a consistent snapshot of the set.
+    ///
+    /// The locks are acquired from the head and held until `f` returns, so the other operations
+    /// block meanwhile. Calling this for another set inside `f` may deadlock if another thread
+    /// does the same in the opposite order.
+    pub(super) fn with_snapshot<R>(&self, f: impl FnOnce(&[&T]) -> R) -> R {
+        let _check = LockLeakCheck::new();
+        let recorder = self.recorder(Op::Iter);
+        let mut guards = vec![recorder.lock(&self.head)];
+        let mut elements = Vec::new();
+        // 不释放经过的锁，遍历结束时整个链表都被锁住
+        while let Some(node) = unsafe { (**guards.last().unwrap()).as_ref() } {
+            elements.push(&node.data);
+            guards.push(recorder.lock(&node.next));
+        }
+        f(&elements)
+    }
+}
+
+impl<T: Ord + Encode> FineGrainedListSet<T> {
+    /// Encodes the elements into a flat buffer in ascending order, which can be restored with
+    /// `decode`.
+    ///
+    /// The elements are read with lock-coupling as in `iter`.
+    pub fn encode(&self) -> Vec<u8> {
+        let _check = LockLeakCheck::new();
+        let mut elements = Vec::new();
+        let mut len = 0u64;
+        for data in self.iter() {
+            data.encode(&mut elements);
+            len += 1;
+        }
+        let mut buf = Vec::with_capacity(mem::size_of::<u64>() + elements.len());
+        len.encode(&mut buf);
+        buf.extend_from_slice(&elements);
+        buf
+    }
+}
+
+impl<T: Ord + Decode> FineGrainedListSet<T> {
+    /// Restores a list encoded with `encode`.
+    ///
+    /// Returns `Err(())` if `buf` is malformed, including when the elements are not in strictly
+    /// ascending order.
+    pub fn decode(buf: &[u8]) -> Result<Self, ()> {
+        let (len, mut buf) = u64::decode(buf)?;
+        let mut elements = Vec::new();
+        for _ in 0..len {
+            let (data, rest) = T::decode(buf)?;
+            if elements.last().is_some_and(|prev| *prev >= data) {
+                return Err(());
+            }
+            elements.push(data);
+            buf = rest;
+        }
+        if !buf.is_empty() {
+            return Err(());
+        }
+        Ok(Self::from_sorted_iter(elements))
+    }
 }
 
 impl<T: Ord> ConcurrentSet<T> for FineGrainedListSet<T> {
     fn contains(&self, key: &T) -> bool {
-        self.find(key).0
+        let _check = LockLeakCheck::new();
+        let logged = self.op_key(key);
+        let (found, _cur) = self.find(key, Op::Contains);
+        Self::log_op("contains", logged, found)
     }
 
     fn insert(&self, key: T) -> bool {
-        let (found, mut cur) = self.find(&key);
-        if !found {
-            // cur在目标位置之前一个节点
-            match unsafe { cur.0.as_mut() } {
-                Some(prev_next) => {
-                    let new_node = Node::new(key, prev_next);
-                    *cur.0 = new_node;
-                }
-                None => {
-                    let new_node = Node::new(key, ptr::null_mut());
-                    *cur.0 = new_node;
-                }
-            }
-            return true;
-        }
-        return false;
+        let _check = LockLeakCheck::new();
+        let logged = self.op_key(&key);
+        let (found, mut cur) = self.find(&key, Op::Insert);
+        // cur在目标位置之前一个节点
+        let inserted = !found && self.link(&mut cur, key);
+        Self::log_op("insert", logged, inserted)
     }
 
     fn remove(&self, key: &T) -> bool {
-        let (found, mut cur) = self.find(&key);
+        let _check = LockLeakCheck::new();
+        let logged = self.op_key(key);
+        let (found, mut cur) = self.find(&key, Op::Remove);
         if found {
             // cur.0始终存在
             let target = unsafe { cur.0.as_mut().unwrap() };
             // 释放目标节点的内存
             let b = unsafe { Box::from_raw(target) };
             // 下下个节点
-            let next = b.next.lock().unwrap();
+            let next = cur.0.lock_next(&b.next);
             *cur.0 = *next;
-            return true;
+            self.uncount(1);
+            return Self::log_op("remove", logged, true);
+        }
+        return Self::log_op("remove", logged, false);
+    }
+
+    /// If the values are sorted, inserts them all in a single pass of a cursor. Otherwise, inserts
+    /// them one by one.
+    fn insert_many<I: IntoIterator<Item = T>>(&self, values: I) -> Vec<bool> {
+        let values = values.into_iter().collect::<Vec<_>>();
+        if !values.windows(2).all(|w| w[0] <= w[1]) {
+            return values.into_iter().map(|value| self.insert(value)).collect();
+        }
+
+        // 输入有序时，每个值的位置都在前一个值之后，光标不需要回到head
+        let _check = LockLeakCheck::new();
+        let mut cur = Cursor::head(self, Op::Insert);
+        values
+            .into_iter()
+            .map(|value| {
+                let logged = self.op_key(&value);
+                let inserted = !cur.find(&value) && self.link(&mut cur, value);
+                Self::log_op("insert", logged, inserted)
+            })
+            .collect()
+    }
+}
+
+impl<T: Ord> FineGrainedListSet<T> {
+    /// Removes all the elements in `range` in a single pass, and returns the number of the removed
+    /// elements. See `SortedSetExt::remove_range`.
+    pub(super) fn remove_range<R: RangeBounds<T>>(&self, range: &R) -> usize {
+        let before_start = |data: &T| match range.start_bound() {
+            Bound::Included(start) => data < start,
+            Bound::Excluded(start) => data <= start,
+            Bound::Unbounded => false,
+        };
+        let _check = LockLeakCheck::new();
+        let mut cur = Cursor::head(self, Op::Remove);
+        while let Some(node) = unsafe { cur.0.as_ref() } {
+            if !before_start(&node.data) {
+                break;
+            }
+            cur.0 = cur.0.lock_next(&node.next);
+        }
+
+        // 一直持有前驱的锁，逐个摘除范围内的节点，其他线程无法越过前驱插入
+        let mut removed = 0;
+        while let Some(node) = unsafe { cur.0.as_ref() } {
+            if !range.contains(&node.data) {
+                break;
+            }
+            drop(self.unlink(&mut cur));
+            removed += 1;
+        }
+        removed
+    }
+}
+
+impl<T: Ord + Clone> FineGrainedListSet<T> {
+    /// Returns the smallest element strictly greater than `key`, or `None` if there is no such
+    /// element. `key` need not be in the set.
+    pub fn successor(&self, key: &T) -> Option<T> {
+        let _check = LockLeakCheck::new();
+        let (found, mut cur) = self.find(key, Op::Contains);
+        if found {
+            // key本身在链表中，再前进一个节点
+            let node = unsafe { &**cur.0 };
+            cur.0 = cur.0.lock_next(&node.next);
         }
-        return false;
+        unsafe { cur.0.as_ref() }.map(|node| node.data.clone())
+    }
+
+    /// Returns the largest element strictly less than `key`, or `None` if there is no such
+    /// element. `key` need not be in the set.
+    pub fn predecessor(&self, key: &T) -> Option<T> {
+        let _check = LockLeakCheck::new();
+        let mut cur = Cursor::head(self, Op::Contains);
+        let (_, prev) = cur.find_predecessor(key, ptr::null());
+        unsafe { prev.as_ref() }.cloned()
+    }
+}
+
+impl<T: Ord> FineGrainedListSet<T> {
+    /// Runs `f` as a transaction, whose operations on the set are atomic with respect to the
+    /// other transactions, e.g. to move an element from a key to another without a concurrent
+    /// transaction observing neither or both.
+    ///
+    /// The transactions run one at a time, serialized by a lock of the whole set, while the
+    /// operations of a transaction still lock the nodes as usual. So the plain operations outside
+    /// of the transactions (e.g. `contains` of a reader) are not blocked, but they may observe the
+    /// intermediate states of a transaction, and their edits may interleave with it.
+    ///
+    /// The edits are not rolled back if `f` panics.
+    pub fn transaction<R>(&self, f: impl FnOnce(&FineGrainedTransaction<'_, T>) -> R) -> R {
+        // 事务panic时不回滚，锁的poison可以忽略
+        let _serialized = self
+            .transactions
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        f(&FineGrainedTransaction { set: self })
+    }
+}
+
+/// Operations on a [`FineGrainedListSet`] within a transaction. See
+/// [`FineGrainedListSet::transaction`].
+#[derive(Debug)]
+pub struct FineGrainedTransaction<'s, T> {
+    set: &'s FineGrainedListSet<T>,
+}
+
+impl<T: Ord> FineGrainedTransaction<'_, T> {
+    /// Returns whether the set contains the key. See `ConcurrentSet::contains`.
+    pub fn contains(&self, key: &T) -> bool {
+        self.set.contains(key)
+    }
+
+    /// Inserts the key, and returns whether it was inserted. See `ConcurrentSet::insert`.
+    pub fn insert(&self, key: T) -> bool {
+        self.set.insert(key)
+    }
+
+    /// Removes the key, and returns whether it was removed. See `ConcurrentSet::remove`.
+    pub fn remove(&self, key: &T) -> bool {
+        self.set.remove(key)
+    }
+}
+
+/// Cursor to a position of a [`FineGrainedListSet`], returned by
+/// [`FineGrainedListSet::remove_returning_predecessor`].
+///
+/// The cursor can only move forward, so it accepts only the keys after the node it is positioned
+/// at (the predecessor).
+pub struct FineGrainedCursor<'l, T> {
+    cursor: Cursor<'l, T>,
+    /// Data of the node whose `next` the cursor locks, or null if the cursor is at the head.
+    predecessor: *const T,
+    set: &'l FineGrainedListSet<T>,
+}
+
+impl<T> FineGrainedCursor<'_, T> {
+    /// Returns the key of the predecessor, or `None` if the cursor is at the head.
+    pub fn predecessor(&self) -> Option<&T> {
+        unsafe { self.predecessor.as_ref() }
+    }
+}
+
+impl<T: Ord> FineGrainedCursor<'_, T> {
+    /// Inserts the key, moving the cursor forward to its position. Returns whether the key was
+    /// inserted, or `Err(key)` without moving the cursor if the key is less than the predecessor.
+    /// The key is not inserted if it is already in the set or the set is full.
+    pub fn insert(&mut self, key: T) -> Result<bool, T> {
+        match self.predecessor().map(|prev| key.cmp(prev)) {
+            Some(Less) => return Err(key),
+            Some(Equal) => return Ok(false),
+            Some(Greater) | None => {}
+        }
+        let _check = LockLeakCheck::new();
+        self.cursor.0.set_recorder(self.set.recorder(Op::Insert));
+        let (found, predecessor) = self.cursor.find_predecessor(&key, self.predecessor);
+        self.predecessor = predecessor;
+        if found {
+            return Ok(false);
+        }
+        Ok(self.set.link(&mut self.cursor, key))
+    }
+}
+
+impl<T: Debug> Debug for FineGrainedCursor<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FineGrainedCursor")
+            .field("predecessor", &self.predecessor())
+            .finish()
     }
 }
 
 #[derive(Debug)]
 pub struct Iter<'l, T> {
-    cursor: MutexGuard<'l, *mut Node<T>>,
+    cursor: HeldLock<'l, *mut Node<T>>,
+    /// The last yielded element, to check the order in debug builds.
+    prev: Option<&'l T>,
 }
 
-impl<T> FineGrainedListSet<T> {
-    /// An iterator visiting all elements.
+impl<T: Ord> FineGrainedListSet<T> {
+    /// An iterator visiting all elements in strictly ascending order.
     pub fn iter(&self) -> Iter<'_, T> {
         Iter {
-            cursor: self.head.lock().unwrap(),
+            cursor: self.recorder(Op::Iter).lock(&self.head),
+            prev: None,
         }
     }
 }
 
-impl<'l, T> Iterator for Iter<'l, T> {
+/// Iterates with `iter`, so that the set can be used as `for x in &set`.
+///
+/// Like `iter`, the iterator keeps the node at its position locked between the iterations. So the
+/// body of the loop must not modify the set at or after the current element, e.g. `set.insert` or
+/// `set.remove` of a larger key, or it deadlocks.
+impl<'l, T: Ord> IntoIterator for &'l FineGrainedListSet<T> {
+    type Item = &'l T;
+    type IntoIter = Iter<'l, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Read-only view of a [`FineGrainedListSet`], which can't be used to mutate the set.
+///
+/// # Examples
+///
+/// ```
+/// use cs431_homework::{ConcurrentSet, FineGrainedListSet};
+///
+/// let set = FineGrainedListSet::new();
+/// assert!(set.insert(1));
+/// let view = set.as_read_only();
+/// assert!(view.contains(&1));
+/// assert_eq!(view.len(), 1);
+/// ```
+///
+/// The view doesn't expose the mutating methods of the set.
+///
+/// ```compile_fail
+/// use cs431_homework::{ConcurrentSet, FineGrainedListSet};
+///
+/// let set = FineGrainedListSet::new();
+/// let view = set.as_read_only();
+/// view.insert(1);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct ReadOnlyView<'s, T> {
+    set: &'s FineGrainedListSet<T>,
+}
+
+impl<T> FineGrainedListSet<T> {
+    /// Returns a read-only view of the set.
+    pub fn as_read_only(&self) -> ReadOnlyView<'_, T> {
+        ReadOnlyView { set: self }
+    }
+}
+
+impl<'s, T: Ord> ReadOnlyView<'s, T> {
+    /// An iterator visiting all elements. See `FineGrainedListSet::iter`.
+    pub fn iter(&self) -> Iter<'s, T> {
+        self.set.iter()
+    }
+
+    /// Returns the number of elements, counted by iterating over the set.
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    /// Returns `true` if the set has no element.
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+}
+
+impl<T: Ord> ReadOnlyView<'_, T> {
+    /// Returns whether the set contains the key.
+    pub fn contains(&self, key: &T) -> bool {
+        self.set.contains(key)
+    }
+}
+
+impl<'l, T: Ord> Iterator for Iter<'l, T> {
     type Item = &'l T;
 
     fn next(&mut self) -> Option<Self::Item> {
@@ -144,7 +902,13 @@ impl<'l, T> Iterator for Iter<'l, T> {
         }
         if let Some(node) = unsafe { self.cursor.as_mut() } {
             let data = &node.data;
-            self.cursor = node.next.lock().unwrap();
+            // 链表的修改破坏了顺序时尽早发现
+            debug_assert!(
+                !self.prev.is_some_and(|prev| prev >= data),
+                "FineGrainedListSet is not sorted"
+            );
+            self.prev = Some(data);
+            self.cursor = self.cursor.lock_next(&node.next);
             return Some(data);
         }
         None
@@ -152,12 +916,20 @@ impl<'l, T> Iterator for Iter<'l, T> {
 }
 
 impl<T> Drop for FineGrainedListSet<T> {
+    /// Frees the nodes and drops the elements in the list order.
+    ///
+    /// We have the exclusive access, so the locks are bypassed. A lock poisoned by a panic (e.g. in
+    /// `T::cmp`) doesn't stop the teardown, so that the rest of the nodes are not leaked.
     fn drop(&mut self) {
-        let mut head = *self.head.lock().unwrap();
+        let mut head = *self.head.get_mut().unwrap_or_else(PoisonError::into_inner);
         while !head.is_null() {
-            let mut node = unsafe { Box::from_raw(head) };
-            head = *node.next.lock().unwrap();
-            drop(node);
+            let node = unsafe { Box::from_raw(head) };
+            // 先取出next再释放节点，每个节点只被释放一次
+            head = node
+                .next
+                .into_inner()
+                .unwrap_or_else(PoisonError::into_inner);
+            drop(node.data);
         }
     }
 }
@@ -167,3 +939,61 @@ impl<T> Default for FineGrainedListSet<T> {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    #[should_panic(expected = "FineGrainedListSet is not sorted")]
+    fn iter_detects_unsorted() {
+        let set = FineGrainedListSet::new();
+        for key in [1, 2, 4] {
+            assert!(set.insert(key));
+        }
+        // Link 3 after 4, as a broken relinking would.
+        let mut cursor = set.find(&4, Op::Insert).1;
+        let _ = cursor.find(&5);
+        cursor.insert(3);
+        drop(cursor);
+        let _ = set.iter().count();
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn no_lock_held_after_operations() {
+        use super::super::lock_metrics::held_locks;
+
+        let set = FineGrainedListSet::new();
+        for key in [3, 1, 2] {
+            assert!(set.insert(key));
+            assert_eq!(held_locks(), 0);
+        }
+        assert!(!set.insert(2));
+        assert_eq!(held_locks(), 0);
+        assert!(set.contains(&2));
+        assert!(!set.contains(&4));
+        assert_eq!(held_locks(), 0);
+        assert!(set.remove(&2));
+        assert!(!set.remove(&2));
+        assert_eq!(held_locks(), 0);
+
+        // The locks of an iterator in progress are not counted as leaked by other operations.
+        let mut iter = set.iter();
+        assert_eq!(iter.next(), Some(&1));
+        assert_eq!(held_locks(), 1);
+        assert!(FineGrainedListSet::new().insert(0));
+        drop(iter);
+        assert_eq!(held_locks(), 0);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "a node lock is leaked")]
+    fn lock_leak_detected() {
+        let set = FineGrainedListSet::new();
+        assert!(set.insert(1));
+        let _check = LockLeakCheck::new();
+        mem::forget(set.find(&1, Op::Contains).1);
+    }
+}
@@ -0,0 +1,217 @@
+//! Lock wait instrumentation of `FineGrainedListSet`, enabled by the `lock-metrics` feature.
+//!
+//! If the feature is off, `Recorder` is zero-sized and `Recorder::lock` is just `Mutex::lock`.
+//!
+//! In debug builds, the locks held by each thread are also counted, so that an operation that
+//! returns with a lock still held is caught by `LockLeakCheck`.
+
+#[cfg(feature = "lock-metrics")]
+use core::sync::atomic::{AtomicU64, Ordering::Relaxed};
+#[cfg(debug_assertions)]
+use std::cell::Cell;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Mutex, MutexGuard};
+#[cfg(debug_assertions)]
+use std::thread;
+#[cfg(feature = "lock-metrics")]
+use std::time::{Duration, Instant};
+
+/// Operations whose lock waits are recorded separately.
+#[derive(Debug, Clone, Copy)]
+pub(super) enum Op {
+    Contains,
+    Insert,
+    Remove,
+    /// `iter` and the snapshots.
+    Iter,
+}
+
+/// Number of the buckets of a lock wait histogram.
+#[cfg(feature = "lock-metrics")]
+const BUCKETS: usize = u64::BITS as usize;
+
+/// Lock wait histograms of a list, one for each `Op`.
+#[cfg(feature = "lock-metrics")]
+#[derive(Debug)]
+pub(super) struct LockMetrics {
+    // 桶的个数是固定的，直接用定长数组，不需要可增长的直方图
+    histograms: [[AtomicU64; BUCKETS]; 4],
+}
+
+#[cfg(feature = "lock-metrics")]
+impl LockMetrics {
+    pub(super) fn new() -> Self {
+        Self {
+            histograms: [(); 4].map(|_| [(); BUCKETS].map(|_| AtomicU64::new(0))),
+        }
+    }
+
+    /// Returns the bucket of a wait, i.e. `⌊log2(nanoseconds)⌋`, or 0 for a wait under 1ns.
+    fn bucket(wait: Duration) -> usize {
+        let nanos = u64::try_from(wait.as_nanos()).unwrap_or(u64::MAX);
+        nanos.checked_ilog2().unwrap_or(0) as usize
+    }
+
+    pub(super) fn stats(&self) -> LockWaitStats {
+        let [contains, insert, remove, iter] = self
+            .histograms
+            .each_ref()
+            .map(|histogram| histogram.iter().map(|b| b.load(Relaxed)).collect());
+        LockWaitStats {
+            contains,
+            insert,
+            remove,
+            iter,
+        }
+    }
+}
+
+/// Histograms of the time that the operations of a `FineGrainedListSet` waited for the node locks,
+/// returned by `FineGrainedListSet::lock_wait_stats`.
+///
+/// Each histogram has 64 buckets, and the `i`-th bucket counts the lock acquisitions that waited
+/// for `[2^i, 2^(i+1))` nanoseconds. Bucket 0 also counts the waits under 1ns.
+#[cfg(feature = "lock-metrics")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LockWaitStats {
+    /// Waits of `contains`.
+    pub contains: Vec<u64>,
+    /// Waits of the insertions, including `insert_many` and `FineGrainedCursor::insert`.
+    pub insert: Vec<u64>,
+    /// Waits of the removals, including `pop_min` and `remove_if`.
+    pub remove: Vec<u64>,
+    /// Waits of `iter` and the snapshots.
+    pub iter: Vec<u64>,
+}
+
+/// Locks the nodes on behalf of an operation, recording the waits if the `lock-metrics` feature is
+/// on.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Recorder<'l> {
+    #[cfg(feature = "lock-metrics")]
+    histogram: &'l [AtomicU64; BUCKETS],
+    _marker: PhantomData<&'l ()>,
+}
+
+impl<'l> Recorder<'l> {
+    #[cfg(feature = "lock-metrics")]
+    pub(super) fn new(metrics: &'l LockMetrics, op: Op) -> Self {
+        Self {
+            histogram: &metrics.histograms[op as usize],
+            _marker: PhantomData,
+        }
+    }
+
+    #[cfg(not(feature = "lock-metrics"))]
+    pub(super) fn new(_metrics: &'l (), _op: Op) -> Self {
+        Self {
+            _marker: PhantomData,
+        }
+    }
+
+    pub(super) fn lock<'m, U>(self, mutex: &'m Mutex<U>) -> HeldLock<'m, U>
+    where
+        'l: 'm,
+    {
+        #[cfg(feature = "lock-metrics")]
+        let start = Instant::now();
+        let guard = mutex.lock().unwrap();
+        #[cfg(feature = "lock-metrics")]
+        let _ = self.histogram[LockMetrics::bucket(start.elapsed())].fetch_add(1, Relaxed);
+        HeldLock::new(guard, self)
+    }
+}
+
+#[cfg(debug_assertions)]
+thread_local! {
+    /// Number of the node locks held by the current thread.
+    static HELD: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Returns the number of the node locks held by the current thread.
+#[cfg(debug_assertions)]
+pub(super) fn held_locks() -> usize {
+    HELD.with(Cell::get)
+}
+
+/// Guard of a node lock, counted in `held_locks` in debug builds while it is alive.
+///
+/// The guard keeps the recorder of the operation that took the lock, so that the lock coupling can
+/// take the next lock on behalf of the same operation.
+#[derive(Debug)]
+pub(super) struct HeldLock<'m, U> {
+    guard: MutexGuard<'m, U>,
+    recorder: Recorder<'m>,
+}
+
+impl<'m, U> HeldLock<'m, U> {
+    pub(super) fn new(guard: MutexGuard<'m, U>, recorder: Recorder<'m>) -> Self {
+        #[cfg(debug_assertions)]
+        HELD.with(|held| held.set(held.get() + 1));
+        Self { guard, recorder }
+    }
+
+    /// Locks `mutex`, e.g. the `next` of the node this lock leads to, on behalf of the same
+    /// operation.
+    pub(super) fn lock_next<V>(&self, mutex: &'m Mutex<V>) -> HeldLock<'m, V> {
+        self.recorder.lock(mutex)
+    }
+
+    /// Records the locks taken by `lock_next` from now on with `recorder`, e.g. when a cursor is
+    /// reused by another operation.
+    pub(super) fn set_recorder(&mut self, recorder: Recorder<'m>) {
+        self.recorder = recorder;
+    }
+}
+
+impl<U> Deref for HeldLock<'_, U> {
+    type Target = U;
+
+    fn deref(&self) -> &U {
+        &self.guard
+    }
+}
+
+impl<U> DerefMut for HeldLock<'_, U> {
+    fn deref_mut(&mut self) -> &mut U {
+        &mut self.guard
+    }
+}
+
+impl<U> Drop for HeldLock<'_, U> {
+    fn drop(&mut self) {
+        #[cfg(debug_assertions)]
+        HELD.with(|held| held.set(held.get() - 1));
+    }
+}
+
+/// Asserts in debug builds that an operation releases all the locks it acquired, when dropped at
+/// the end of the operation.
+///
+/// The count is compared with the one at the start of the operation rather than 0, as the caller
+/// may hold the locks of another operation, e.g. an `iter` in progress.
+#[derive(Debug)]
+pub(super) struct LockLeakCheck {
+    #[cfg(debug_assertions)]
+    held: usize,
+}
+
+impl LockLeakCheck {
+    pub(super) fn new() -> Self {
+        Self {
+            #[cfg(debug_assertions)]
+            held: held_locks(),
+        }
+    }
+}
+
+impl Drop for LockLeakCheck {
+    fn drop(&mut self) {
+        // 已经在panic时不再检查，避免二次panic
+        #[cfg(debug_assertions)]
+        if !thread::panicking() {
+            debug_assert_eq!(held_locks(), self.held, "a node lock is leaked");
+        }
+    }
+}
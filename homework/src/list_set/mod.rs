@@ -1,5 +1,30 @@
+mod algebra;
+mod blocking_queue;
+mod codec;
 mod fine_grained;
+mod lock_metrics;
+#[cfg(feature = "op-log")]
+mod op_log;
 mod optimistic_fine_grained;
+mod rate_limiter;
+mod sequential;
+mod skip_list;
+mod unrolled;
+mod versioned;
 
-pub use fine_grained::FineGrainedListSet;
+pub use algebra::SortedSetExt;
+pub use blocking_queue::BlockingSortedQueue;
+pub use codec::{Decode, Encode};
+pub use fine_grained::{
+    FineGrainedCursor, FineGrainedListSet, FineGrainedTransaction, OwningIter, ReadOnlyView,
+};
+#[cfg(feature = "lock-metrics")]
+pub use lock_metrics::LockWaitStats;
+#[cfg(feature = "op-log")]
+pub use op_log::{dump_op_log, OpRecord};
 pub use optimistic_fine_grained::OptimisticFineGrainedListSet;
+pub use rate_limiter::SlidingWindowLimiter;
+pub use sequential::SequentialListSet;
+pub use skip_list::SkipListSet;
+pub use unrolled::UnrolledListSet;
+pub use versioned::VersionedListSet;
@@ -0,0 +1,95 @@
+//! Log of the operations of `FineGrainedListSet`, enabled by the `op-log` feature.
+//!
+//! Each thread records its operations into its own bounded buffer, so that logging doesn't add a
+//! lock shared by the threads, which would serialize them and mask the race being diagnosed. The
+//! buffers are merged in the order of the sequence numbers on `dump_op_log`.
+
+use std::collections::VecDeque;
+use std::fmt::Debug;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering::Relaxed;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::{self, ThreadId};
+
+/// Number of the latest records kept for each thread.
+const CAPACITY: usize = 1 << 12;
+
+/// An operation recorded in the log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpRecord {
+    /// Order of the record among all the records, taken when the operation returns.
+    pub seq: u64,
+    /// The thread that performed the operation.
+    pub thread: ThreadId,
+    /// Name of the operation, e.g. `"insert"`.
+    pub op: &'static str,
+    /// The key, formatted with `Debug`.
+    pub key: String,
+    /// The returned value.
+    pub result: bool,
+}
+
+/// Buffer of the latest records of a thread.
+type Buffer = Arc<Mutex<VecDeque<OpRecord>>>;
+
+/// Next sequence number.
+static SEQ: AtomicU64 = AtomicU64::new(0);
+
+/// Buffers of all the threads that recorded an operation, kept after the threads exit so that
+/// their records are still dumped.
+static BUFFERS: Mutex<Vec<Buffer>> = Mutex::new(Vec::new());
+
+thread_local! {
+    /// Buffer of the current thread, registered in `BUFFERS` on the first record.
+    static BUFFER: Buffer = {
+        let buffer = Buffer::default();
+        BUFFERS
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(Arc::clone(&buffer));
+        buffer
+    };
+}
+
+/// Records an operation of the current thread, dropping its oldest record if the buffer is full.
+pub(super) fn record(op: &'static str, key: String, result: bool) {
+    let record = OpRecord {
+        seq: SEQ.fetch_add(1, Relaxed),
+        thread: thread::current().id(),
+        op,
+        key,
+        result,
+    };
+    BUFFER.with(|buffer| {
+        // 只有dump时才会与其他线程竞争这个锁
+        let mut buffer = buffer.lock().unwrap_or_else(PoisonError::into_inner);
+        if buffer.len() == CAPACITY {
+            let _ = buffer.pop_front();
+        }
+        buffer.push_back(record);
+    });
+}
+
+/// Formats a key for the log.
+pub(super) fn format_key<T: Debug>(key: &T) -> String {
+    format!("{key:?}")
+}
+
+/// Returns the recorded operations of all the threads, ordered by the sequence number.
+///
+/// Only the operations of the sets that opted in with `FineGrainedListSet::log_ops` are recorded,
+/// and only the latest records of each thread are kept. The sequence number is taken when an
+/// operation returns, so the order approximates the order the operations took effect, e.g. to
+/// reconstruct the interleaving that led to a bad state after a stress test fails.
+pub fn dump_op_log() -> Vec<OpRecord> {
+    let buffers = BUFFERS.lock().unwrap_or_else(PoisonError::into_inner);
+    let mut records = buffers
+        .iter()
+        .flat_map(|buffer| {
+            let buffer = buffer.lock().unwrap_or_else(PoisonError::into_inner);
+            buffer.iter().cloned().collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    records.sort_unstable_by_key(|record| record.seq);
+    records
+}
@@ -0,0 +1,69 @@
+//! Sliding window rate limiter on top of `FineGrainedListSet`.
+
+use std::time::{Duration, Instant};
+
+use super::FineGrainedListSet;
+use crate::ConcurrentSet;
+
+/// Rate limiter that allows at most `limit` requests in any window of the given length.
+///
+/// The times of the allowed requests are kept in a [`FineGrainedListSet`] bounded by `limit`. A
+/// request first removes the times that fell out of the window with a single range removal, and
+/// then is allowed only if its own time fits in the set. The bound of the set is enforced by the
+/// insertion itself, so the concurrent requests never exceed the limit together.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use cs431_homework::SlidingWindowLimiter;
+///
+/// let limiter = SlidingWindowLimiter::new(2, Duration::from_secs(60));
+/// assert!(limiter.allow());
+/// assert!(limiter.allow());
+/// assert!(!limiter.allow());
+/// ```
+#[derive(Debug)]
+pub struct SlidingWindowLimiter {
+    /// The times of the allowed requests in the window, and maybe some older ones.
+    times: FineGrainedListSet<Instant>,
+    window: Duration,
+}
+
+impl SlidingWindowLimiter {
+    /// Creates a limiter that allows at most `limit` requests in any window of length `window`.
+    pub fn new(limit: usize, window: Duration) -> Self {
+        Self {
+            times: FineGrainedListSet::with_max_len(limit),
+            window,
+        }
+    }
+
+    /// Returns whether a request now is allowed, and counts it if so.
+    pub fn allow(&self) -> bool {
+        self.allow_at(Instant::now())
+    }
+
+    /// Returns whether a request at `now` is allowed, and counts it if so, e.g. to replay the
+    /// requests with their own times.
+    ///
+    /// The window is `(now - window, now]`, so the requests at or before `now - window` no longer
+    /// count.
+    pub fn allow_at(&self, now: Instant) -> bool {
+        if let Some(start) = now.checked_sub(self.window) {
+            let _ = self.times.remove_range(&(..=start));
+        }
+        // 同一时刻的请求错开1ns，以区分重复和已满
+        let mut time = now;
+        loop {
+            if self.times.insert(time) {
+                return true;
+            }
+            if !self.times.contains(&time) {
+                return false;
+            }
+            time += Duration::from_nanos(1);
+        }
+    }
+}
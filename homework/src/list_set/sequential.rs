@@ -0,0 +1,140 @@
+use std::cell::RefCell;
+use std::sync::PoisonError;
+use std::{mem, ptr};
+
+use super::fine_grained::Node;
+use crate::ConcurrentSet;
+
+/// Sorted singly linked list without any lock, for single-threaded use.
+///
+/// This has the same `ConcurrentSet` API as [`FineGrainedListSet`](super::FineGrainedListSet),
+/// and even the same nodes, so that `FineGrainedListSet::into_sequential` moves the nodes over
+/// without reallocating them. But the locks of the nodes are bypassed with `Mutex::get_mut` and
+/// the whole list is behind a `RefCell`, so every operation is free of atomic read-modify-write
+/// instructions. The set is not `Sync`, so it can't be shared between threads.
+#[derive(Debug)]
+pub struct SequentialListSet<T> {
+    head: RefCell<*mut Node<T>>,
+}
+
+unsafe impl<T: Send> Send for SequentialListSet<T> {}
+
+/// Returns the `next` of `node` without locking it.
+///
+/// # Safety
+///
+/// `node` must be a valid node of a list that is exclusively borrowed for `'l`.
+unsafe fn next<'l, T>(node: *mut Node<T>) -> &'l mut *mut Node<T> {
+    unsafe { &mut *node }
+        .next
+        .get_mut()
+        .unwrap_or_else(PoisonError::into_inner)
+}
+
+impl<T> SequentialListSet<T> {
+    /// Creates a new list.
+    pub fn new() -> Self {
+        Self::from_head(ptr::null_mut())
+    }
+
+    /// Creates a list owning the chain of nodes from `head`, which must be in strictly ascending
+    /// order and not shared with any other list.
+    pub(super) fn from_head(head: *mut Node<T>) -> Self {
+        Self {
+            head: RefCell::new(head),
+        }
+    }
+
+    /// Returns the elements in ascending order.
+    pub fn to_vec(&self) -> Vec<T>
+    where
+        T: Clone,
+    {
+        let mut head = self.head.borrow_mut();
+        let mut elements = Vec::new();
+        let mut link = &mut *head;
+        while let Some(node) = unsafe { link.as_ref() } {
+            elements.push(node.data.clone());
+            link = unsafe { next(*link) };
+        }
+        elements
+    }
+
+    /// Returns the number of elements.
+    pub fn len(&self) -> usize {
+        let mut head = self.head.borrow_mut();
+        let mut len = 0;
+        let mut link = &mut *head;
+        while !link.is_null() {
+            len += 1;
+            link = unsafe { next(*link) };
+        }
+        len
+    }
+
+    /// Returns `true` if the set has no element.
+    pub fn is_empty(&self) -> bool {
+        self.head.borrow().is_null()
+    }
+}
+
+impl<T: Ord> SequentialListSet<T> {
+    /// Returns the link to the first node that is not less than `key`.
+    fn find<'l>(mut link: &'l mut *mut Node<T>, key: &T) -> &'l mut *mut Node<T> {
+        while unsafe { link.as_ref() }.is_some_and(|node| node.data < *key) {
+            link = unsafe { next(*link) };
+        }
+        link
+    }
+}
+
+impl<T: Ord> ConcurrentSet<T> for SequentialListSet<T> {
+    fn contains(&self, key: &T) -> bool {
+        let mut head = self.head.borrow_mut();
+        unsafe { Self::find(&mut head, key).as_ref() }.is_some_and(|node| node.data == *key)
+    }
+
+    fn insert(&self, key: T) -> bool {
+        let mut head = self.head.borrow_mut();
+        let link = Self::find(&mut head, &key);
+        if unsafe { link.as_ref() }.is_some_and(|node| node.data == key) {
+            return false;
+        }
+        *link = Node::new(key, *link);
+        true
+    }
+
+    fn remove(&self, key: &T) -> bool {
+        let mut head = self.head.borrow_mut();
+        let link = Self::find(&mut head, key);
+        if !unsafe { link.as_ref() }.is_some_and(|node| node.data == *key) {
+            return false;
+        }
+        let node = unsafe { Box::from_raw(*link) };
+        *link = node
+            .next
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner);
+        true
+    }
+}
+
+impl<T> Default for SequentialListSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for SequentialListSet<T> {
+    fn drop(&mut self) {
+        // 逐个释放，避免长链表递归drop时栈溢出
+        let mut link = mem::replace(self.head.get_mut(), ptr::null_mut());
+        while !link.is_null() {
+            let node = unsafe { Box::from_raw(link) };
+            link = node
+                .next
+                .into_inner()
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+}
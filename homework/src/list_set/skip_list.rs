@@ -0,0 +1,194 @@
+use std::sync::{Mutex, MutexGuard};
+use std::{mem, ptr};
+
+use rand::{thread_rng, Rng};
+
+use crate::ConcurrentSet;
+
+/// Maximum number of the levels. The expected cost of an operation stays logarithmic up to about
+/// 2^16 elements.
+const MAX_HEIGHT: usize = 16;
+
+/// Pointer to the next node at a level, protected by its own lock.
+type Link<T> = Mutex<*mut Node<T>>;
+
+#[derive(Debug)]
+struct Node<T> {
+    data: T,
+    /// The links at the levels of the node, from the bottom.
+    next: Box<[Link<T>]>,
+}
+
+/// Concurrent sorted skip list using fine-grained lock-coupling at each level.
+///
+/// Each node is linked at a random number of levels, so that a search skips most of the nodes at
+/// the upper levels, and `contains`, `insert` and `remove` take O(log n) expected time versus the
+/// O(n) of [`FineGrainedListSet`](super::FineGrainedListSet).
+///
+/// Like `FineGrainedListSet`, each link has its own lock. A search moves along a level by lock
+/// coupling, and goes down a level by locking the lower link of the same node before releasing the
+/// upper one. The locks are always taken from the upper levels to the lower levels, and from the
+/// front to the back within a level, so the operations don't deadlock. `insert` and `remove` keep
+/// the links around the node locked at all of its levels, so a node is linked and unlinked at all
+/// of them at once.
+#[derive(Debug)]
+pub struct SkipListSet<T> {
+    /// The links of the head at all the levels.
+    head: Box<[Link<T>]>,
+}
+
+unsafe impl<T: Send> Send for SkipListSet<T> {}
+unsafe impl<T: Send> Sync for SkipListSet<T> {}
+
+/// Returns the links of the given height, all null.
+fn links<T>(height: usize) -> Box<[Link<T>]> {
+    (0..height).map(|_| Mutex::new(ptr::null_mut())).collect()
+}
+
+/// Returns a random height, where the height `h` has the probability of 2^-h.
+fn random_height() -> usize {
+    (thread_rng().gen::<u32>().trailing_ones() as usize + 1).min(MAX_HEIGHT)
+}
+
+impl<T> SkipListSet<T> {
+    /// Creates a new skip list.
+    pub fn new() -> Self {
+        Self {
+            head: links(MAX_HEIGHT),
+        }
+    }
+}
+
+/// Position of a search at a level: the locked link of the predecessor at the level, and the
+/// links of the predecessor at all the levels, to go down from it.
+struct Cursor<'l, T> {
+    links: &'l [Link<T>],
+    guard: MutexGuard<'l, *mut Node<T>>,
+    level: usize,
+}
+
+impl<'l, T: Ord> Cursor<'l, T> {
+    /// Creates a cursor at the head at the top level.
+    fn head(set: &'l SkipListSet<T>) -> Self {
+        Self {
+            links: &set.head,
+            guard: set.head[MAX_HEIGHT - 1].lock().unwrap(),
+            level: MAX_HEIGHT - 1,
+        }
+    }
+
+    /// Moves the cursor forward at the current level while the next node is less than `key`.
+    /// Returns the next node, which is not less than `key`, or `None` at the end of the level.
+    fn advance(&mut self, key: &T) -> Option<&'l Node<T>> {
+        // SAFETY: The next node is not freed while the link to it is locked.
+        while let Some(node) = unsafe { self.guard.as_ref() } {
+            if node.data >= *key {
+                return Some(node);
+            }
+            self.guard = node.next[self.level].lock().unwrap();
+            self.links = &node.next;
+        }
+        None
+    }
+
+    /// Moves the cursor down a level, returning the guard of the link at the current level.
+    /// Returns `None` at the bottom level.
+    fn descend(&mut self) -> Option<MutexGuard<'l, *mut Node<T>>> {
+        let level = self.level.checked_sub(1)?;
+        // 先锁下一层再放开当前层
+        let guard = self.links[level].lock().unwrap();
+        self.level = level;
+        Some(mem::replace(&mut self.guard, guard))
+    }
+}
+
+impl<T: Ord> ConcurrentSet<T> for SkipListSet<T> {
+    fn contains(&self, key: &T) -> bool {
+        let mut cursor = Cursor::head(self);
+        loop {
+            if cursor.advance(key).is_some_and(|node| node.data == *key) {
+                return true;
+            }
+            if cursor.descend().is_none() {
+                return false;
+            }
+        }
+    }
+
+    fn insert(&self, key: T) -> bool {
+        let height = random_height();
+        // 新节点所在各层的前驱，从高到低，一直锁到插入结束
+        let mut preds = Vec::with_capacity(height);
+        let mut cursor = Cursor::head(self);
+        let bottom = loop {
+            if cursor.advance(&key).is_some_and(|node| node.data == key) {
+                return false;
+            }
+            let level = cursor.level;
+            match cursor.descend() {
+                Some(guard) if level < height => preds.push(guard),
+                Some(_) => {}
+                None => break cursor.guard,
+            }
+        };
+        preds.push(bottom);
+
+        let node = Box::into_raw(Box::new(Node {
+            data: key,
+            next: links(height),
+        }));
+        for (pred, level) in preds.iter_mut().zip((0..height).rev()) {
+            // SAFETY: The node is not shared until the links to it are unlocked.
+            unsafe { *(*node).next[level].get_mut().unwrap() = **pred };
+            **pred = node;
+        }
+        true
+    }
+
+    fn remove(&self, key: &T) -> bool {
+        // 被删除节点在各层的前驱，以及它自己在各层的link，从高到低
+        let mut preds = Vec::new();
+        let mut nexts = Vec::new();
+        let mut cursor = Cursor::head(self);
+        let bottom = loop {
+            let found = cursor.advance(key).filter(|node| node.data == *key);
+            if let Some(node) = found {
+                // 同一层中按从前往后的顺序加锁
+                nexts.push(node.next[cursor.level].lock().unwrap());
+            }
+            match cursor.descend() {
+                Some(guard) if found.is_some() => preds.push(guard),
+                Some(_) => {}
+                None if found.is_some() => break cursor.guard,
+                None => return false,
+            }
+        };
+        preds.push(bottom);
+
+        let node = *preds[0];
+        for (pred, next) in preds.iter_mut().zip(&nexts) {
+            **pred = **next;
+        }
+        // 持有所有前驱和node自身的锁，其他线程都无法到达node
+        drop(nexts);
+        drop(preds);
+        drop(unsafe { Box::from_raw(node) });
+        true
+    }
+}
+
+impl<T> Drop for SkipListSet<T> {
+    fn drop(&mut self) {
+        let mut next = *self.head[0].get_mut().unwrap();
+        while !next.is_null() {
+            let mut node = unsafe { Box::from_raw(next) };
+            next = *node.next[0].get_mut().unwrap();
+        }
+    }
+}
+
+impl<T> Default for SkipListSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
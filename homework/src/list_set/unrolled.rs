@@ -0,0 +1,156 @@
+use std::sync::{Mutex, MutexGuard};
+use std::{mem, ptr};
+
+use crate::ConcurrentSet;
+
+/// Elements of a node and the pointer to the next node, protected by the node's lock.
+#[derive(Debug)]
+struct Chunk<T> {
+    /// Sorted elements. Empty only for the head.
+    elements: Vec<T>,
+    next: *mut Node<T>,
+}
+
+type Node<T> = Mutex<Chunk<T>>;
+
+/// Concurrent sorted unrolled linked list using fine-grained lock-coupling.
+///
+/// Unlike [`FineGrainedListSet`](super::FineGrainedListSet), each node holds up to `B` elements
+/// under a single lock, so a traversal takes fewer locks and chases fewer pointers. A full node is
+/// split in half on insertion, and an empty node is unlinked on removal.
+///
+/// All the elements of a node are less than the elements of the next node.
+#[derive(Debug)]
+pub struct UnrolledListSet<T, const B: usize> {
+    /// The head is a node without elements.
+    head: Node<T>,
+}
+
+unsafe impl<T: Send, const B: usize> Send for UnrolledListSet<T, B> {}
+unsafe impl<T: Send, const B: usize> Sync for UnrolledListSet<T, B> {}
+
+/// Pair of locked adjacent nodes. `curr` is the node that contains the position of a key, or
+/// `None` if the list is empty.
+struct Cursor<'l, T> {
+    prev: MutexGuard<'l, Chunk<T>>,
+    curr: Option<MutexGuard<'l, Chunk<T>>>,
+}
+
+impl<T> Chunk<T> {
+    fn new(elements: Vec<T>, next: *mut Node<T>) -> *mut Node<T> {
+        Box::into_raw(Box::new(Mutex::new(Self { elements, next })))
+    }
+}
+
+impl<T, const B: usize> UnrolledListSet<T, B> {
+    /// Creates a new list.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `B` is less than 2, as a full node can't be split then.
+    pub fn new() -> Self {
+        assert!(B >= 2, "nodes must be able to hold at least 2 elements");
+        Self {
+            head: Mutex::new(Chunk {
+                elements: Vec::new(),
+                next: ptr::null_mut(),
+            }),
+        }
+    }
+}
+
+impl<T: Ord, const B: usize> UnrolledListSet<T, B> {
+    /// Returns the cursor at the first node whose largest element is not less than `key`, or the
+    /// last node if there is no such node.
+    fn find(&self, key: &T) -> Cursor<'_, T> {
+        let mut prev = self.head.lock().unwrap();
+        loop {
+            let Some(node) = (unsafe { prev.next.as_ref() }) else {
+                return Cursor { prev, curr: None };
+            };
+            let curr = node.lock().unwrap();
+            // 节点中的最大元素不小于key，或者已经是最后一个节点
+            if curr.elements.last().is_some_and(|last| last >= key) || curr.next.is_null() {
+                return Cursor {
+                    prev,
+                    curr: Some(curr),
+                };
+            }
+            prev = curr;
+        }
+    }
+}
+
+impl<T: Ord, const B: usize> ConcurrentSet<T> for UnrolledListSet<T, B> {
+    fn contains(&self, key: &T) -> bool {
+        let cursor = self.find(key);
+        cursor
+            .curr
+            .is_some_and(|curr| curr.elements.binary_search(key).is_ok())
+    }
+
+    fn insert(&self, key: T) -> bool {
+        let Cursor { mut prev, curr } = self.find(&key);
+        let Some(mut curr) = curr else {
+            // 链表为空
+            prev.next = Chunk::new(vec![key], ptr::null_mut());
+            return true;
+        };
+        drop(prev);
+
+        let Err(index) = curr.elements.binary_search(&key) else {
+            return false;
+        };
+        if curr.elements.len() < B {
+            curr.elements.insert(index, key);
+            return true;
+        }
+
+        // 节点已满，把后一半移到新节点，新节点链接之前不会被其他线程访问
+        let mid = B / 2;
+        let mut upper = Vec::with_capacity(B);
+        upper.extend(curr.elements.drain(mid..));
+        if index <= mid {
+            curr.elements.insert(index, key);
+        } else {
+            upper.insert(index - mid, key);
+        }
+        curr.next = Chunk::new(upper, curr.next);
+        true
+    }
+
+    fn remove(&self, key: &T) -> bool {
+        let Cursor { mut prev, curr } = self.find(key);
+        let Some(mut curr) = curr else {
+            return false;
+        };
+        let Ok(index) = curr.elements.binary_search(key) else {
+            return false;
+        };
+        let _ = curr.elements.remove(index);
+
+        if curr.elements.is_empty() {
+            // 持有prev的锁时，其他线程无法到达curr，可以直接释放
+            let node = mem::replace(&mut prev.next, curr.next);
+            drop(curr);
+            drop(unsafe { Box::from_raw(node) });
+        }
+        true
+    }
+}
+
+impl<T, const B: usize> Drop for UnrolledListSet<T, B> {
+    fn drop(&mut self) {
+        let mut next = self.head.get_mut().unwrap().next;
+        while !next.is_null() {
+            let node = unsafe { Box::from_raw(next) };
+            next = node.into_inner().unwrap().next;
+        }
+    }
+}
+
+impl<T, const B: usize> Default for UnrolledListSet<T, B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,191 @@
+use std::cmp::Ordering::*;
+#[cfg(not(feature = "check-loom"))]
+use std::sync::atomic::{AtomicUsize, Ordering::*};
+#[cfg(not(feature = "check-loom"))]
+use std::sync::{Mutex, MutexGuard};
+
+use crossbeam_epoch::{pin, unprotected, Atomic, Guard, Owned};
+#[cfg(feature = "check-loom")]
+use loom::sync::atomic::{AtomicUsize, Ordering::*};
+#[cfg(feature = "check-loom")]
+use loom::sync::{Mutex, MutexGuard};
+
+use crate::ConcurrentSet;
+
+#[derive(Debug)]
+struct Node<T> {
+    data: T,
+    /// Written only while `lock` is held, but read without the lock by the optimistic readers.
+    next: Atomic<Node<T>>,
+    lock: Mutex<()>,
+}
+
+/// Concurrent sorted singly linked list using fine-grained lock-coupling, whose `contains` first
+/// tries a lock-free read validated by a version number, seqlock-style.
+///
+/// Unlike [`FineGrainedListSet`](super::FineGrainedListSet), the `next` pointers are atomic so
+/// that they can be read without the locks, and the removed nodes are reclaimed with the
+/// epoch-based GC so that an optimistic reader never dereferences a freed node.
+///
+/// The writers count the modifications they start and finish. `contains` traverses the list
+/// without any lock, and the result is valid if no modification was in flight when it started and
+/// none started until it finished, as the list didn't change during the traversal then. Otherwise,
+/// it falls back to lock-coupling. So this is suited to read-mostly workloads: a read never blocks
+/// a write, but a read overlapping with a write does the work twice.
+#[derive(Debug)]
+pub struct VersionedListSet<T> {
+    head: Atomic<Node<T>>,
+    /// Protects `head`.
+    head_lock: Mutex<()>,
+    /// Number of the modifications started.
+    started: AtomicUsize,
+    /// Number of the modifications finished.
+    finished: AtomicUsize,
+}
+
+unsafe impl<T: Send> Send for VersionedListSet<T> {}
+unsafe impl<T: Send> Sync for VersionedListSet<T> {}
+
+/// Locked `next` pointer of the previous node (or the head), which points to the current node.
+struct Cursor<'g, T> {
+    _lock: MutexGuard<'g, ()>,
+    next: &'g Atomic<Node<T>>,
+}
+
+impl<T> VersionedListSet<T> {
+    /// Creates a new list.
+    pub fn new() -> Self {
+        Self {
+            head: Atomic::null(),
+            head_lock: Mutex::new(()),
+            started: AtomicUsize::new(0),
+            finished: AtomicUsize::new(0),
+        }
+    }
+
+    /// Runs the modification `f` while counting it.
+    fn modify<R>(&self, f: impl FnOnce() -> R) -> R {
+        let _ = self.started.fetch_add(1, SeqCst);
+        let result = f();
+        let _ = self.finished.fetch_add(1, SeqCst);
+        result
+    }
+}
+
+impl<T: Ord> VersionedListSet<T> {
+    /// Returns the locked cursor at the position of `key`, and whether the key was found.
+    fn find<'g>(&'g self, key: &T, guard: &'g Guard) -> (bool, Cursor<'g, T>) {
+        let mut cursor = Cursor {
+            _lock: self.head_lock.lock().unwrap(),
+            next: &self.head,
+        };
+        // 和FineGrainedListSet一样的lock-coupling，节点被锁住期间不会被删除
+        while let Some(node) = unsafe { cursor.next.load(Acquire, guard).as_ref() } {
+            match node.data.cmp(key) {
+                Less => {
+                    cursor = Cursor {
+                        _lock: node.lock.lock().unwrap(),
+                        next: &node.next,
+                    }
+                }
+                Equal => return (true, cursor),
+                Greater => break,
+            }
+        }
+        (false, cursor)
+    }
+
+    /// Searches for `key` without any lock. Returns `None` if a concurrent modification may have
+    /// invalidated the result.
+    fn contains_optimistic(&self, key: &T, guard: &Guard) -> Option<bool> {
+        // started == finished: 开始时没有进行中的修改
+        let finished = self.finished.load(SeqCst);
+        let started = self.started.load(SeqCst);
+        if started != finished {
+            return None;
+        }
+
+        let mut found = false;
+        let mut curr = self.head.load(Acquire, guard);
+        // SAFETY: The removed nodes are not reclaimed while `guard` is alive.
+        while let Some(node) = unsafe { curr.as_ref() } {
+            match node.data.cmp(key) {
+                Less => curr = node.next.load(Acquire, guard),
+                Equal => {
+                    found = true;
+                    break;
+                }
+                Greater => break,
+            }
+        }
+
+        // 期间没有开始新的修改，读到的就是一个静止的链表
+        (self.started.load(SeqCst) == started).then_some(found)
+    }
+}
+
+impl<T: Ord> ConcurrentSet<T> for VersionedListSet<T> {
+    fn contains(&self, key: &T) -> bool {
+        let guard = &pin();
+        if let Some(found) = self.contains_optimistic(key, guard) {
+            return found;
+        }
+        let (found, _cursor) = self.find(key, guard);
+        found
+    }
+
+    fn insert(&self, key: T) -> bool {
+        let guard = &pin();
+        let (found, cursor) = self.find(&key, guard);
+        if found {
+            return false;
+        }
+        let node = Owned::new(Node {
+            data: key,
+            next: Atomic::from(cursor.next.load(Relaxed, guard)),
+            lock: Mutex::new(()),
+        });
+        self.modify(|| cursor.next.store(node, Release));
+        true
+    }
+
+    fn remove(&self, key: &T) -> bool {
+        let guard = &pin();
+        let (found, cursor) = self.find(key, guard);
+        if !found {
+            return false;
+        }
+        let target = cursor.next.load(Relaxed, guard);
+        let target_ref = unsafe { target.deref() };
+        // 锁住目标节点，等待正在经过它的线程离开
+        let target_lock = target_ref.lock.lock().unwrap();
+        self.modify(|| {
+            cursor
+                .next
+                .store(target_ref.next.load(Relaxed, guard), Release)
+        });
+        drop(target_lock);
+        // SAFETY: The node is unlinked, and the optimistic readers that may still be reading it
+        // are protected by their guards.
+        unsafe { guard.defer_destroy(target) };
+        true
+    }
+}
+
+impl<T> Default for VersionedListSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for VersionedListSet<T> {
+    fn drop(&mut self) {
+        // SAFETY: We have the exclusive access to the list.
+        let guard = unsafe { unprotected() };
+        let mut curr = self.head.load(Relaxed, guard);
+        while !curr.is_null() {
+            let node = unsafe { curr.into_owned() };
+            curr = node.next.load(Relaxed, guard);
+        }
+    }
+}
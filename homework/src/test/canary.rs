@@ -0,0 +1,53 @@
+//! Utilities for checking that the elements of a data structure are dropped exactly once.
+
+use core::fmt;
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering::Relaxed;
+use std::sync::Arc;
+
+/// Counts the drops of the [`Canary`]s it creates.
+///
+/// The counter is shared with the canaries, so that they can outlive it, e.g. when they are moved
+/// to other threads or leaked by the data structure under test. It is built on the std
+/// primitives, so it works both inside and outside `loom::model`.
+#[derive(Clone)]
+pub struct DropCounter(Arc<AtomicUsize>);
+
+impl DropCounter {
+    /// Creates a counter with no drop.
+    pub fn new() -> Self {
+        Self(Arc::new(AtomicUsize::new(0)))
+    }
+
+    /// Creates a canary that increments this counter when dropped.
+    pub fn canary(&self) -> Canary {
+        Canary(self.0.clone())
+    }
+
+    /// Returns the number of the canaries dropped so far.
+    pub fn count(&self) -> usize {
+        self.0.load(Relaxed)
+    }
+}
+
+impl Default for DropCounter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for DropCounter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("DropCounter").field(&self.count()).finish()
+    }
+}
+
+/// Value that increments the counter of the [`DropCounter`] it is created by when dropped.
+#[derive(Debug)]
+pub struct Canary(Arc<AtomicUsize>);
+
+impl Drop for Canary {
+    fn drop(&mut self) {
+        let _ = self.0.fetch_add(1, Relaxed);
+    }
+}
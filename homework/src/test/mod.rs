@@ -2,7 +2,9 @@
 // <https://stackoverflow.com/a/44541071>
 
 pub mod adt;
+pub mod canary;
 pub mod loom;
 pub mod rand;
 
+pub use canary::{Canary, DropCounter};
 pub use rand::RandGen;
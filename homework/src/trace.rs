@@ -0,0 +1,17 @@
+//! Internal diagnostics.
+
+/// Prints a diagnostic message like `println!` if the `debug-trace` feature is enabled. Otherwise,
+/// expands to nothing, so the arguments are neither evaluated nor need to exist.
+#[cfg(feature = "debug-trace")]
+macro_rules! trace {
+    ($($arg:tt)*) => {
+        println!($($arg)*)
+    };
+}
+
+/// Prints a diagnostic message like `println!` if the `debug-trace` feature is enabled. Otherwise,
+/// expands to nothing, so the arguments are neither evaluated nor need to exist.
+#[cfg(not(feature = "debug-trace"))]
+macro_rules! trace {
+    ($($arg:tt)*) => {};
+}
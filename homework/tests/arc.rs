@@ -19,6 +19,7 @@ mod basic {
     use cs431_homework::test::loom::sync::atomic::Ordering::Relaxed;
     use cs431_homework::test::loom::sync::mpsc::channel;
     use cs431_homework::test::loom::thread;
+    use cs431_homework::test::DropCounter;
     use cs431_homework::Arc;
 
     use super::Canary;
@@ -116,6 +117,26 @@ mod basic {
         assert!(!Arc::ptr_eq(&five, &other_five));
     }
 
+    #[test]
+    fn test_content_eq() {
+        /// Panics if the contents are actually compared.
+        #[derive(Debug)]
+        struct NoEq;
+
+        impl PartialEq for NoEq {
+            fn eq(&self, _: &Self) -> bool {
+                panic!("compared the contents");
+            }
+        }
+
+        let a = Arc::new(NoEq);
+        assert!(Arc::content_eq(&a, &a.clone()));
+
+        let five = Arc::new(5);
+        assert!(Arc::content_eq(&five, &Arc::new(5)));
+        assert!(!Arc::content_eq(&five, &Arc::new(6)));
+    }
+
     #[test]
     fn test_try_unwrap_drop_once() {
         let canary = AtomicUsize::new(0);
@@ -146,6 +167,113 @@ mod basic {
         assert_eq!(Arc::count(&data3), 1);
     }
 
+    #[test]
+    fn test_weak_upgrade() {
+        let canary = AtomicUsize::new(0);
+        let x = Arc::new(Canary(&canary));
+        let weak = Arc::downgrade(&x);
+        assert_eq!(Arc::weak_count(&x), 1);
+
+        let y = weak.upgrade().unwrap();
+        assert!(Arc::ptr_eq(&x, &y));
+        assert_eq!(Arc::count(&x), 2);
+
+        drop(x);
+        drop(y);
+        assert_eq!(canary.load(Relaxed), 1);
+        assert!(weak.upgrade().is_none());
+        assert_eq!(weak.strong_count(), 0);
+    }
+
+    #[test]
+    fn test_weak_blocks_unique() {
+        let mut x = Arc::new(3);
+        let weak = Arc::downgrade(&x);
+        assert!(Arc::get_mut(&mut x).is_none());
+        drop(weak);
+        assert!(Arc::get_mut(&mut x).is_some());
+    }
+
+    #[test]
+    fn test_try_unwrap_with_weak() {
+        let canary = AtomicUsize::new(0);
+        let x = Arc::new(Canary(&canary));
+        let weak = Arc::downgrade(&x);
+        let inner = Arc::try_unwrap(x).ok().unwrap();
+        assert!(weak.upgrade().is_none());
+        assert_eq!(canary.load(Relaxed), 0);
+        drop(inner);
+        assert_eq!(canary.load(Relaxed), 1);
+    }
+
+    #[test]
+    fn test_weak_outlives_value() {
+        let drops = DropCounter::new();
+        let x = Arc::new(drops.canary());
+        let weak = Arc::downgrade(&x);
+        drop(x);
+        assert_eq!(drops.count(), 1);
+        // The value is dropped with the last `Arc`, not again with the last `Weak`.
+        drop(weak);
+        assert_eq!(drops.count(), 1);
+    }
+
+    #[test]
+    fn test_zst() {
+        let a = Arc::new(());
+        let b = a.clone();
+        assert!(Arc::ptr_eq(&a, &b));
+        assert_eq!(Arc::count(&a), 2);
+        assert_eq!(*b, ());
+        drop(a);
+        assert!(Arc::try_unwrap(b).is_ok());
+
+        let a = Arc::new([0u8; 0]);
+        let b = a.clone();
+        assert!(b.is_empty());
+        assert_eq!(Arc::count(&a), 2);
+        drop(b);
+        assert_eq!(Arc::count(&a), 1);
+        drop(a);
+
+        // The reference to a zero-sized value is still aligned for its type, which may be more
+        // aligned than the counts.
+        #[repr(align(64))]
+        struct Aligned;
+
+        let a = Arc::new(Aligned);
+        assert_eq!(
+            (&*a as *const Aligned as usize) % std::mem::align_of::<Aligned>(),
+            0
+        );
+
+        // Each `Arc` has its own allocation even if `T` is zero-sized.
+        let a = Arc::new(());
+        let b = Arc::new(());
+        assert!(!Arc::ptr_eq(&a, &b));
+    }
+
+    #[test]
+    fn test_zst_drop_once() {
+        static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+        struct Zst;
+
+        impl Drop for Zst {
+            fn drop(&mut self) {
+                let _ = DROPPED.fetch_add(1, Relaxed);
+            }
+        }
+
+        let a = Arc::new(Zst);
+        let b = a.clone();
+        let weak = Arc::downgrade(&a);
+        drop(a);
+        drop(b);
+        assert_eq!(DROPPED.load(Relaxed), 1);
+        assert!(weak.upgrade().is_none());
+    }
+
     #[test]
     fn test_stress() {
         let count = Arc::new(AtomicUsize::new(0));
@@ -164,6 +292,125 @@ mod basic {
         }
         assert_eq!(count.load(Relaxed), 8 * 128);
     }
+
+    #[test]
+    fn test_std_round_trip() {
+        /// Counts the clones.
+        struct Value<'c>(usize, &'c AtomicUsize);
+
+        impl Clone for Value<'_> {
+            fn clone(&self) -> Self {
+                let _ = self.1.fetch_add(1, Relaxed);
+                Value(self.0, self.1)
+            }
+        }
+
+        let clones = AtomicUsize::new(0);
+
+        // unique: moved without cloning
+        let std = Arc::into_std(Arc::new(Value(1, &clones)));
+        assert_eq!(std::sync::Arc::strong_count(&std), 1);
+        let arc = Arc::from_std(std);
+        assert_eq!(arc.0, 1);
+        assert_eq!(Arc::count(&arc), 1);
+        assert_eq!(clones.load(Relaxed), 0);
+
+        // shared: cloned, and the other references are intact
+        let shared = arc.clone();
+        let std = Arc::into_std(arc);
+        assert_eq!(clones.load(Relaxed), 1);
+        assert_eq!(Arc::count(&shared), 1);
+        let std_shared = std.clone();
+        let arc = Arc::from_std(std);
+        assert_eq!(clones.load(Relaxed), 2);
+        assert_eq!(std::sync::Arc::strong_count(&std_shared), 1);
+        assert_eq!((arc.0, shared.0, std_shared.0), (1, 1, 1));
+    }
+
+    #[test]
+    fn test_slice_drop_order() {
+        use std::sync::Mutex;
+
+        /// Records its index when dropped.
+        struct Recorder<'a>(usize, &'a Mutex<Vec<usize>>);
+
+        impl Drop for Recorder<'_> {
+            fn drop(&mut self) {
+                self.1.lock().unwrap().push(self.0);
+            }
+        }
+
+        let order = Mutex::new(Vec::new());
+        let slice: Arc<[Recorder<'_>]> =
+            Arc::from((0..5).map(|i| Recorder(i, &order)).collect::<Vec<_>>());
+        let clone = slice.clone();
+        let weak = Arc::downgrade(&slice);
+        assert_eq!(slice.len(), 5);
+        assert_eq!(clone[3].0, 3);
+
+        drop(slice);
+        assert!(order.lock().unwrap().is_empty());
+        drop(clone);
+        assert_eq!(*order.lock().unwrap(), [0, 1, 2, 3, 4]);
+        assert!(weak.upgrade().is_none());
+    }
+
+    #[test]
+    #[ignore = "the GrowableArray homework (`get` and `Drop`) is not done yet"]
+    fn test_raw_weak_registry() {
+        use std::sync::atomic::{AtomicPtr, Ordering};
+
+        use crossbeam_epoch::{pin, Owned};
+        use cs431_homework::GrowableArray;
+
+        // Registry of type-erased weak handles, indexed by id.
+        let registry = GrowableArray::<AtomicPtr<()>>::new();
+        let guard = pin();
+        let values = (0..8).map(|i| Arc::new(i.to_string())).collect::<Vec<_>>();
+        for (id, value) in values.iter().enumerate() {
+            let handle = Arc::clone_to_raw_weak(value);
+            registry.try_get(id, &guard).unwrap().store(
+                Owned::new(AtomicPtr::new(handle.cast_mut())),
+                Ordering::Release,
+            );
+            assert_eq!(Arc::weak_count(value), 1);
+            assert_eq!(Arc::count(value), 1);
+        }
+
+        // Drop the strong references of the odd ids.
+        let values = values
+            .into_iter()
+            .enumerate()
+            .filter(|(id, _)| id % 2 == 0)
+            .collect::<Vec<_>>();
+        for id in 0..8 {
+            let slot = registry
+                .try_get(id, &guard)
+                .unwrap()
+                .load(Ordering::Acquire, &guard);
+            let handle = unsafe { slot.deref() }.load(Ordering::Relaxed);
+            let weak = unsafe { Arc::<String>::from_raw_weak(handle) };
+            match weak.upgrade() {
+                Some(value) => assert_eq!(*value, id.to_string()),
+                None => assert_eq!(id % 2, 1),
+            }
+            assert_eq!(weak.upgrade().is_some(), id % 2 == 0);
+            // Releases the weak reference of the handle.
+            drop(weak);
+            drop(unsafe { slot.into_owned() });
+        }
+        for (_, value) in values {
+            assert_eq!(Arc::weak_count(&value), 0);
+        }
+    }
+
+    #[test]
+    fn test_slice_empty() {
+        let slice: Arc<[String]> = Arc::from(Vec::new());
+        assert!(slice.is_empty());
+        let slice: Arc<[()]> = Arc::from(vec![(); 3]);
+        assert_eq!(slice.len(), 3);
+    }
 }
 
 mod correctness {
@@ -211,6 +458,38 @@ mod correctness {
         })
     }
 
+    #[cfg(feature = "check-loom")]
+    #[test]
+    /// value:=123 → count:=1 → uniqueness check → (scheduling point) → get_mut success
+    ///
+    /// The clone may be dropped right before the check, and the other thread runs on at the
+    /// check, so the value must already be visible there.
+    fn get_mut_observed_sync() {
+        model(|| {
+            let mut value = Arc::new(AtomicUsize::new(0));
+            let handle = {
+                let value = value.clone();
+                thread::spawn(move || {
+                    value.store(123, Relaxed);
+                })
+            };
+            let observed = AtomicUsize::new(0);
+            let result = Arc::get_mut_observed(&mut value, || {
+                thread::yield_now();
+                observed.store(1, Relaxed);
+            });
+            match result {
+                Some(val) => {
+                    assert_eq!(observed.load(Relaxed), 1);
+                    assert_eq!(val.load(Relaxed), 123);
+                }
+                None => assert_eq!(observed.load(Relaxed), 0),
+            }
+            handle.join().unwrap();
+            assert!(Arc::get_mut_observed(&mut value, || {}).is_some());
+        })
+    }
+
     #[test]
     /// value:=123 → count:=1 → try_unwrap success
     fn try_unwrap_sync() {
@@ -249,6 +528,33 @@ mod correctness {
         })
     }
 
+    #[test]
+    /// data:=1 → release decrement → acquire in the last drop → destructor sees data==1
+    ///
+    /// Fails if `drop` decrements with `Relaxed` without a fence, as the destructor may run in the
+    /// main thread without seeing the write of the spawned thread.
+    fn drop_release_acquire() {
+        struct Reader(Arc<AtomicUsize>);
+
+        impl Drop for Reader {
+            fn drop(&mut self) {
+                assert_eq!(self.0.load(Relaxed), 1);
+            }
+        }
+
+        model(|| {
+            let data = Arc::new(AtomicUsize::new(0));
+            let arc1 = Arc::new(Reader(data.clone()));
+            let arc2 = arc1.clone();
+            let handle = thread::spawn(move || {
+                data.store(1, Relaxed);
+                drop(arc1);
+            });
+            drop(arc2);
+            handle.join().unwrap();
+        })
+    }
+
     #[test]
     /// Resistance against arbitrary interleaving of instructions in `clone` and `drop`.
     fn clone_drop_atomic() {
@@ -0,0 +1,137 @@
+#[cfg(not(feature = "check-loom"))]
+mod basic {
+    use std::thread::scope;
+
+    use cs431_homework::test::DropCounter;
+    use cs431_homework::ArcLinkedList;
+
+    /// Pops all the values from the front.
+    fn drain<T: Copy>(list: &ArcLinkedList<T>) -> Vec<T> {
+        std::iter::from_fn(|| list.pop_front().map(|node| *node.value())).collect()
+    }
+
+    #[test]
+    fn push_pop_both_ends() {
+        let list = ArcLinkedList::new();
+        assert!(list.is_empty());
+        assert!(list.pop_front().is_none());
+        assert!(list.pop_back().is_none());
+
+        for i in 0..3 {
+            let _ = list.push_back(i);
+            let _ = list.push_front(-i - 1);
+        }
+        assert_eq!(list.len(), 6);
+        assert_eq!(*list.front().unwrap().value(), -3);
+        assert_eq!(*list.back().unwrap().value(), 2);
+
+        assert_eq!(*list.pop_back().unwrap().value(), 2);
+        assert_eq!(*list.pop_front().unwrap().value(), -3);
+        assert_eq!(drain(&list), [-2, -1, 0, 1]);
+        assert!(list.front().is_none() && list.back().is_none());
+
+        // The list is usable after it is emptied.
+        let _ = list.push_front(7);
+        assert_eq!(*list.pop_back().unwrap().value(), 7);
+        assert!(list.is_empty());
+    }
+
+    #[test]
+    fn remove_through_handle() {
+        let list = ArcLinkedList::new();
+        let nodes = (0..5).map(|i| list.push_back(i)).collect::<Vec<_>>();
+
+        // Middle, head, and tail.
+        assert!(list.remove(&nodes[2]));
+        assert!(!nodes[2].is_linked());
+        assert!(list.remove(&nodes[0]));
+        assert!(list.remove(&nodes[4]));
+        assert_eq!(list.len(), 2);
+        assert_eq!(*list.front().unwrap().value(), 1);
+        assert_eq!(*list.back().unwrap().value(), 3);
+
+        // Already removed, or popped.
+        assert!(!list.remove(&nodes[2]));
+        assert_eq!(*list.pop_front().unwrap().value(), 1);
+        assert!(!list.remove(&nodes[1]));
+
+        // Linked in another list.
+        let other = ArcLinkedList::new();
+        let foreign = other.push_back(10);
+        assert!(!list.remove(&foreign));
+        assert!(foreign.is_linked());
+
+        // The removed node can't reach the list anymore, while the rest is still linked.
+        let _ = list.push_front(0);
+        let _ = list.push_back(4);
+        assert_eq!(drain(&list), [0, 3, 4]);
+    }
+
+    #[test]
+    fn no_leak() {
+        let count = DropCounter::new();
+        let list = ArcLinkedList::new();
+        let handles = (0..10)
+            .map(|_| list.push_back(count.canary()))
+            .collect::<Vec<_>>();
+        assert!(list.remove(&handles[5]));
+        drop(list.pop_front());
+        assert_eq!(count.count(), 0);
+
+        // Under Miri, a node kept alive by a cycle of the links is reported as a leak. The `prev`
+        // pointers are weak, so each node is freed once neither the list nor a handle holds it.
+        drop(handles);
+        assert_eq!(count.count(), 2);
+        drop(list);
+        assert_eq!(count.count(), 10);
+
+        // Handles outliving the list.
+        let list = ArcLinkedList::new();
+        let handles = (0..10)
+            .map(|_| list.push_back(count.canary()))
+            .collect::<Vec<_>>();
+        drop(list);
+        assert!(handles.iter().all(|node| !node.is_linked()));
+        assert_eq!(count.count(), 10);
+        drop(handles);
+        assert_eq!(count.count(), 20);
+    }
+
+    #[test]
+    fn long_list_drop() {
+        let list = ArcLinkedList::new();
+        for i in 0..if cfg!(miri) { 1_000 } else { 100_000 } {
+            let _ = list.push_back(i);
+        }
+    }
+
+    #[test]
+    fn concurrent_push_remove() {
+        const THREADS: usize = 4;
+        const STEPS: usize = 1_000;
+
+        let list = ArcLinkedList::new();
+        scope(|s| {
+            for t in 0..THREADS {
+                let list = &list;
+                let _ = s.spawn(move || {
+                    for i in 0..STEPS {
+                        let node = match i % 2 {
+                            0 => list.push_front(t * STEPS + i),
+                            _ => list.push_back(t * STEPS + i),
+                        };
+                        // Keep every fourth value.
+                        if i % 4 >= 2 {
+                            assert!(list.remove(&node));
+                        }
+                    }
+                });
+            }
+        });
+        let mut values = drain(&list);
+        values.sort_unstable();
+        assert!(values
+            .into_iter()
+            .eq((0..THREADS * STEPS).filter(|v| v % STEPS % 4 < 2)));
+    }
+}
@@ -0,0 +1,147 @@
+use cs431_homework::test::DropCounter;
+use cs431_homework::ArcStack;
+
+#[cfg(not(feature = "check-loom"))]
+mod basic {
+    use std::thread::scope;
+
+    use cs431_homework::ArcStack;
+
+    use super::*;
+
+    #[test]
+    fn push_pop() {
+        let stack = ArcStack::new();
+        assert!(stack.is_empty());
+        assert_eq!(stack.pop(), None);
+        for i in 0..10 {
+            stack.push(i);
+        }
+        assert!(!stack.is_empty());
+        for i in (0..10).rev() {
+            assert_eq!(stack.pop(), Some(i));
+        }
+        assert_eq!(stack.pop(), None);
+    }
+
+    #[test]
+    fn drop_values() {
+        let count = DropCounter::new();
+        let stack = ArcStack::new();
+        for _ in 0..10 {
+            stack.push(count.canary());
+        }
+        drop(stack.pop());
+        assert_eq!(count.count(), 1);
+        drop(stack);
+        assert_eq!(count.count(), 10);
+    }
+
+    #[test]
+    fn drop_values_concurrent() {
+        const THREADS: usize = 4;
+        const STEPS: usize = 1000;
+
+        let count = DropCounter::new();
+        let stack = ArcStack::new();
+        scope(|s| {
+            for _ in 0..THREADS {
+                let count = count.clone();
+                let stack = &stack;
+                let _ = s.spawn(move || {
+                    for _ in 0..STEPS {
+                        stack.push(count.canary());
+                        stack.push(count.canary());
+                        drop(stack.pop());
+                    }
+                });
+            }
+        });
+        assert_eq!(count.count(), THREADS * STEPS);
+        drop(stack);
+        assert_eq!(count.count(), 2 * THREADS * STEPS);
+    }
+
+    #[test]
+    fn stress_concurrent() {
+        const THREADS: usize = 16;
+        const STEPS: usize = 10_000;
+
+        let stack = ArcStack::new();
+        let popped = scope(|s| {
+            let handles = (0..THREADS)
+                .map(|t| {
+                    let stack = &stack;
+                    s.spawn(move || {
+                        let mut popped = Vec::new();
+                        for i in 0..STEPS {
+                            stack.push(t * STEPS + i);
+                            popped.extend(stack.pop());
+                        }
+                        popped
+                    })
+                })
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+        assert!(stack.is_empty());
+
+        let mut popped = popped;
+        popped.sort_unstable();
+        assert_eq!(popped, (0..THREADS * STEPS).collect::<Vec<_>>());
+    }
+}
+
+mod correctness {
+    use cs431_homework::test::loom::sync::Arc;
+    use cs431_homework::test::loom::{model, thread};
+
+    use super::*;
+
+    #[test]
+    /// Concurrent pushes and pops neither lose nor duplicate a value.
+    fn push_pop_concurrent() {
+        model(|| {
+            let stack = Arc::new(ArcStack::new());
+            stack.push(0);
+            let handles = [1, 2]
+                .map(|value| {
+                    let stack = stack.clone();
+                    thread::spawn(move || {
+                        stack.push(value);
+                        stack.pop()
+                    })
+                })
+                .map(|handle| handle.join().unwrap());
+
+            let mut values = handles.into_iter().flatten().collect::<Vec<_>>();
+            values.extend(stack.pop());
+            assert_eq!(stack.pop(), None);
+            values.sort_unstable();
+            assert_eq!(values, [0, 1, 2]);
+        })
+    }
+
+    #[test]
+    /// Concurrent pops of the same node drop each value exactly once.
+    fn pop_concurrent() {
+        model(|| {
+            let count = DropCounter::new();
+            let stack = Arc::new(ArcStack::new());
+            stack.push(count.canary());
+            stack.push(count.canary());
+            let handles = [(); 2].map(|_| {
+                let stack = stack.clone();
+                thread::spawn(move || stack.pop().is_some())
+            });
+            for handle in handles {
+                assert!(handle.join().unwrap());
+            }
+            assert_eq!(count.count(), 2);
+            assert!(stack.is_empty());
+        })
+    }
+}
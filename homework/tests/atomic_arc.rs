@@ -0,0 +1,133 @@
+use crossbeam_epoch::Collector;
+use cs431_homework::test::DropCounter;
+use cs431_homework::{Arc, AtomicArc};
+
+#[cfg(not(feature = "check-loom"))]
+mod basic {
+    use std::thread::scope;
+
+    use super::*;
+
+    #[test]
+    fn load_store_cas() {
+        let collector = Collector::new();
+        let handle = collector.register();
+        let pinned = handle.pin();
+        let guard = &pinned;
+
+        let first = Arc::new(1);
+        let slot = AtomicArc::new(first.clone());
+        assert_eq!(Arc::count(&first), 2);
+        let loaded = slot.load(guard);
+        assert!(Arc::ptr_eq(&loaded, &first));
+        assert_eq!(Arc::count(&first), 3);
+        drop(loaded);
+
+        let second = Arc::new(2);
+        let rejected = slot.compare_exchange(&second, Arc::new(3), guard);
+        assert_eq!(*rejected.unwrap_err(), 3);
+        assert!(slot.compare_exchange(&first, second.clone(), guard).is_ok());
+        assert!(Arc::ptr_eq(&slot.load(guard), &second));
+        slot.store(Arc::new(4), guard);
+        assert_eq!(*slot.load(guard), 4);
+
+        // The references of the slot to the replaced values are released with the collector.
+        drop((pinned, handle, collector));
+        assert_eq!(Arc::count(&first), 1);
+        assert_eq!(Arc::count(&second), 1);
+        assert_eq!(Arc::count(&slot.into_inner()), 1);
+    }
+
+    #[test]
+    fn stress() {
+        const THREADS: usize = 8;
+        const STEPS: usize = 1000;
+
+        let count = DropCounter::new();
+        let collector = Collector::new();
+        let slot = AtomicArc::new(Arc::new((0, count.canary())));
+        scope(|s| {
+            for thread in 0..THREADS {
+                let collector = &collector;
+                let slot = &slot;
+                let count = &count;
+                let _ = s.spawn(move || {
+                    let handle = collector.register();
+                    for step in 0..STEPS {
+                        let guard = &handle.pin();
+                        let current = slot.load(guard);
+                        let new = Arc::new((thread * STEPS + step + 1, count.canary()));
+                        match thread % 2 {
+                            0 => slot.store(new, guard),
+                            _ => {
+                                let _ = slot.compare_exchange(&current, new, guard);
+                            }
+                        }
+                        // The loaded value is alive even if it is replaced meanwhile.
+                        assert!(current.0 <= THREADS * STEPS);
+                    }
+                });
+            }
+        });
+        drop(slot);
+        drop(collector);
+        // The values rejected by `compare_exchange` are dropped right away, and all the others
+        // once they are replaced, so every value is dropped exactly once.
+        assert_eq!(count.count(), THREADS * STEPS + 1);
+    }
+}
+
+mod correctness {
+    use cs431_homework::test::loom::sync::Arc as LoomArc;
+    use cs431_homework::test::loom::{model, thread};
+
+    use super::*;
+
+    #[test]
+    /// A load concurrent with a store or a CAS gets either value, which is still alive, and every
+    /// value is dropped exactly once after all the references are gone.
+    fn load_store_cas_concurrent() {
+        model(|| {
+            let count = DropCounter::new();
+            let collector = Collector::new();
+            let slot = LoomArc::new(AtomicArc::new(Arc::new((0, count.canary()))));
+
+            let storer = {
+                let (collector, slot, count) = (collector.clone(), slot.clone(), count.clone());
+                thread::spawn(move || {
+                    let handle = collector.register();
+                    slot.store(Arc::new((1, count.canary())), &handle.pin());
+                })
+            };
+            let swapper = {
+                let (collector, slot, count) = (collector.clone(), slot.clone(), count.clone());
+                thread::spawn(move || {
+                    let handle = collector.register();
+                    let guard = &handle.pin();
+                    let current = slot.load(guard);
+                    let swapped = slot
+                        .compare_exchange(&current, Arc::new((2, count.canary())), guard)
+                        .is_ok();
+                    (current.0, swapped)
+                })
+            };
+            let handle = collector.register();
+            let loaded = slot.load(&handle.pin());
+            assert!([0, 1, 2].contains(&loaded.0));
+
+            storer.join().unwrap();
+            let (current, swapped) = swapper.join().unwrap();
+            assert!([0, 1].contains(&current));
+            let last = slot.load(&handle.pin());
+            assert_eq!(last.0, if swapped && current == 1 { 2 } else { 1 });
+            assert_eq!(
+                Arc::count(&last),
+                2 + usize::from(Arc::ptr_eq(&loaded, &last))
+            );
+
+            drop((loaded, last, handle, collector));
+            drop(LoomArc::try_unwrap(slot).unwrap());
+            assert_eq!(count.count(), 3);
+        })
+    }
+}
@@ -0,0 +1,64 @@
+use std::thread::scope;
+
+use cs431_homework::ConcurrentBitmap;
+
+#[test]
+#[ignore = "the GrowableArray homework (`get` and `Drop`) is not done yet"]
+fn smoke() {
+    let bitmap = ConcurrentBitmap::new();
+    assert!(!bitmap.test(0));
+    assert!(!bitmap.set(0));
+    assert!(bitmap.set(0));
+    assert!(!bitmap.set(63));
+    assert!(!bitmap.set(64));
+    assert!(!bitmap.set(usize::MAX));
+    assert!(bitmap.test(0));
+    assert!(bitmap.test(63));
+    assert!(bitmap.test(64));
+    assert!(!bitmap.test(65));
+    assert!(bitmap.test(usize::MAX));
+    assert_eq!(bitmap.count_ones(), 4);
+
+    assert!(bitmap.clear(63));
+    assert!(!bitmap.clear(63));
+    assert!(!bitmap.test(63));
+    assert!(bitmap.test(0));
+    assert_eq!(bitmap.count_ones(), 3);
+}
+
+#[test]
+#[ignore = "the GrowableArray homework (`get` and `Drop`) is not done yet"]
+fn stress_concurrent() {
+    const THREADS: usize = 16;
+    const BITS: usize = 64 * 64;
+    const ROUNDS: usize = 20;
+
+    // The threads own interleaved bits, so every word is shared by all the threads.
+    let bitmap = ConcurrentBitmap::new();
+    scope(|s| {
+        for t in 0..THREADS {
+            let bitmap = &bitmap;
+            let _ = s.spawn(move || {
+                let own = (t..BITS).step_by(THREADS);
+                for _ in 0..ROUNDS {
+                    for i in own.clone() {
+                        assert!(!bitmap.set(i));
+                    }
+                    for i in own.clone() {
+                        assert!(bitmap.test(i));
+                        assert!(bitmap.clear(i));
+                    }
+                }
+                // Leave the bits at the odd multiples of the thread count set.
+                for i in own.filter(|i| (i / THREADS) % 2 == 1) {
+                    assert!(!bitmap.set(i));
+                }
+            });
+        }
+    });
+
+    for i in 0..BITS {
+        assert_eq!(bitmap.test(i), (i / THREADS) % 2 == 1);
+    }
+    assert_eq!(bitmap.count_ones(), BITS / 2);
+}
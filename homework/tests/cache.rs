@@ -1,5 +1,11 @@
-use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::Barrier;
+// The tests outside `correctness` use the std primitives, so they are compiled out under loom.
+#![cfg_attr(feature = "check-loom", allow(dead_code, unused_imports))]
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::BuildHasherDefault;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Barrier, Mutex};
 use std::thread::scope;
 use std::time::Duration;
 
@@ -9,6 +15,7 @@ use cs431_homework::hello_server::Cache;
 const NUM_THREADS: usize = 8;
 const NUM_KEYS: usize = 128;
 
+#[cfg(not(feature = "check-loom"))]
 #[test]
 fn cache_no_duplicate_sequential() {
     let cache = Cache::default();
@@ -20,6 +27,7 @@ fn cache_no_duplicate_sequential() {
     assert_eq!(cache.get_or_insert_with(3, |_| panic!()), 3);
 }
 
+#[cfg(not(feature = "check-loom"))]
 #[test]
 fn cache_no_duplicate_concurrent() {
     for _ in 0..8 {
@@ -44,6 +52,7 @@ fn cache_no_duplicate_concurrent() {
     }
 }
 
+#[cfg(not(feature = "check-loom"))]
 #[test]
 fn cache_no_block_disjoint() {
     let cache = &Cache::default();
@@ -76,6 +85,7 @@ fn cache_no_block_disjoint() {
     });
 }
 
+#[cfg(not(feature = "check-loom"))]
 #[test]
 fn cache_no_reader_block() {
     let cache = &Cache::default();
@@ -111,3 +121,641 @@ fn cache_no_reader_block() {
         t1_quit_sender.send(()).unwrap();
     });
 }
+
+#[cfg(not(feature = "check-loom"))]
+#[test]
+fn cache_reentrant_different_key() {
+    // A single shard, so that both keys share the shard lock.
+    let cache = &Cache::with_shards(1);
+
+    scope(|s| {
+        let (done_sender, done_receiver) = bounded(0);
+        let _ = s.spawn(move || {
+            let a = cache.get_or_insert_with("a", |_| {
+                // The value of "a" depends on "b".
+                cache.get_or_insert_with("b", |_| 1) + 1
+            });
+            done_sender.send(a).unwrap();
+        });
+
+        let a = done_receiver
+            .recv_timeout(Duration::from_secs(3))
+            .expect("Computing a different key in `f` should not deadlock");
+        assert_eq!(a, 2);
+    });
+    assert_eq!(cache.get_or_insert_with("a", |_| panic!()), 2);
+    assert_eq!(cache.get_or_insert_with("b", |_| panic!()), 1);
+}
+
+#[cfg(not(feature = "check-loom"))]
+#[test]
+fn cache_export_snapshot() {
+    let cache = &Cache::default();
+    assert_eq!(cache.get_or_insert_with(1, |k| k * 10), 10);
+
+    scope(|s| {
+        let (computing_sender, computing_receiver) = bounded(0);
+        let (quit_sender, quit_receiver) = bounded(0);
+        let _ = s.spawn(move || {
+            cache.get_or_insert_with(2, |k| {
+                computing_sender.send(()).unwrap();
+                quit_receiver.recv().unwrap();
+                k * 10
+            })
+        });
+        computing_receiver.recv().unwrap();
+
+        let (snapshot_sender, snapshot_receiver) = bounded(0);
+        let _ = s.spawn(move || snapshot_sender.send(cache.export_snapshot()).unwrap());
+        let mut snapshot = snapshot_receiver
+            .recv_timeout(Duration::from_secs(3))
+            .expect("Snapshot should not wait for the computation");
+        snapshot.sort();
+        assert_eq!(snapshot, [(1, Some(10)), (2, None)]);
+
+        quit_sender.send(()).unwrap();
+    });
+
+    let mut snapshot = cache.export_snapshot();
+    snapshot.sort();
+    assert_eq!(snapshot, [(1, Some(10)), (2, Some(20))]);
+}
+
+#[cfg(not(feature = "check-loom"))]
+#[test]
+fn cache_in_flight_keys() {
+    let cache = &Cache::default();
+    assert_eq!(cache.get_or_insert_with(0, |k| k), 0);
+    assert!(cache.in_flight_keys().is_empty());
+
+    scope(|s| {
+        let (computing_sender, computing_receiver) = bounded(0);
+        let (quit_sender, quit_receiver) = bounded::<()>(0);
+        for key in [1, 2] {
+            let computing_sender = computing_sender.clone();
+            let quit_receiver = quit_receiver.clone();
+            let _ = s.spawn(move || {
+                cache.get_or_insert_with(key, |k| {
+                    computing_sender.send(()).unwrap();
+                    // Blocks until `quit_sender` is dropped.
+                    let _ = quit_receiver.recv();
+                    k
+                })
+            });
+        }
+        computing_receiver.recv().unwrap();
+        computing_receiver.recv().unwrap();
+
+        let mut keys = cache.in_flight_keys();
+        keys.sort_unstable();
+        assert_eq!(keys, [1, 2]);
+        drop(quit_sender);
+    });
+
+    assert!(cache.in_flight_keys().is_empty());
+}
+
+#[cfg(not(feature = "check-loom"))]
+#[test]
+fn cache_scope() {
+    const THREADS: usize = 8;
+
+    let cache = Cache::default();
+    let computed = AtomicUsize::new(0);
+    let barrier = Barrier::new(THREADS);
+    let values = cache.scope(|s, cache| {
+        let handles: Vec<_> = (0..THREADS)
+            .map(|_| {
+                s.spawn(|| {
+                    let _ = barrier.wait();
+                    cache.get_or_insert_with(1, |k| {
+                        let _ = computed.fetch_add(1, Ordering::Relaxed);
+                        k * 10
+                    })
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|h| h.join().unwrap())
+            .collect::<Vec<_>>()
+    });
+    assert_eq!(values, [10; THREADS]);
+    assert_eq!(computed.load(Ordering::Relaxed), 1);
+}
+
+#[cfg(not(feature = "check-loom"))]
+#[test]
+fn cache_optimistic() {
+    const THREADS: usize = 8;
+
+    let cache = Cache::default();
+    let computed = AtomicUsize::new(0);
+    let barrier = Barrier::new(THREADS);
+    let values = scope(|s| {
+        let handles: Vec<_> = (0..THREADS)
+            .map(|t| {
+                let (cache, computed, barrier) = (&cache, &computed, &barrier);
+                s.spawn(move || {
+                    cache.optimistic_get_or_insert_with(0, |_| {
+                        let _ = computed.fetch_add(1, Ordering::Relaxed);
+                        // All the threads compute before any of them installs its result.
+                        let _ = barrier.wait();
+                        t
+                    })
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|h| h.join().unwrap())
+            .collect::<Vec<_>>()
+    });
+    assert_eq!(computed.load(Ordering::Relaxed), THREADS);
+
+    // Every thread returns the installed result, which is the cached one.
+    let cached = cache.get_or_insert_with(0, |_| unreachable!());
+    assert_eq!(values, [cached; THREADS]);
+    assert_eq!(
+        cache.optimistic_get_or_insert_with(0, |_| unreachable!()),
+        cached
+    );
+}
+
+#[cfg(not(feature = "check-loom"))]
+#[test]
+fn cache_check() {
+    /// Value that must not be cloned by `check`.
+    #[derive(Debug)]
+    struct Page {
+        status: u16,
+        body: Vec<u8>,
+    }
+
+    impl Clone for Page {
+        fn clone(&self) -> Self {
+            assert!(!CHECKING.load(Ordering::Relaxed), "cloned by check");
+            Self {
+                status: self.status,
+                body: self.body.clone(),
+            }
+        }
+    }
+
+    static CHECKING: AtomicBool = AtomicBool::new(false);
+
+    let cache = Cache::default();
+    let page = cache.get_or_insert_with("index", |_| Page {
+        status: 200,
+        body: vec![0; 1 << 20],
+    });
+    assert_eq!(page.body.len(), 1 << 20);
+
+    CHECKING.store(true, Ordering::Relaxed);
+    assert!(cache.check(&"index", |page| page.is_some_and(|page| page.status == 200)));
+    assert_eq!(
+        cache.check(&"missing", |page| page.map(|page| page.status)),
+        None
+    );
+    CHECKING.store(false, Ordering::Relaxed);
+}
+
+#[cfg(not(feature = "check-loom"))]
+#[test]
+fn cache_recovers_from_poison() {
+    static PANIC_ON_CLONE: AtomicBool = AtomicBool::new(false);
+
+    #[derive(Debug, PartialEq)]
+    struct Value(usize);
+
+    impl Clone for Value {
+        fn clone(&self) -> Self {
+            assert!(
+                !PANIC_ON_CLONE.swap(false, Ordering::SeqCst),
+                "clone panicked"
+            );
+            Self(self.0)
+        }
+    }
+
+    let cache = Cache::default();
+    let computed = AtomicUsize::new(0);
+    let compute = |k| {
+        let _ = computed.fetch_add(1, Ordering::SeqCst);
+        Value(k)
+    };
+    assert_eq!(cache.get_or_insert_with(1, compute), Value(1));
+
+    // Cloning the cached value panics while its slot is locked, which poisons the lock.
+    PANIC_ON_CLONE.store(true, Ordering::SeqCst);
+    assert!(std::panic::catch_unwind(|| cache.get_or_insert_with(1, compute)).is_err());
+
+    // The poisoned slot is recomputed once, and then cached again.
+    assert_eq!(cache.get_or_insert_with(1, compute), Value(1));
+    assert_eq!(cache.get_or_insert_with(1, compute), Value(1));
+    assert_eq!(computed.load(Ordering::SeqCst), 2);
+}
+
+#[cfg(not(feature = "check-loom"))]
+#[test]
+fn cache_auto_sharded() {
+    let cache = Cache::<usize, usize>::auto_sharded();
+    let shards = cache.shard_count();
+    assert!(shards.is_power_of_two());
+    if let Ok(parallelism) = std::thread::available_parallelism() {
+        assert!(shards >= parallelism.get());
+    }
+
+    for key in 0..NUM_KEYS {
+        assert_eq!(cache.get_or_insert_with(key, |k| k), key);
+    }
+    for key in 0..NUM_KEYS {
+        assert_eq!(cache.get_or_insert_with(key, |_| panic!()), key);
+    }
+}
+
+#[cfg(not(feature = "check-loom"))]
+#[test]
+fn cache_shutdown() {
+    let cache = Cache::with_shards(4);
+    for key in 0..NUM_KEYS {
+        assert_eq!(cache.get_or_insert_with(key, |k| k * 2), key * 2);
+    }
+    // The computation for this key panics, so its entry is removed.
+    assert!(std::panic::catch_unwind(|| cache.get_or_insert_with(NUM_KEYS, |_| panic!())).is_err());
+
+    let mut entries = cache.shutdown().collect::<Vec<_>>();
+    entries.sort_unstable();
+    assert_eq!(entries.len(), NUM_KEYS);
+    for (key, value) in &entries {
+        assert_eq!(*value, Some(key * 2));
+    }
+}
+
+#[cfg(not(feature = "check-loom"))]
+#[test]
+fn cache_panic_retry() {
+    let cache = Cache::default();
+    assert!(std::panic::catch_unwind(|| cache.get_or_insert_with(1, |_| panic!())).is_err());
+    assert_eq!(cache.get_or_insert_with(1, |k| k), 1);
+
+    // A waiter for the panicking computation retries with its own `f`.
+    let (started_sender, started_receiver) = bounded(0);
+    let (quit_sender, quit_receiver) = bounded::<()>(0);
+    scope(|s| {
+        let computing = s.spawn(|| {
+            cache.get_or_insert_with(2, |_| {
+                started_sender.send(()).unwrap();
+                quit_receiver.recv().unwrap();
+                panic!()
+            })
+        });
+        started_receiver.recv().unwrap();
+
+        let waiter = s.spawn(|| cache.get_or_insert_with(2, |k| k * 10));
+        // Let the waiter block on the entry.
+        std::thread::sleep(Duration::from_millis(100));
+        quit_sender.send(()).unwrap();
+
+        assert!(computing.join().is_err());
+        assert_eq!(waiter.join().unwrap(), 20);
+    });
+    assert_eq!(cache.get_or_insert_with(2, |_| panic!()), 20);
+}
+
+/// Every return path of `get_or_insert_with` passes its debug check that the returned value is
+/// the computed value of the slot it found or claimed.
+#[cfg(not(feature = "check-loom"))]
+#[test]
+fn cache_return_paths() {
+    let cache = Cache::builder().ttl(Duration::from_millis(100)).build();
+    // Claimed and computed, then found by the read lookup.
+    assert_eq!(cache.get_or_insert_with(1, |k| k * 10), 10);
+    assert_eq!(cache.get_or_insert_with(1, |_| panic!()), 10);
+
+    // Expired, so claimed again and recomputed.
+    std::thread::sleep(Duration::from_millis(150));
+    assert_eq!(cache.get_or_insert_with(1, |k| k * 20), 20);
+
+    // Abandoned by a panic, so claimed again by the next call.
+    assert!(std::panic::catch_unwind(|| cache.get_or_insert_with(2, |_| panic!())).is_err());
+    assert_eq!(cache.get_or_insert_with(2, |k| k * 10), 20);
+
+    // Concurrent misses on the same keys: one call claims each key, and the others find the
+    // slot either by the read lookup or by the write lookup after missing the read lookup.
+    let barrier = Barrier::new(NUM_THREADS);
+    scope(|s| {
+        for _ in 0..NUM_THREADS {
+            let _ = s.spawn(|| {
+                let _ = barrier.wait();
+                for key in 100..100 + NUM_KEYS {
+                    assert_eq!(cache.get_or_insert_with(key, |k| k * 10), key * 10);
+                }
+            });
+        }
+    });
+}
+
+#[cfg(not(feature = "check-loom"))]
+#[test]
+fn cache_waiters_receive_value() {
+    const WAITERS: usize = 32;
+
+    let cache = Cache::default();
+    let num_compute = AtomicUsize::new(0);
+    let (started_sender, started_receiver) = bounded(0);
+    let (quit_sender, quit_receiver) = bounded::<()>(0);
+    scope(|s| {
+        let _ = s.spawn(|| {
+            cache.get_or_insert_with(1, |k| {
+                let _ = num_compute.fetch_add(1, Ordering::Relaxed);
+                started_sender.send(()).unwrap();
+                quit_receiver.recv().unwrap();
+                k * 10
+            })
+        });
+        started_receiver.recv().unwrap();
+
+        let waiters = (0..WAITERS)
+            .map(|_| s.spawn(|| cache.get_or_insert_with(1, |_| panic!())))
+            .collect::<Vec<_>>();
+        // Let the waiters block on the entry.
+        std::thread::sleep(Duration::from_millis(100));
+        quit_sender.send(()).unwrap();
+
+        for waiter in waiters {
+            assert_eq!(waiter.join().unwrap(), 10);
+        }
+    });
+    assert_eq!(num_compute.load(Ordering::Relaxed), 1);
+}
+
+#[cfg(not(feature = "check-loom"))]
+#[test]
+#[ignore = "run in a subprocess by `cache_trace_output`"]
+fn cache_trace_output_helper() {
+    let cache = Cache::default();
+    assert_eq!(cache.get_or_insert_with(1, |k| k), 1);
+    assert_eq!(cache.get_or_insert_with(1, |_| panic!()), 1);
+}
+
+#[cfg(not(feature = "check-loom"))]
+#[test]
+fn cache_trace_output() {
+    let output = std::process::Command::new(std::env::current_exe().unwrap())
+        .args([
+            "cache_trace_output_helper",
+            "--exact",
+            "--ignored",
+            "--nocapture",
+            "--quiet",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    // Everything other than the libtest's own report is printed by the cache.
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("running 1 test"), "{stdout}");
+    let traced = stdout.lines().any(|line| {
+        !(line.is_empty()
+            || line == "."
+            || line.starts_with("running ")
+            || line.starts_with("test result: "))
+    });
+    assert_eq!(traced, cfg!(feature = "debug-trace"), "{stdout}");
+}
+
+#[cfg(not(feature = "check-loom"))]
+#[test]
+fn cache_with_shards_init() {
+    const SHARDS: usize = 4;
+
+    let cache = Cache::with_shards_init(SHARDS, |shard| {
+        (0..NUM_KEYS)
+            .filter(|key| key % SHARDS == shard)
+            .map(|key| (key, key * 2))
+            .collect::<HashMap<_, _>>()
+    });
+    assert_eq!(cache.shard_count(), SHARDS);
+    for key in 0..NUM_KEYS {
+        assert_eq!(cache.get_or_insert_with(key, |_| panic!()), key * 2);
+    }
+    assert_eq!(cache.get_or_insert_with(NUM_KEYS, |k| k), NUM_KEYS);
+}
+
+#[cfg(not(feature = "check-loom"))]
+#[test]
+fn cache_reserve() {
+    const ENTRIES: usize = 10_000;
+
+    let cache = Cache::default();
+    cache.reserve(ENTRIES);
+    let capacity = cache.capacity();
+    assert!(capacity >= ENTRIES);
+    for key in 0..ENTRIES {
+        assert_eq!(cache.get_or_insert_with(key, |k| k), key);
+    }
+    // No shard reallocated while being filled.
+    assert_eq!(cache.capacity(), capacity);
+
+    let cache = Cache::<usize, usize>::with_shards(4);
+    cache.reserve(ENTRIES);
+    assert!(cache.capacity() >= ENTRIES);
+}
+
+#[cfg(not(feature = "check-loom"))]
+#[test]
+fn cache_for_each() {
+    /// A value that can't be cloned.
+    struct Value(usize);
+
+    let cache = Cache::with_shards_init(4, |shard| {
+        (0..NUM_KEYS)
+            .filter(|key| key % 4 == shard)
+            .map(|key| (key, Value(key)))
+            .collect::<HashMap<_, _>>()
+    });
+    let mut keys = 0;
+    let mut sum = 0;
+    cache.for_each(|key, value| {
+        assert_eq!(*key, value.0);
+        keys += 1;
+        sum += value.0;
+    });
+    assert_eq!(keys, NUM_KEYS);
+    assert_eq!(sum, NUM_KEYS * (NUM_KEYS - 1) / 2);
+}
+
+#[cfg(not(feature = "check-loom"))]
+#[test]
+fn cache_for_each_skips_in_flight() {
+    let cache = Cache::default();
+    assert_eq!(cache.get_or_insert_with(1, |k| k), 1);
+    let (started_sender, started_receiver) = bounded(0);
+    let (quit_sender, quit_receiver) = bounded::<()>(0);
+    scope(|s| {
+        let _ = s.spawn(|| {
+            cache.get_or_insert_with(2, |k| {
+                started_sender.send(()).unwrap();
+                quit_receiver.recv().unwrap();
+                k
+            })
+        });
+        started_receiver.recv().unwrap();
+
+        let mut entries = Vec::new();
+        cache.for_each(|key, value| entries.push((*key, *value)));
+        assert_eq!(entries, [(1, 1)]);
+        quit_sender.send(()).unwrap();
+    });
+}
+
+#[cfg(not(feature = "check-loom"))]
+#[test]
+fn cache_quiesce() {
+    let cache = Cache::default();
+    // Returns immediately if nothing is in flight.
+    cache.quiesce();
+    assert_eq!(cache.get_or_insert_with(1, |k| k), 1);
+    cache.quiesce();
+
+    let done = AtomicBool::new(false);
+    let (started_sender, started_receiver) = bounded(0);
+    scope(|s| {
+        let _ = s.spawn(|| {
+            cache.get_or_insert_with(2, |k| {
+                started_sender.send(()).unwrap();
+                std::thread::sleep(Duration::from_millis(200));
+                done.store(true, Ordering::Relaxed);
+                k
+            })
+        });
+        started_receiver.recv().unwrap();
+
+        let quiescer = s.spawn(|| {
+            cache.quiesce();
+            done.load(Ordering::Relaxed)
+        });
+        assert!(quiescer.join().unwrap());
+    });
+}
+
+#[cfg(not(feature = "check-loom"))]
+#[test]
+fn cache_builder() {
+    let evicted = Arc::new(Mutex::new(Vec::new()));
+    let cache = Cache::builder()
+        .shards(2)
+        .capacity(4)
+        .ttl(Duration::from_millis(200))
+        .hasher(BuildHasherDefault::<DefaultHasher>::default())
+        .on_evict({
+            let evicted = Arc::clone(&evicted);
+            move |key: &usize, value: &usize| evicted.lock().unwrap().push((*key, *value))
+        })
+        .build();
+    assert_eq!(cache.shard_count(), 2);
+
+    let computed = AtomicUsize::new(0);
+    let get = |key| {
+        cache.get_or_insert_with(key, |key| {
+            let _ = computed.fetch_add(1, Ordering::Relaxed);
+            key * 10
+        })
+    };
+
+    // Capacity: at most 2 entries per shard.
+    for key in 0..16 {
+        assert_eq!(get(key), key * 10);
+    }
+    let mut len = 0;
+    cache.for_each(|_, _| len += 1);
+    assert!(len <= 4);
+    assert_eq!(evicted.lock().unwrap().len(), 16 - len);
+    for (key, value) in evicted.lock().unwrap().iter() {
+        assert_eq!(*value, key * 10);
+    }
+
+    // TTL: a live entry is not recomputed, but an expired one is.
+    let mut live = 0;
+    cache.for_each(|key, _| live = *key);
+    let before = computed.load(Ordering::Relaxed);
+    assert_eq!(get(live), live * 10);
+    assert_eq!(computed.load(Ordering::Relaxed), before);
+    std::thread::sleep(Duration::from_millis(250));
+    evicted.lock().unwrap().clear();
+    assert_eq!(get(live), live * 10);
+    assert_eq!(computed.load(Ordering::Relaxed), before + 1);
+    assert_eq!(*evicted.lock().unwrap(), [(live, live * 10)]);
+}
+
+#[cfg(not(feature = "check-loom"))]
+#[test]
+fn cache_pin() {
+    let cache = Cache::builder().shards(1).capacity(4).build();
+    let _ = cache.get_or_insert_with(0, |k| k);
+    assert!(cache.pin(&0));
+    assert!(!cache.pin(&100));
+
+    // The pinned key is the oldest, but the others are evicted instead.
+    for key in 1..16 {
+        let _ = cache.get_or_insert_with(key, |k| k);
+    }
+    let mut keys = Vec::new();
+    cache.for_each(|key, _| keys.push(*key));
+    keys.sort_unstable();
+    assert_eq!(keys, [0, 13, 14, 15]);
+
+    // With all the entries pinned, the cache grows over its capacity.
+    for key in [13, 14, 15] {
+        assert!(cache.pin(&key));
+    }
+    let _ = cache.get_or_insert_with(16, |k| k);
+    let mut len = 0;
+    cache.for_each(|_, _| len += 1);
+    assert_eq!(len, 5);
+
+    // An unpinned key is evicted again, back down to the capacity.
+    assert!(cache.unpin(&0));
+    let _ = cache.get_or_insert_with(17, |k| k);
+    let mut keys = Vec::new();
+    cache.for_each(|key, _| keys.push(*key));
+    keys.sort_unstable();
+    assert_eq!(keys, [13, 14, 15, 17]);
+}
+
+#[cfg(feature = "check-loom")]
+mod correctness {
+    use cs431_homework::hello_server::Cache;
+    use cs431_homework::test::loom::sync::atomic::AtomicUsize;
+    use cs431_homework::test::loom::sync::atomic::Ordering::Relaxed;
+    use cs431_homework::test::loom::sync::Arc;
+    use cs431_homework::test::loom::{model, thread};
+
+    #[test]
+    /// A misses in the read lock → B inserts → A takes the write lock → A finds B's entry
+    ///
+    /// The model explores all the interleavings, including the one above. In every interleaving,
+    /// `f` is called only once and both threads return the same value.
+    fn read_then_write_window() {
+        model(|| {
+            let cache = Arc::new(Cache::default());
+            let num_compute = Arc::new(AtomicUsize::new(0));
+            let values = [1, 2]
+                .map(|value| {
+                    let cache = cache.clone();
+                    let num_compute = num_compute.clone();
+                    thread::spawn(move || {
+                        cache.get_or_insert_with(0, |_| {
+                            let _ = num_compute.fetch_add(1, Relaxed);
+                            value
+                        })
+                    })
+                })
+                .map(|handle| handle.join().unwrap());
+            assert_eq!(values[0], values[1]);
+            assert_eq!(num_compute.load(Relaxed), 1);
+        })
+    }
+}
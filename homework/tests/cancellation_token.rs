@@ -0,0 +1,108 @@
+use cs431_homework::CancellationToken;
+
+#[cfg(not(feature = "check-loom"))]
+mod basic {
+    use std::thread::scope;
+
+    use super::*;
+
+    #[test]
+    fn cancel_clones() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+        assert!(!token.is_cancelled());
+        assert!(!clone.is_cancelled());
+        clone.cancel();
+        assert!(token.is_cancelled());
+        assert!(clone.is_cancelled());
+        // idempotent
+        token.cancel();
+        assert!(token.is_cancelled());
+    }
+
+    #[test]
+    fn cancel_children() {
+        let root = CancellationToken::new();
+        let child = root.child();
+        let grandchild = child.child();
+        let sibling = root.child();
+
+        grandchild.cancel();
+        assert!(grandchild.is_cancelled());
+        assert!(!child.is_cancelled());
+        assert!(!root.is_cancelled());
+
+        root.cancel();
+        assert!(child.is_cancelled());
+        assert!(sibling.is_cancelled());
+        // The parent outlives its handle.
+        drop(root);
+        assert!(sibling.child().is_cancelled());
+    }
+
+    #[test]
+    fn cancel_across_threads() {
+        let token = CancellationToken::new();
+        scope(|s| {
+            let workers = (0..4)
+                .map(|_| {
+                    let token = token.child();
+                    s.spawn(move || {
+                        let mut spins = 0usize;
+                        while !token.is_cancelled() {
+                            spins += 1;
+                            std::hint::spin_loop();
+                        }
+                        spins
+                    })
+                })
+                .collect::<Vec<_>>();
+            token.cancel();
+            for worker in workers {
+                let _ = worker.join().unwrap();
+            }
+        });
+    }
+}
+
+mod correctness {
+    use cs431_homework::test::loom::sync::atomic::AtomicUsize;
+    use cs431_homework::test::loom::sync::atomic::Ordering::Relaxed;
+    use cs431_homework::test::loom::sync::Arc;
+    use cs431_homework::test::loom::{model, thread};
+
+    use super::*;
+
+    #[test]
+    /// After `cancel` on one clone, every clone and child observes it, together with the writes
+    /// before the cancellation.
+    fn cancel_visible() {
+        model(|| {
+            let data = Arc::new(AtomicUsize::new(0));
+            let token = CancellationToken::new();
+            let child = token.child();
+            let canceller = {
+                let data = data.clone();
+                let token = token.clone();
+                thread::spawn(move || {
+                    data.store(1, Relaxed);
+                    token.cancel();
+                })
+            };
+            let observer = {
+                let data = data.clone();
+                let token = token.clone();
+                thread::spawn(move || {
+                    for token in [token, child] {
+                        if token.is_cancelled() {
+                            assert_eq!(data.load(Relaxed), 1);
+                        }
+                    }
+                })
+            };
+            canceller.join().unwrap();
+            observer.join().unwrap();
+            assert!(token.is_cancelled());
+        })
+    }
+}
@@ -0,0 +1,115 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::time::Duration;
+
+use crossbeam_epoch::pin;
+use cs431_homework::{
+    BlockingSortedQueue, ConcurrencyError, ConcurrentSet, FineGrainedListSet, GrowableArray,
+};
+
+/// Allocator that fails the allocations of the current thread while `FAIL` is set.
+struct FailingAlloc;
+
+thread_local! {
+    static FAIL: Cell<bool> = const { Cell::new(false) };
+}
+
+unsafe impl GlobalAlloc for FailingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if FAIL.with(Cell::get) {
+            return std::ptr::null_mut();
+        }
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOC: FailingAlloc = FailingAlloc;
+
+#[test]
+fn full() {
+    let queue = BlockingSortedQueue::bounded(2);
+    assert_eq!(queue.try_push(1), Ok(true));
+    assert_eq!(queue.try_push(1), Ok(false));
+    assert_eq!(queue.try_push(2), Ok(true));
+    assert_eq!(queue.try_push(3), Err(ConcurrencyError::Full));
+    assert!(!queue.push(3));
+
+    assert_eq!(queue.pop_min_blocking(), Ok(1));
+    assert_eq!(queue.try_push(3), Ok(true));
+}
+
+#[test]
+fn would_block() {
+    let set = FineGrainedListSet::new();
+    assert!(set.insert_many([1, 2, 3]).into_iter().all(|b| b));
+    assert_eq!(set.try_contains(&2), Ok(true));
+    assert_eq!(set.try_contains(&4), Ok(false));
+
+    // The iterator holds the lock of the `next` field of node 1.
+    let mut iter = set.iter();
+    assert_eq!(iter.next(), Some(&1));
+    assert_eq!(set.try_contains(&1), Ok(true));
+    assert_eq!(set.try_contains(&2), Err(ConcurrencyError::WouldBlock));
+    drop(iter);
+    assert_eq!(set.try_contains(&2), Ok(true));
+}
+
+#[test]
+fn timeout() {
+    let queue = BlockingSortedQueue::new();
+    assert_eq!(
+        queue.pop_min_timeout(Duration::from_millis(10)),
+        Err(ConcurrencyError::Timeout)
+    );
+    assert!(queue.push(1));
+    assert_eq!(
+        queue.pop_min_timeout(Duration::from_millis(10)),
+        Ok(Some(1))
+    );
+    queue.close();
+    assert_eq!(queue.pop_min_timeout(Duration::from_millis(10)), Ok(None));
+}
+
+#[test]
+#[ignore = "the GrowableArray homework (`get` and `Drop`) is not done yet"]
+fn index_out_of_bounds() {
+    let array = GrowableArray::<usize>::bounded(100);
+    let guard = pin();
+    assert!(array.try_get(100, &guard).is_ok());
+    assert_eq!(
+        array.try_get(101, &guard).err(),
+        Some(ConcurrencyError::IndexOutOfBounds)
+    );
+}
+
+#[test]
+#[ignore = "the GrowableArray homework (`get` and `Drop`) is not done yet"]
+fn alloc_error() {
+    let array = GrowableArray::<usize>::new();
+    let guard = pin();
+    FAIL.with(|fail| fail.set(true));
+    let result = array.try_get(1 << 20, &guard).map(|_| ());
+    FAIL.with(|fail| fail.set(false));
+    assert_eq!(result, Err(ConcurrencyError::AllocError));
+
+    // The array is still usable after the failure.
+    assert!(array.try_get(1 << 20, &guard).is_ok());
+}
+
+#[test]
+fn display() {
+    for (error, msg) in [
+        (ConcurrencyError::Full, "container is full"),
+        (ConcurrencyError::WouldBlock, "operation would block"),
+        (ConcurrencyError::Timeout, "operation timed out"),
+        (ConcurrencyError::IndexOutOfBounds, "index out of bounds"),
+        (ConcurrencyError::AllocError, "memory allocation failed"),
+    ] {
+        assert_eq!(error.to_string(), msg);
+    }
+}
@@ -0,0 +1,54 @@
+#[cfg(not(feature = "check-loom"))]
+mod basic {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Barrier;
+    use std::thread::scope;
+
+    use cs431_homework::hello_server::Flyweight;
+    use cs431_homework::Arc;
+
+    const NUM_THREADS: usize = 8;
+
+    #[test]
+    fn flyweight_dedup_and_recreate() {
+        let flyweight = Flyweight::<usize, String>::default();
+        let barrier = Barrier::new(NUM_THREADS);
+        let num_calls = AtomicUsize::new(0);
+        let create = |key: &usize| {
+            let _ = num_calls.fetch_add(1, Ordering::Relaxed);
+            key.to_string()
+        };
+
+        let values = scope(|s| {
+            let handles = (0..NUM_THREADS)
+                .map(|_| {
+                    s.spawn(|| {
+                        let _ = barrier.wait();
+                        flyweight.get(1, create)
+                    })
+                })
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(num_calls.load(Ordering::Relaxed), 1);
+        for value in &values {
+            assert!(Arc::ptr_eq(value, &values[0]));
+        }
+        assert_eq!(*values[0], "1");
+
+        let other = flyweight.get(2, create);
+        assert_eq!(num_calls.load(Ordering::Relaxed), 2);
+        assert!(Arc::ptr_eq(&flyweight.get(2, create), &other));
+
+        // Dropping all the references to a value lets it be created again.
+        drop(values);
+        let recreated = flyweight.get(1, create);
+        assert_eq!(num_calls.load(Ordering::Relaxed), 3);
+        assert_eq!(*recreated, "1");
+        assert!(Arc::ptr_eq(&flyweight.get(1, create), &recreated));
+        assert_eq!(num_calls.load(Ordering::Relaxed), 3);
+    }
+}
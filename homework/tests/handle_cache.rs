@@ -0,0 +1,72 @@
+#[cfg(not(feature = "check-loom"))]
+mod basic {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Barrier, Mutex};
+    use std::thread::scope;
+
+    use cs431_homework::hello_server::HandleCache;
+    use cs431_homework::Arc;
+
+    const NUM_THREADS: usize = 8;
+
+    #[test]
+    fn handle_cache_shared_concurrent() {
+        let cache = HandleCache::default();
+        let barrier = Barrier::new(NUM_THREADS);
+        let num_open = AtomicUsize::new(0);
+        let handles = Mutex::new(Vec::new());
+
+        scope(|s| {
+            for _ in 0..NUM_THREADS {
+                let _ = s.spawn(|| {
+                    let _ = barrier.wait();
+                    let handle = cache.get_or_open("file", |name| {
+                        let _ = num_open.fetch_add(1, Ordering::Relaxed);
+                        name.len()
+                    });
+                    handles.lock().unwrap().push(handle);
+                });
+            }
+        });
+
+        assert_eq!(num_open.load(Ordering::Relaxed), 1);
+        let handles = handles.into_inner().unwrap();
+        assert_eq!(*handles[0], 4);
+        for handle in &handles {
+            assert!(Arc::ptr_eq(handle, &handles[0]));
+        }
+    }
+
+    #[test]
+    fn handle_cache_close() {
+        /// Counts the number of dropped handles.
+        struct Handle<'a>(&'a AtomicUsize);
+
+        impl Drop for Handle<'_> {
+            fn drop(&mut self) {
+                let _ = self.0.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let dropped = AtomicUsize::new(0);
+        let cache = HandleCache::default();
+        let first = cache.get_or_open(1, |_| Handle(&dropped));
+        assert!(Arc::ptr_eq(&first, &cache.get_or_open(1, |_| panic!())));
+
+        assert!(cache.close(&1));
+        assert!(!cache.close(&1));
+        // The handle is alive while it is referenced.
+        assert_eq!(dropped.load(Ordering::Relaxed), 0);
+
+        // Reopened after close.
+        let second = cache.get_or_open(1, |_| Handle(&dropped));
+        assert!(!Arc::ptr_eq(&first, &second));
+
+        drop(first);
+        assert_eq!(dropped.load(Ordering::Relaxed), 1);
+        drop(second);
+        assert_eq!(dropped.load(Ordering::Relaxed), 1);
+        drop(cache);
+        assert_eq!(dropped.load(Ordering::Relaxed), 2);
+    }
+}
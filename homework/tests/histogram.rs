@@ -0,0 +1,53 @@
+use std::thread::scope;
+
+use cs431_homework::ConcurrentHistogram;
+
+#[test]
+#[ignore = "the GrowableArray homework (`get` and `Drop`) is not done yet"]
+fn smoke() {
+    let histogram = ConcurrentHistogram::new();
+    assert_eq!(histogram.count(0), 0);
+    histogram.record(0);
+    histogram.record(7);
+    histogram.record(7);
+    histogram.record(usize::MAX);
+    assert_eq!(histogram.count(0), 1);
+    assert_eq!(histogram.count(7), 2);
+    assert_eq!(histogram.count(8), 0);
+    assert_eq!(histogram.count(usize::MAX), 1);
+}
+
+#[test]
+#[ignore = "the GrowableArray homework (`get` and `Drop`) is not done yet"]
+fn stress_concurrent() {
+    const THREADS: usize = 16;
+    const STEPS: usize = 10_000;
+    /// Buckets recorded by every thread.
+    const SHARED: [usize; 4] = [0, 1, 1 << 20, usize::MAX];
+
+    let histogram = ConcurrentHistogram::new();
+    scope(|s| {
+        for t in 0..THREADS {
+            let histogram = &histogram;
+            let _ = s.spawn(move || {
+                // A bucket recorded only by this thread, far from the others.
+                let own = (t + 1) << 32;
+                for i in 0..STEPS {
+                    histogram.record(SHARED[i % SHARED.len()]);
+                    histogram.record(own);
+                }
+            });
+        }
+    });
+
+    for bucket in SHARED {
+        assert_eq!(
+            histogram.count(bucket),
+            (THREADS * STEPS / SHARED.len()) as u64
+        );
+    }
+    for t in 0..THREADS {
+        assert_eq!(histogram.count((t + 1) << 32), STEPS as u64);
+    }
+    assert_eq!(histogram.count(2), 0);
+}
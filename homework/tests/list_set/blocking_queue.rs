@@ -0,0 +1,91 @@
+use std::sync::Mutex;
+use std::thread::{scope, sleep};
+use std::time::Duration;
+
+use cs431_homework::BlockingSortedQueue;
+
+#[test]
+fn pop_ascending() {
+    const THREADS: usize = 8;
+    const STEPS: usize = 1024;
+
+    let queue = BlockingSortedQueue::new();
+    scope(|s| {
+        for t in 0..THREADS {
+            let queue = &queue;
+            let _ = s.spawn(move || {
+                for i in 0..STEPS {
+                    assert!(queue.push(i * THREADS + t));
+                }
+            });
+        }
+    });
+    assert!(!queue.push(0));
+
+    for i in 0..THREADS * STEPS {
+        assert_eq!(queue.pop_min_blocking(), Ok(i));
+    }
+}
+
+#[test]
+fn pop_blocks_until_push() {
+    const THREADS: usize = 8;
+    const STEPS: usize = 1024;
+
+    let queue = BlockingSortedQueue::new();
+    let popped = Mutex::new(Vec::new());
+    scope(|s| {
+        // Consumers start first, so they block on the empty queue.
+        for _ in 0..THREADS {
+            let _ = s.spawn(|| {
+                let mut local = Vec::new();
+                for _ in 0..STEPS {
+                    local.push(queue.pop_min_blocking().unwrap());
+                }
+                popped.lock().unwrap().extend(local);
+            });
+        }
+        sleep(Duration::from_millis(100));
+        for t in 0..THREADS {
+            let queue = &queue;
+            let _ = s.spawn(move || {
+                for i in 0..STEPS {
+                    assert!(queue.push(i * THREADS + t));
+                }
+            });
+        }
+    });
+
+    let mut popped = popped.into_inner().unwrap();
+    popped.sort();
+    assert_eq!(popped, (0..THREADS * STEPS).collect::<Vec<_>>());
+}
+
+#[test]
+fn close_wakes_waiters() {
+    const THREADS: usize = 4;
+
+    let queue = BlockingSortedQueue::<usize>::new();
+    scope(|s| {
+        let waiters = (0..THREADS)
+            .map(|_| s.spawn(|| queue.pop_min_blocking()))
+            .collect::<Vec<_>>();
+        sleep(Duration::from_millis(100));
+        queue.close();
+        for waiter in waiters {
+            assert_eq!(waiter.join().unwrap(), Err(()));
+        }
+    });
+    assert!(!queue.push(1));
+}
+
+#[test]
+fn close_drains_remaining() {
+    let queue = BlockingSortedQueue::new();
+    assert!(queue.push(2));
+    assert!(queue.push(1));
+    queue.close();
+    assert_eq!(queue.pop_min_blocking(), Ok(1));
+    assert_eq!(queue.pop_min_blocking(), Ok(2));
+    assert_eq!(queue.pop_min_blocking(), Err(()));
+}
@@ -1,11 +1,16 @@
 use std::collections::HashSet;
 use std::iter::zip;
+use std::ops::Bound;
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering::{Acquire, Release};
 use std::thread;
+use std::time::{Duration, Instant};
 
 use cs431_homework::test::adt::set;
-use cs431_homework::{ConcurrentSet, FineGrainedListSet};
+use cs431_homework::test::{Canary, DropCounter};
+use cs431_homework::{
+    ConcurrencyError, ConcurrentSet, FineGrainedListSet, OwningIter, SortedSetExt,
+};
 use rand::prelude::*;
 
 #[test]
@@ -84,3 +89,816 @@ fn iter_consistent() {
         });
     });
 }
+
+/// Interleaves all the operations on a small key space for a fixed duration, and then checks that
+/// the list is still well-formed.
+#[test]
+fn torture() {
+    const THREADS: usize = 16;
+    const KEYS: u16 = 32;
+    const DURATION: Duration = Duration::from_millis(500);
+
+    let set = FineGrainedListSet::new();
+    let start = Instant::now();
+    thread::scope(|s| {
+        for _ in 0..THREADS {
+            let _ = s.spawn(|| {
+                let mut rng = thread_rng();
+                while start.elapsed() < DURATION {
+                    let key = rng.gen_range(0..KEYS);
+                    match rng.gen_range(0..4) {
+                        0 => {
+                            let _ = set.insert(key);
+                        }
+                        1 => {
+                            let _ = set.remove(&key);
+                        }
+                        2 => {
+                            let _ = set.contains(&key);
+                        }
+                        _ => {
+                            // bounded walk, so that a cycle fails instead of hanging
+                            let snapshot = set
+                                .iter()
+                                .take(usize::from(KEYS) + 1)
+                                .copied()
+                                .collect::<Vec<_>>();
+                            assert!(snapshot.len() <= usize::from(KEYS));
+                            assert!(snapshot.windows(2).all(|k| k[0] < k[1]));
+                        }
+                    }
+                }
+            });
+        }
+    });
+
+    let keys = set
+        .iter()
+        .take(usize::from(KEYS) + 1)
+        .copied()
+        .collect::<Vec<_>>();
+    // no cycle
+    assert!(keys.len() <= usize::from(KEYS));
+    // sorted without duplicates
+    assert!(keys.windows(2).all(|k| k[0] < k[1]));
+    for key in 0..KEYS {
+        assert_eq!(set.contains(&key), keys.contains(&key));
+    }
+}
+
+#[test]
+fn encode_decode() {
+    let set = FineGrainedListSet::new();
+    for i in [5u32, 1, 4, 2, 3, 1000, u32::MAX] {
+        assert!(set.insert(i));
+    }
+    let buf = set.encode();
+    let decoded = FineGrainedListSet::<u32>::decode(&buf).unwrap();
+    assert!(decoded.iter().copied().eq([1, 2, 3, 4, 5, 1000, u32::MAX]));
+    assert_eq!(decoded.encode(), buf);
+
+    let empty = FineGrainedListSet::<u32>::new();
+    assert!(FineGrainedListSet::<u32>::decode(&empty.encode())
+        .unwrap()
+        .iter()
+        .next()
+        .is_none());
+}
+
+#[test]
+fn decode_malformed() {
+    let set = FineGrainedListSet::from_sorted_iter([1u16, 2, 3]);
+    let buf = set.encode();
+    // truncated
+    assert!(FineGrainedListSet::<u16>::decode(&buf[..buf.len() - 1]).is_err());
+    // trailing bytes
+    let mut trailing = buf.clone();
+    trailing.push(0);
+    assert!(FineGrainedListSet::<u16>::decode(&trailing).is_err());
+    // not sorted
+    let unsorted = [
+        2u64.to_le_bytes().as_slice(),
+        &2u16.to_le_bytes(),
+        &1u16.to_le_bytes(),
+    ]
+    .concat();
+    assert!(FineGrainedListSet::<u16>::decode(&unsorted).is_err());
+}
+
+#[test]
+fn insert_many_sorted() {
+    let set = FineGrainedListSet::new();
+    assert!(set.insert(3));
+    assert_eq!(
+        set.insert_many([1, 2, 2, 3, 4, 7, 7]),
+        [true, true, false, false, true, true, false]
+    );
+    assert!(set.iter().copied().eq([1, 2, 3, 4, 7]));
+}
+
+#[test]
+fn insert_many_unsorted() {
+    let set = FineGrainedListSet::new();
+    assert!(set.insert(3));
+    assert_eq!(
+        set.insert_many([5, 1, 3, 5, 0, 1]),
+        [true, true, false, false, true, false]
+    );
+    assert!(set.iter().copied().eq([0, 1, 3, 5]));
+}
+
+#[test]
+fn read_only_view() {
+    let set = FineGrainedListSet::new();
+    let view = set.as_read_only();
+    assert!(view.is_empty());
+
+    thread::scope(|s| {
+        let _ = s.spawn(|| {
+            for i in 0..100 {
+                assert!(set.insert(i));
+            }
+        });
+        // The view is `Copy` and can be shared with readers.
+        let _ = s.spawn(move || {
+            let mut prev = None;
+            for &i in view.iter() {
+                assert!(prev < Some(i));
+                prev = Some(i);
+            }
+        });
+    });
+    assert_eq!(view.len(), 100);
+    assert!(view.contains(&42));
+    assert!(!view.contains(&100));
+    assert!(view.iter().copied().eq(0..100));
+}
+
+#[test]
+fn drop_order() {
+    use std::cmp::Ordering;
+    use std::sync::Mutex;
+
+    /// Records its id to `dropped` when dropped.
+    #[derive(Debug)]
+    struct Canary<'a> {
+        id: usize,
+        dropped: &'a Mutex<Vec<usize>>,
+    }
+
+    impl PartialEq for Canary<'_> {
+        fn eq(&self, other: &Self) -> bool {
+            self.id == other.id
+        }
+    }
+
+    impl Eq for Canary<'_> {}
+
+    impl PartialOrd for Canary<'_> {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Canary<'_> {
+        fn cmp(&self, other: &Self) -> Ordering {
+            assert!(other.id != POISON, "poisoned comparison");
+            self.id.cmp(&other.id)
+        }
+    }
+
+    impl Drop for Canary<'_> {
+        fn drop(&mut self) {
+            self.dropped.lock().unwrap().push(self.id);
+        }
+    }
+
+    /// Comparing with this id panics, poisoning the locks held by the comparing thread.
+    const POISON: usize = usize::MAX;
+
+    let dropped = Mutex::new(Vec::new());
+    let set = FineGrainedListSet::new();
+    let mut ids = (0..100).collect::<Vec<_>>();
+    ids.shuffle(&mut thread_rng());
+    for &id in &ids {
+        assert!(set.insert(Canary {
+            id,
+            dropped: &dropped,
+        }));
+    }
+    // The rejected duplicate is dropped right away.
+    assert!(!set.insert(Canary {
+        id: 42,
+        dropped: &dropped,
+    }));
+    assert_eq!(dropped.lock().unwrap().drain(..).collect::<Vec<_>>(), [42]);
+
+    // Poisoned locks don't stop the teardown.
+    let poison = Canary {
+        id: POISON,
+        dropped: &dropped,
+    };
+    assert!(std::panic::catch_unwind(|| set.contains(&poison)).is_err());
+    drop(poison);
+    assert_eq!(
+        dropped.lock().unwrap().drain(..).collect::<Vec<_>>(),
+        [POISON]
+    );
+
+    drop(set);
+    assert_eq!(dropped.into_inner().unwrap(), (0..100).collect::<Vec<_>>());
+}
+
+#[test]
+fn remove_returning_predecessor() {
+    let set = FineGrainedListSet::from_sorted_iter([1, 3, 5, 7]);
+    let (removed, cursor) = set.remove_returning_predecessor(&4);
+    assert!(!removed);
+    assert!(cursor.is_none());
+
+    let (removed, cursor) = set.remove_returning_predecessor(&5);
+    assert!(removed);
+    let mut cursor = cursor.unwrap();
+    assert_eq!(cursor.predecessor(), Some(&3));
+    // The cursor holds the lock of the predecessor, not of the head.
+    assert_eq!(set.try_contains(&1), Ok(true));
+    assert_eq!(set.try_contains(&7), Err(ConcurrencyError::WouldBlock));
+
+    assert_eq!(cursor.insert(2), Err(2));
+    assert_eq!(cursor.insert(3), Ok(false));
+    assert_eq!(cursor.insert(4), Ok(true));
+    assert_eq!(cursor.insert(6), Ok(true));
+    assert_eq!(cursor.predecessor(), Some(&4));
+    assert_eq!(cursor.insert(7), Ok(false));
+    assert_eq!(cursor.insert(8), Ok(true));
+    drop(cursor);
+    assert!(set.iter().copied().eq([1, 3, 4, 6, 7, 8]));
+
+    // The first element has no predecessor.
+    let (removed, cursor) = set.remove_returning_predecessor(&1);
+    assert!(removed);
+    let mut cursor = cursor.unwrap();
+    assert_eq!(cursor.predecessor(), None);
+    assert_eq!(cursor.insert(0), Ok(true));
+    drop(cursor);
+    assert!(set.iter().copied().eq([0, 3, 4, 6, 7, 8]));
+}
+
+#[test]
+fn remove_if() {
+    use std::cmp::Ordering;
+
+    /// Ordered only by the key.
+    #[derive(Debug)]
+    struct Versioned {
+        key: usize,
+        version: usize,
+    }
+
+    impl PartialEq for Versioned {
+        fn eq(&self, other: &Self) -> bool {
+            self.key == other.key
+        }
+    }
+
+    impl Eq for Versioned {}
+
+    impl PartialOrd for Versioned {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Versioned {
+        fn cmp(&self, other: &Self) -> Ordering {
+            self.key.cmp(&other.key)
+        }
+    }
+
+    let set = FineGrainedListSet::new();
+    for key in 0..3 {
+        assert!(set.insert(Versioned { key, version: 1 }));
+    }
+    let key = |key| Versioned { key, version: 0 };
+
+    assert!(!set.remove_if(&key(1), |v| v.version == 0));
+    assert!(set.contains(&key(1)));
+    assert!(set.remove_if(&key(1), |v| v.version == 1));
+    assert!(!set.contains(&key(1)));
+    assert!(!set.remove_if(&key(1), |_| true));
+    assert!(!set.remove_if(&key(3), |_| panic!()));
+    assert!(set.iter().map(|v| v.key).eq([0, 2]));
+}
+
+#[test]
+fn with_max_len() {
+    const MAX_LEN: usize = 4;
+
+    let set = FineGrainedListSet::with_max_len(MAX_LEN);
+    for i in 0..MAX_LEN {
+        assert!(set.insert(i * 2));
+    }
+    // full, even for a key in the middle
+    assert!(!set.insert(MAX_LEN * 2));
+    assert!(!set.insert(1));
+    assert!(!set.insert_many([5, 9]).into_iter().any(|inserted| inserted));
+    assert_eq!(set.iter().count(), MAX_LEN);
+
+    assert!(set.remove(&2));
+    assert!(set.insert(1));
+    assert!(!set.insert(3));
+    assert_eq!(set.pop_min(), Some(0));
+    let (removed, cursor) = set.remove_returning_predecessor(&4);
+    assert!(removed);
+    let mut cursor = cursor.unwrap();
+    assert_eq!(cursor.insert(5), Ok(true));
+    assert_eq!(cursor.insert(7), Ok(true));
+    assert_eq!(cursor.insert(8), Ok(false));
+    drop(cursor);
+    assert_eq!(set.iter().copied().collect::<Vec<_>>(), [1, 5, 6, 7]);
+
+    // Concurrent insertions never exceed the limit.
+    let set = FineGrainedListSet::with_max_len(MAX_LEN);
+    let inserted = thread::scope(|s| {
+        let handles = (0..8)
+            .map(|t| {
+                let set = &set;
+                s.spawn(move || (0..8).filter(|i| set.insert(t * 8 + i)).count())
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .sum::<usize>()
+    });
+    assert_eq!(inserted, MAX_LEN);
+    assert_eq!(set.iter().count(), MAX_LEN);
+}
+
+#[test]
+fn set_algebra() {
+    fn set_of(keys: impl IntoIterator<Item = u32>) -> FineGrainedListSet<u32> {
+        let set = FineGrainedListSet::new();
+        for key in keys {
+            assert!(set.insert(key));
+        }
+        set
+    }
+
+    // overlapping
+    let a = set_of([1, 3, 5, 7, 9]);
+    let b = set_of([3, 4, 5, 6, 10]);
+    assert_eq!(a.intersection(&b), [3, 5]);
+    assert_eq!(b.intersection(&a), [3, 5]);
+    assert_eq!(a.symmetric_difference(&b), [1, 4, 6, 7, 9, 10]);
+    assert_eq!(b.symmetric_difference(&a), [1, 4, 6, 7, 9, 10]);
+
+    // disjoint
+    let c = set_of([2, 8]);
+    assert!(a.intersection(&c).is_empty());
+    assert_eq!(a.symmetric_difference(&c), [1, 2, 3, 5, 7, 8, 9]);
+    let empty = set_of([]);
+    assert!(a.intersection(&empty).is_empty());
+    assert_eq!(empty.symmetric_difference(&a), [1, 3, 5, 7, 9]);
+
+    // identical
+    let d = set_of([1, 3, 5, 7, 9]);
+    assert_eq!(a.intersection(&d), [1, 3, 5, 7, 9]);
+    assert!(a.symmetric_difference(&d).is_empty());
+    assert_eq!(a.intersection(&a), [1, 3, 5, 7, 9]);
+    assert!(a.symmetric_difference(&a).is_empty());
+
+    // Opposite orders from concurrent threads don't deadlock.
+    thread::scope(|s| {
+        let _ = s.spawn(|| {
+            for _ in 0..1000 {
+                assert_eq!(a.intersection(&b), [3, 5]);
+            }
+        });
+        for _ in 0..1000 {
+            assert_eq!(b.intersection(&a), [3, 5]);
+        }
+    });
+}
+
+/// `iter` and the snapshots of the set yield strictly ascending keys, regardless of the insertion
+/// order.
+#[test]
+fn iter_ascending() {
+    fn assert_ascending(keys: &[u16]) {
+        assert!(keys.windows(2).all(|k| k[0] < k[1]), "{keys:?}");
+    }
+
+    let set = FineGrainedListSet::new();
+    let mut rng = thread_rng();
+    for _ in 0..1024 {
+        let _ = set.insert(rng.gen_range(0..512u16));
+    }
+    for _ in 0..256 {
+        let _ = set.remove(&rng.gen_range(0..512u16));
+    }
+
+    let keys = set.iter().copied().collect::<Vec<_>>();
+    assert_ascending(&keys);
+    assert_eq!(set.as_read_only().iter().copied().collect::<Vec<_>>(), keys);
+    assert_eq!(
+        FineGrainedListSet::<u16>::decode(&set.encode())
+            .unwrap()
+            .iter()
+            .copied()
+            .collect::<Vec<_>>(),
+        keys
+    );
+    assert_eq!(set.into_sequential().to_vec(), keys);
+}
+
+#[cfg(feature = "lock-metrics")]
+#[test]
+fn lock_wait_stats() {
+    let set = FineGrainedListSet::new();
+    for key in 0..8 {
+        assert!(set.insert(key));
+    }
+    let stats = set.lock_wait_stats();
+    assert_eq!(stats.insert.iter().sum::<u64>(), 8 + (0..8).sum::<u64>());
+    assert_eq!(stats.contains.iter().sum::<u64>(), 0);
+
+    // Hold the head lock with an iterator while `contains` waits for it.
+    let (started_sender, started_receiver) = crossbeam_channel::bounded(0);
+    thread::scope(|s| {
+        let mut iter = set.iter();
+        let waiter = s.spawn(|| {
+            started_sender.send(()).unwrap();
+            set.contains(&7)
+        });
+        started_receiver.recv().unwrap();
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(iter.next(), Some(&0));
+        drop(iter);
+        assert!(waiter.join().unwrap());
+    });
+
+    let stats = set.lock_wait_stats();
+    // 2^26ns ≈ 67ms
+    assert!(stats.contains[26..].iter().sum::<u64>() >= 1);
+    // the head and the nodes 0 to 6
+    assert_eq!(stats.contains.iter().sum::<u64>(), 8);
+    assert_eq!(stats.iter.iter().sum::<u64>(), 2);
+    assert_eq!(stats.remove.iter().sum::<u64>(), 0);
+}
+
+#[cfg(feature = "op-log")]
+#[test]
+fn op_log() {
+    use std::collections::BTreeSet;
+
+    use cs431_homework::dump_op_log;
+
+    const THREADS: usize = 4;
+    const STEPS: usize = 1000;
+
+    let set = FineGrainedListSet::new().log_ops();
+    let threads = thread::scope(|s| {
+        let handles = (0..THREADS)
+            .map(|_| {
+                let set = &set;
+                s.spawn(move || {
+                    let mut rng = thread_rng();
+                    for _ in 0..STEPS {
+                        let key = rng.gen_range(0..16u8);
+                        let _ = match rng.gen_range(0..3) {
+                            0 => set.contains(&key),
+                            1 => set.insert(key),
+                            _ => set.remove(&key),
+                        };
+                    }
+                    thread::current().id()
+                })
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .map(|h| h.join().unwrap())
+            .collect::<HashSet<_>>()
+    });
+
+    let log = dump_op_log()
+        .into_iter()
+        .filter(|record| threads.contains(&record.thread))
+        .collect::<Vec<_>>();
+    assert_eq!(log.len(), THREADS * STEPS);
+    assert!(log.windows(2).all(|w| w[0].seq < w[1].seq));
+
+    // Replaying the log in the order of the sequence numbers reproduces the results and the
+    // final state.
+    let mut replayed = BTreeSet::new();
+    for record in &log {
+        let key = record.key.parse::<u8>().unwrap();
+        let result = match record.op {
+            "contains" => replayed.contains(&key),
+            "insert" => replayed.insert(key),
+            "remove" => replayed.remove(&key),
+            op => panic!("unexpected op {op}"),
+        };
+        assert_eq!(result, record.result, "{record:?}");
+    }
+    assert!(set.iter().eq(replayed.iter()));
+}
+
+#[test]
+fn transaction() {
+    const THREADS: usize = 8;
+    const STEPS: usize = 1000;
+
+    // Exactly one of 1 and 2 is in the set, as the transactions move it back and forth.
+    let set = FineGrainedListSet::new();
+    assert!(set.insert(1));
+    thread::scope(|s| {
+        for _ in 0..THREADS {
+            let _ = s.spawn(|| {
+                for _ in 0..STEPS {
+                    set.transaction(|tx| {
+                        assert_ne!(tx.contains(&1), tx.contains(&2));
+                        let (from, to) = if tx.contains(&1) { (1, 2) } else { (2, 1) };
+                        assert!(tx.remove(&from));
+                        assert!(tx.insert(to));
+                    });
+                }
+            });
+        }
+        // A plain reader is not blocked by the transactions.
+        let _ = s.spawn(|| {
+            for _ in 0..STEPS {
+                let _ = set.contains(&1);
+            }
+        });
+    });
+    // An even number of moves, back to 1.
+    assert!(set.iter().eq([1].iter()));
+
+    assert!(set.transaction(|tx| tx.insert(3) && tx.remove(&3)));
+}
+
+#[test]
+fn replace_all() {
+    const STEPS: usize = 1000;
+
+    // The versions share 32..64, so a concurrent `contains` of those keys never misses.
+    let versions = [(0..64).collect::<Vec<_>>(), (32..96).collect::<Vec<_>>()];
+    let set = FineGrainedListSet::new();
+    set.replace_all(versions[0].iter().copied().rev());
+    assert!(set.iter().eq(versions[0].iter()));
+
+    let done = AtomicBool::new(false);
+    thread::scope(|s| {
+        let _ = s.spawn(|| {
+            for step in 1..=STEPS {
+                set.replace_all(versions[step % 2].iter().copied());
+            }
+            done.store(true, Release);
+        });
+        let _ = s.spawn(|| {
+            while !done.load(Acquire) {
+                assert!(set.contains(&40));
+            }
+        });
+        while !done.load(Acquire) {
+            let snapshot = set.iter().copied().collect::<Vec<_>>();
+            assert!(versions.contains(&snapshot), "{snapshot:?}");
+        }
+    });
+    assert!(set.iter().eq(versions[0].iter()));
+    assert_eq!(set.as_read_only().len(), 64);
+
+    set.replace_all([3, 1, 3, 2]);
+    assert!(set.iter().eq([1, 2, 3].iter()));
+    set.replace_all([]);
+    assert!(set.as_read_only().is_empty());
+}
+
+#[test]
+fn remove_range() {
+    fn keys(set: &FineGrainedListSet<u32>) -> Vec<u32> {
+        set.iter().copied().collect()
+    }
+
+    let set = FineGrainedListSet::from_sorted_iter(0..100);
+    assert_eq!(set.remove_range(20..40), 20);
+    assert_eq!(keys(&set), (0..20).chain(40..100).collect::<Vec<_>>());
+    assert_eq!(set.remove_range(20..40), 0);
+
+    // all the bound combinations
+    let set = FineGrainedListSet::from_sorted_iter(0..10);
+    assert_eq!(set.remove_range(2..=3), 2);
+    assert_eq!(
+        set.remove_range((Bound::Excluded(4), Bound::Included(6))),
+        2
+    );
+    assert_eq!(
+        set.remove_range((Bound::Excluded(6), Bound::Excluded(8))),
+        1
+    );
+    assert_eq!(keys(&set), [0, 1, 4, 8, 9]);
+    assert_eq!(set.remove_range(..1), 1);
+    assert_eq!(set.remove_range(9..), 1);
+    assert_eq!(set.remove_range(5..5), 0);
+    assert_eq!(keys(&set), [1, 4, 8]);
+    assert_eq!(set.remove_range(..=4), 2);
+    assert_eq!(set.remove_range(..), 1);
+    assert!(keys(&set).is_empty());
+
+    // Concurrent insertions outside of the range are not lost.
+    let set = FineGrainedListSet::from_sorted_iter((0..1000).filter(|k| k % 2 == 0));
+    thread::scope(|s| {
+        let _ = s.spawn(|| {
+            for key in (0..1000).filter(|k| k % 2 == 1 && !(400..600).contains(k)) {
+                assert!(set.insert(key));
+            }
+        });
+        assert_eq!(set.remove_range(400..600), 100);
+    });
+    assert_eq!(
+        keys(&set),
+        (0..1000)
+            .filter(|k| !(400..600).contains(k))
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn successor_predecessor() {
+    let set = FineGrainedListSet::from_sorted_iter((10..=50).step_by(10));
+
+    // present
+    assert_eq!(set.successor(&30), Some(40));
+    assert_eq!(set.predecessor(&30), Some(20));
+    // absent
+    assert_eq!(set.successor(&25), Some(30));
+    assert_eq!(set.predecessor(&25), Some(20));
+    // boundaries
+    assert_eq!(set.successor(&5), Some(10));
+    assert_eq!(set.predecessor(&5), None);
+    assert_eq!(set.predecessor(&10), None);
+    assert_eq!(set.successor(&10), Some(20));
+    assert_eq!(set.predecessor(&50), Some(40));
+    assert_eq!(set.successor(&50), None);
+    assert_eq!(set.successor(&55), None);
+    assert_eq!(set.predecessor(&55), Some(50));
+
+    let empty = FineGrainedListSet::<i32>::new();
+    assert_eq!(empty.successor(&0), None);
+    assert_eq!(empty.predecessor(&0), None);
+}
+
+#[test]
+fn retain_count() {
+    let set = FineGrainedListSet::from_sorted_iter(0..100);
+    assert_eq!(set.retain_count(|&k| k % 3 != 0), 34);
+    assert_eq!(
+        set.iter().copied().collect::<Vec<_>>(),
+        (0..100).filter(|k| k % 3 != 0).collect::<Vec<_>>()
+    );
+    assert_eq!(set.retain_count(|&k| k % 3 != 0), 0);
+
+    set.retain(|&k| k < 50);
+    assert_eq!(set.iter().count(), 33);
+    assert_eq!(set.retain_count(|_| false), 33);
+    assert!(set.iter().next().is_none());
+}
+
+#[test]
+fn into_iter_ref() {
+    let set = FineGrainedListSet::new();
+    for key in [3, 1, 2] {
+        assert!(set.insert(key));
+    }
+
+    let mut keys = Vec::new();
+    for key in &set {
+        keys.push(*key);
+    }
+    assert_eq!(keys, [1, 2, 3]);
+    assert_eq!((&set).into_iter().sum::<i32>(), 6);
+}
+
+#[test]
+fn drain_into() {
+    let set = FineGrainedListSet::new();
+    for key in [5, 1, 4, 2, 3] {
+        assert!(set.insert(key.to_string()));
+    }
+
+    // Under Miri, a node left behind is reported as a leak.
+    let mut drained = Vec::new();
+    set.drain_into(|key| drained.push(key));
+    assert_eq!(drained, ["1", "2", "3", "4", "5"]);
+
+    FineGrainedListSet::<String>::new().drain_into(|_| unreachable!());
+}
+
+#[test]
+fn owning_iter() {
+    /// Consumer pulling the elements on demand from the iterator in its field.
+    struct Consumer {
+        source: OwningIter<String>,
+    }
+
+    let set = FineGrainedListSet::from_sorted_iter((0..5).map(|key| key.to_string()));
+    let mut consumer = Consumer {
+        source: set.owning_iter(),
+    };
+    assert_eq!(consumer.source.next().as_deref(), Some("0"));
+    assert_eq!(consumer.source.next().as_deref(), Some("1"));
+    // Under Miri, a node left behind is reported as a leak, either after yielding its element or
+    // after dropping the iterator with the rest of the elements.
+    drop(consumer);
+
+    let set = FineGrainedListSet::from_sorted_iter((0..5).map(|key| key.to_string()));
+    assert!(set.owning_iter().eq(["0", "1", "2", "3", "4"]));
+    assert_eq!(
+        FineGrainedListSet::<String>::new().owning_iter().next(),
+        None
+    );
+}
+
+#[test]
+fn drain_map() {
+    // Order-preserving.
+    let set = FineGrainedListSet::from_sorted_iter(0..10);
+    let doubled = set.drain_map(|key| key * 2);
+    assert!(doubled.iter().copied().eq((0..20).step_by(2)));
+
+    // Order-reversing, so the mapped elements are sorted.
+    let set = FineGrainedListSet::from_sorted_iter(0..10);
+    let negated = set.drain_map(|key: i32| -key);
+    assert!(negated.iter().copied().eq(-9..=0));
+
+    // Collapsing elements, so the duplicates are dropped.
+    let set = FineGrainedListSet::from_sorted_iter(0..10);
+    let halved = set.drain_map(|key| (key / 2).to_string());
+    assert!(halved.iter().eq(["0", "1", "2", "3", "4"]));
+}
+
+#[test]
+fn drop_elements() {
+    /// Element ordered by its key, carrying a canary.
+    struct Element {
+        key: usize,
+        _canary: Canary,
+    }
+
+    impl PartialEq for Element {
+        fn eq(&self, other: &Self) -> bool {
+            self.key == other.key
+        }
+    }
+
+    impl Eq for Element {}
+
+    impl PartialOrd for Element {
+        fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Element {
+        fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+            self.key.cmp(&other.key)
+        }
+    }
+
+    let drops = DropCounter::new();
+    let element = |key| Element {
+        key,
+        _canary: drops.canary(),
+    };
+    let set = FineGrainedListSet::new();
+    for key in 0..10 {
+        assert!(set.insert(element(key)));
+    }
+    // The rejected duplicate is dropped right away, and a removed element is dropped on removal.
+    assert!(!set.insert(element(3)));
+    assert!(set.remove(&element(5)));
+    assert_eq!(drops.count(), 3);
+
+    drop(set);
+    assert_eq!(drops.count(), 12);
+}
+
+#[test]
+fn push_front() {
+    let set = FineGrainedListSet::new();
+    for key in (0..100).rev() {
+        assert!(set.push_front(key));
+    }
+    assert!(set.iter().copied().eq(0..100));
+
+    // Not smaller than the smallest element.
+    assert!(!set.push_front(0));
+    assert!(!set.push_front(50));
+    assert!(set.iter().copied().eq(0..100));
+
+    let full = FineGrainedListSet::with_max_len(1);
+    assert!(full.push_front(1));
+    assert!(!full.push_front(0));
+}
@@ -1,5 +1,11 @@
 // optimistic_fine_grained on thread santizer has very unstable performance on gg.
 #![feature(cfg_sanitize)]
 
+mod blocking_queue;
 mod fine_grained;
 mod optimistic_fine_grained;
+mod rate_limiter;
+mod sequential;
+mod skip_list;
+mod unrolled;
+mod versioned;
@@ -0,0 +1,59 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread::scope;
+use std::time::{Duration, Instant};
+
+use cs431_homework::SlidingWindowLimiter;
+
+#[test]
+fn sliding_window() {
+    let ms = Duration::from_millis;
+    let limiter = SlidingWindowLimiter::new(3, ms(100));
+    let base = Instant::now();
+
+    // A burst at a single instant is cut at the limit.
+    let burst = SlidingWindowLimiter::new(3, ms(100));
+    assert_eq!((0..5).filter(|_| burst.allow_at(base)).count(), 3);
+
+    for (offset, allowed) in [
+        (0, true),
+        (10, true),
+        (20, true),
+        (30, false),
+        (99, false),
+        // The request at 0 falls out of the window.
+        (100, true),
+        (105, false),
+        (110, true),
+        (120, true),
+        (150, false),
+        // All the previous requests fall out of the window.
+        (300, true),
+        (300, true),
+        (300, true),
+        (300, false),
+    ] {
+        assert_eq!(limiter.allow_at(base + ms(offset)), allowed, "{offset}");
+    }
+}
+
+#[test]
+fn sliding_window_concurrent() {
+    const THREADS: usize = 8;
+    const REQUESTS: usize = 100;
+    const LIMIT: usize = 10;
+
+    let limiter = SlidingWindowLimiter::new(LIMIT, Duration::from_secs(60));
+    let allowed = AtomicUsize::new(0);
+    scope(|s| {
+        for _ in 0..THREADS {
+            let _ = s.spawn(|| {
+                for _ in 0..REQUESTS {
+                    if limiter.allow() {
+                        let _ = allowed.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+            });
+        }
+    });
+    assert_eq!(allowed.load(Ordering::Relaxed), LIMIT);
+}
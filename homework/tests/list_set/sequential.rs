@@ -0,0 +1,52 @@
+use cs431_homework::test::adt::set;
+use cs431_homework::{ConcurrentSet, FineGrainedListSet, SequentialListSet};
+
+#[test]
+fn smoke() {
+    let set = SequentialListSet::new();
+    assert!(set.is_empty());
+    for i in [3, 1, 2, 5, 4] {
+        assert!(set.insert(i));
+    }
+    assert!(!set.insert(3));
+    assert_eq!(set.to_vec(), [1, 2, 3, 4, 5]);
+    assert!(set.contains(&4));
+    assert!(!set.contains(&6));
+    assert!(set.remove(&1));
+    assert!(!set.remove(&1));
+    assert!(set.remove(&5));
+    assert_eq!(set.to_vec(), [2, 3, 4]);
+    assert_eq!(set.len(), 3);
+}
+
+#[test]
+fn stress_sequential() {
+    const STEPS: usize = 4096;
+    set::stress_sequential::<u8, SequentialListSet<_>>(STEPS);
+}
+
+#[test]
+fn into_sequential() {
+    let set = FineGrainedListSet::new();
+    std::thread::scope(|s| {
+        for t in 0..4 {
+            let set = &set;
+            let _ = s.spawn(move || {
+                for i in (t..100).step_by(4) {
+                    assert!(set.insert(i));
+                }
+            });
+        }
+    });
+
+    let set = set.into_sequential();
+    assert_eq!(set.to_vec(), (0..100).collect::<Vec<_>>());
+    assert!(set.remove(&42));
+    assert!(set.insert(100));
+    assert!(!set.contains(&42));
+    assert_eq!(set.len(), 100);
+
+    assert!(FineGrainedListSet::<usize>::new()
+        .into_sequential()
+        .is_empty());
+}
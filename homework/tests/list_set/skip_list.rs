@@ -0,0 +1,51 @@
+use cs431_homework::test::adt::set;
+use cs431_homework::{ConcurrentSet, FineGrainedListSet, SkipListSet};
+
+#[test]
+fn smoke() {
+    let set = SkipListSet::new();
+    for i in (0..256).rev() {
+        assert!(set.insert(i * 2));
+    }
+    assert!(!set.insert(6));
+    for i in 0..512 {
+        assert_eq!(set.contains(&i), i % 2 == 0);
+    }
+    for i in 0..256 {
+        assert!(set.remove(&(i * 2)));
+        assert!(!set.remove(&(i * 2)));
+    }
+    assert!(!set.contains(&0));
+    assert!(set.insert(0));
+}
+
+#[test]
+fn stress_sequential() {
+    const STEPS: usize = 4096;
+    set::stress_sequential::<u8, SkipListSet<_>>(STEPS);
+}
+
+#[test]
+fn stress_concurrent() {
+    const THREADS: usize = 16;
+    const STEPS: usize = 4096 * 16;
+    set::stress_concurrent::<u8, SkipListSet<_>>(THREADS, STEPS);
+}
+
+#[test]
+fn log_concurrent() {
+    const THREADS: usize = 16;
+    const STEPS: usize = 4096 * 16;
+    set::log_concurrent::<u8, SkipListSet<_>>(THREADS, STEPS);
+}
+
+#[test]
+fn into_skip_list() {
+    let list = FineGrainedListSet::new();
+    let _ = list.insert_many(0..100);
+    let set = list.into_skip_list();
+    for i in 0..100 {
+        assert!(set.contains(&i));
+    }
+    assert!(!set.contains(&100));
+}
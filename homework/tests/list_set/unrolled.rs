@@ -0,0 +1,51 @@
+use cs431_homework::test::adt::set;
+use cs431_homework::{ConcurrentSet, UnrolledListSet};
+
+#[test]
+fn smoke() {
+    let set = UnrolledListSet::<_, 4>::new();
+    // Splits the nodes several times.
+    for i in (0..32).rev() {
+        assert!(set.insert(i * 2));
+    }
+    assert!(!set.insert(6));
+    for i in 0..64 {
+        assert_eq!(set.contains(&i), i % 2 == 0);
+    }
+    // Empties and unlinks the nodes.
+    for i in 0..32 {
+        assert!(set.remove(&(i * 2)));
+        assert!(!set.remove(&(i * 2)));
+    }
+    assert!(!set.contains(&0));
+    assert!(set.insert(0));
+}
+
+#[test]
+#[should_panic(expected = "at least 2 elements")]
+fn too_small_node() {
+    let _ = UnrolledListSet::<usize, 1>::new();
+}
+
+#[test]
+fn stress_sequential() {
+    const STEPS: usize = 4096;
+    set::stress_sequential::<_, UnrolledListSet<u8, 2>>(STEPS);
+    set::stress_sequential::<_, UnrolledListSet<u8, 16>>(STEPS);
+}
+
+#[test]
+fn stress_concurrent() {
+    const THREADS: usize = 16;
+    const STEPS: usize = 4096 * 16;
+    set::stress_concurrent::<_, UnrolledListSet<u8, 2>>(THREADS, STEPS);
+    set::stress_concurrent::<_, UnrolledListSet<u8, 16>>(THREADS, STEPS);
+}
+
+#[test]
+fn log_concurrent() {
+    const THREADS: usize = 16;
+    const STEPS: usize = 4096 * 16;
+    set::log_concurrent::<_, UnrolledListSet<u8, 2>>(THREADS, STEPS);
+    set::log_concurrent::<_, UnrolledListSet<u8, 16>>(THREADS, STEPS);
+}
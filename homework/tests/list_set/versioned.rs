@@ -0,0 +1,72 @@
+#[cfg(not(feature = "check-loom"))]
+mod basic {
+    use cs431_homework::test::adt::set;
+    use cs431_homework::{ConcurrentSet, VersionedListSet};
+
+    #[test]
+    fn smoke() {
+        let set = VersionedListSet::new();
+        assert!(set.insert(1));
+        assert!(set.insert(3));
+        assert!(set.insert(2));
+        assert!(!set.insert(2));
+        assert!(set.contains(&2));
+        assert!(set.remove(&2));
+        assert!(!set.contains(&2));
+        assert!(!set.remove(&2));
+        assert!(set.contains(&1));
+        assert!(set.contains(&3));
+    }
+
+    #[test]
+    fn stress_sequential() {
+        const STEPS: usize = 4096;
+        set::stress_sequential::<u8, VersionedListSet<_>>(STEPS);
+    }
+
+    #[test]
+    fn stress_concurrent() {
+        const THREADS: usize = 16;
+        const STEPS: usize = 4096 * 8;
+        set::stress_concurrent::<u8, VersionedListSet<_>>(THREADS, STEPS);
+    }
+
+    #[test]
+    fn log_concurrent() {
+        const THREADS: usize = 16;
+        const STEPS: usize = 4096 * 4;
+        set::log_concurrent::<u8, VersionedListSet<_>>(THREADS, STEPS);
+    }
+}
+
+mod correctness {
+    use cs431_homework::test::loom::sync::Arc;
+    use cs431_homework::test::loom::{model, thread};
+    use cs431_homework::{ConcurrentSet, VersionedListSet};
+
+    #[test]
+    /// With only insertions, `contains` concurrent with `insert` returns either the state before or
+    /// after it, and the keys inserted before are always found.
+    fn add_only() {
+        model(|| {
+            let set = Arc::new(VersionedListSet::new());
+            assert!(set.insert(1));
+            assert!(set.insert(3));
+            let writer = {
+                let set = set.clone();
+                thread::spawn(move || assert!(set.insert(2)))
+            };
+            let reader = {
+                let set = set.clone();
+                thread::spawn(move || {
+                    assert!(set.contains(&1));
+                    assert!(set.contains(&3));
+                    set.contains(&2)
+                })
+            };
+            writer.join().unwrap();
+            let _ = reader.join().unwrap();
+            assert!(set.contains(&2));
+        })
+    }
+}
@@ -0,0 +1,81 @@
+use std::thread::scope;
+
+use cs431_homework::ConcurrentLru;
+use rand::{thread_rng, Rng};
+
+#[test]
+fn hit_miss() {
+    let lru = ConcurrentLru::new(4);
+    assert!(lru.is_empty());
+    assert_eq!(lru.get(&1usize), None);
+    lru.put(1, "one");
+    lru.put(2, "two");
+    assert_eq!(lru.get(&1), Some("one"));
+    assert_eq!(lru.get(&2), Some("two"));
+    assert_eq!(lru.get(&3), None);
+    assert_eq!(lru.len(), 2);
+
+    // Replacing doesn't add an entry.
+    lru.put(1, "uno");
+    assert_eq!(lru.get(&1), Some("uno"));
+    assert_eq!(lru.len(), 2);
+}
+
+#[test]
+fn eviction_order() {
+    let lru = ConcurrentLru::new(3);
+    for key in 0..3usize {
+        lru.put(key, key * 10);
+    }
+    // 0 becomes the most recently used, so 1 is the least.
+    assert_eq!(lru.get(&0), Some(0));
+    lru.put(3, 30);
+    assert_eq!(lru.len(), 3);
+    assert_eq!(lru.get(&1), None);
+
+    // Now 2 is the least recently used. Replacing 2 makes it the most recently used, so 0 is.
+    lru.put(2, 21);
+    lru.put(4, 40);
+    assert_eq!(lru.get(&0), None);
+    assert_eq!(lru.get(&2), Some(21));
+    assert_eq!(lru.get(&3), Some(30));
+    assert_eq!(lru.get(&4), Some(40));
+    assert_eq!(lru.len(), 3);
+}
+
+#[test]
+fn stress_concurrent() {
+    const THREADS: usize = 8;
+    const STEPS: usize = 20_000;
+    const KEYS: usize = 256;
+    const CAPACITY: usize = 32;
+
+    let lru = ConcurrentLru::new(CAPACITY);
+    scope(|s| {
+        for _ in 0..THREADS {
+            let _ = s.spawn(|| {
+                let mut rng = thread_rng();
+                for _ in 0..STEPS {
+                    let key = rng.gen_range(0..KEYS);
+                    if rng.gen() {
+                        lru.put(key, key);
+                    } else if let Some(value) = lru.get(&key) {
+                        assert_eq!(value, key);
+                    }
+                }
+            });
+        }
+    });
+
+    assert!(lru.len() <= CAPACITY);
+    let present = (0..KEYS).filter(|key| lru.get(key).is_some()).count();
+    assert_eq!(present, lru.len());
+
+    // The recency list is consistent with the index: inserting CAPACITY new keys evicts all of
+    // the old ones.
+    for key in KEYS..KEYS + CAPACITY {
+        lru.put(key, key);
+    }
+    assert_eq!((0..KEYS).filter(|key| lru.get(key).is_some()).count(), 0);
+    assert_eq!(lru.len(), CAPACITY);
+}
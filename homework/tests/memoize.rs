@@ -0,0 +1,56 @@
+#[cfg(not(feature = "check-loom"))]
+mod basic {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread::scope;
+
+    use cs431_homework::hello_server::{CycleError, Memoize};
+
+    const NUM_THREADS: usize = 8;
+
+    #[test]
+    fn memoize_fibonacci() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let fib = {
+            let calls = Arc::clone(&calls);
+            Memoize::new(move |fib, n: u64| {
+                let _ = calls.fetch_add(1, Ordering::Relaxed);
+                Ok(if n < 2 {
+                    n
+                } else {
+                    fib.get(n - 1)? + fib.get(n - 2)?
+                })
+            })
+        };
+
+        scope(|s| {
+            for _ in 0..NUM_THREADS {
+                let _ = s.spawn(|| assert_eq!(fib.get(80), Ok(23416728348467685)));
+            }
+        });
+        // Each value is computed only once.
+        assert_eq!(calls.load(Ordering::Relaxed), 81);
+        assert_eq!(fib.get(10), Ok(55));
+        assert_eq!(calls.load(Ordering::Relaxed), 81);
+    }
+
+    #[test]
+    fn memoize_cycle() {
+        // 0 → 1 → 2 → 0, and 3 → 4 without a cycle
+        let memo = Memoize::new(|memo, n: usize| match n {
+            0..=2 => memo.get((n + 1) % 3),
+            3 => Ok(memo.get(4)? + 1),
+            _ => Ok(n),
+        });
+
+        assert_eq!(memo.get(0), Err(CycleError { key: 0 }));
+        // The keys on the cycle fail without deadlocking, and the error is cached.
+        assert_eq!(memo.get(1), Err(CycleError { key: 0 }));
+        assert_eq!(memo.get(2), Err(CycleError { key: 0 }));
+        assert_eq!(memo.get(3), Ok(5));
+        assert_eq!(
+            CycleError { key: 0 }.to_string(),
+            "cyclic dependency on key 0"
+        );
+    }
+}
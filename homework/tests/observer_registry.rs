@@ -0,0 +1,93 @@
+#[cfg(not(feature = "check-loom"))]
+mod basic {
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering::Relaxed;
+    use std::thread::scope;
+
+    use cs431_homework::{Arc, Observer, ObserverRegistry};
+
+    /// Observer that counts the sum of the events it received.
+    fn counter(sum: &'static AtomicUsize) -> Arc<Observer<usize>> {
+        ObserverRegistry::observer(move |e: &usize| {
+            let _ = sum.fetch_add(*e, Relaxed);
+        })
+    }
+
+    #[test]
+    fn notify_live_only() {
+        static A: AtomicUsize = AtomicUsize::new(0);
+        static B: AtomicUsize = AtomicUsize::new(0);
+        static C: AtomicUsize = AtomicUsize::new(0);
+
+        let registry = ObserverRegistry::new();
+        let a = counter(&A);
+        let b = counter(&B);
+        let c = counter(&C);
+        registry.register(&a);
+        registry.register(&b);
+        registry.register(&c);
+        assert_eq!(registry.len(), 3);
+
+        registry.notify(&1);
+        assert_eq!(
+            (A.load(Relaxed), B.load(Relaxed), C.load(Relaxed)),
+            (1, 1, 1)
+        );
+
+        drop(b);
+        registry.notify(&10);
+        assert_eq!(
+            (A.load(Relaxed), B.load(Relaxed), C.load(Relaxed)),
+            (11, 1, 11)
+        );
+        // `b` is pruned.
+        assert_eq!(registry.len(), 2);
+
+        drop(a);
+        drop(c);
+        registry.notify(&100);
+        assert_eq!(
+            (A.load(Relaxed), B.load(Relaxed), C.load(Relaxed)),
+            (11, 1, 11)
+        );
+        assert!(registry.is_empty());
+    }
+
+    #[test]
+    fn register_does_not_keep_alive() {
+        static SUM: AtomicUsize = AtomicUsize::new(0);
+
+        let registry = ObserverRegistry::new();
+        let observer = counter(&SUM);
+        registry.register(&observer);
+        assert_eq!(Arc::count(&observer), 1);
+        assert_eq!(Arc::weak_count(&observer), 1);
+
+        drop(observer);
+        registry.notify(&1);
+        assert_eq!(SUM.load(Relaxed), 0);
+        assert!(registry.is_empty());
+    }
+
+    #[test]
+    fn notify_concurrent() {
+        const THREADS: usize = 8;
+        const STEPS: usize = 1024;
+        static SUM: AtomicUsize = AtomicUsize::new(0);
+
+        let registry = ObserverRegistry::new();
+        let observer = counter(&SUM);
+        registry.register(&observer);
+
+        scope(|s| {
+            for _ in 0..THREADS {
+                let _ = s.spawn(|| {
+                    for _ in 0..STEPS {
+                        registry.notify(&1);
+                    }
+                });
+            }
+        });
+        assert_eq!(SUM.load(Relaxed), THREADS * STEPS);
+    }
+}
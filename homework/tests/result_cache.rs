@@ -0,0 +1,81 @@
+#[cfg(not(feature = "check-loom"))]
+mod basic {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Barrier;
+    use std::thread::{scope, sleep};
+    use std::time::Duration;
+
+    use crossbeam_channel::bounded;
+    use cs431_homework::hello_server::ResultCache;
+
+    const NUM_THREADS: usize = 8;
+
+    #[test]
+    fn result_cache_caches_ok() {
+        let cache = ResultCache::<usize, usize, ()>::default();
+        let barrier = Barrier::new(NUM_THREADS);
+        let num_calls = AtomicUsize::new(0);
+
+        scope(|s| {
+            for _ in 0..NUM_THREADS {
+                let _ = s.spawn(|| {
+                    let _ = barrier.wait();
+                    let value = cache.get_or_insert_with(1, |key| {
+                        let _ = num_calls.fetch_add(1, Ordering::Relaxed);
+                        Ok(key + 1)
+                    });
+                    assert_eq!(value, Ok(2));
+                });
+            }
+        });
+        assert_eq!(num_calls.load(Ordering::Relaxed), 1);
+        assert_eq!(cache.get_or_insert_with(1, |_| Err(())), Ok(2));
+    }
+
+    #[test]
+    fn result_cache_retries_err() {
+        let cache = ResultCache::<usize, usize, &str>::default();
+        let num_calls = AtomicUsize::new(0);
+        let (started_sender, started_receiver) = bounded(0);
+        let (fail_sender, fail_receiver) = bounded::<()>(0);
+
+        scope(|s| {
+            let cache = &cache;
+            let failing = |_| {
+                let _ = num_calls.fetch_add(1, Ordering::Relaxed);
+                started_sender.send(()).unwrap();
+                fail_receiver.recv().unwrap();
+                Err("unavailable")
+            };
+            let computing = s.spawn(move || cache.get_or_insert_with(1, failing));
+            started_receiver.recv().unwrap();
+
+            // The invocations coalescing on the failing computation all get its error.
+            let waiters = (1..NUM_THREADS)
+                .map(|_| {
+                    s.spawn(|| {
+                        cache.get_or_insert_with(1, |_| {
+                            let _ = num_calls.fetch_add(1, Ordering::Relaxed);
+                            Ok(0)
+                        })
+                    })
+                })
+                .collect::<Vec<_>>();
+            sleep(Duration::from_millis(200));
+            fail_sender.send(()).unwrap();
+
+            assert_eq!(computing.join().unwrap(), Err("unavailable"));
+            for waiter in waiters {
+                assert_eq!(waiter.join().unwrap(), Err("unavailable"));
+            }
+        });
+        assert_eq!(num_calls.load(Ordering::Relaxed), 1);
+
+        // The error is not cached, so the next invocation retries.
+        assert_eq!(cache.get_or_insert_with(1, |key| Ok(key * 10)), Ok(10));
+        assert_eq!(cache.get_or_insert_with(1, |_| Err("unreachable")), Ok(10));
+        assert!(cache.remove(&1));
+        assert_eq!(cache.get_or_insert_with(1, |_| Err("again")), Err("again"));
+        assert!(!cache.remove(&1));
+    }
+}
@@ -0,0 +1,105 @@
+use cs431_homework::{Arc, SharedLatest};
+
+#[cfg(not(feature = "check-loom"))]
+mod basic {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread::scope;
+
+    use super::*;
+
+    #[test]
+    fn store_load() {
+        let latest = SharedLatest::new(Arc::new(0));
+        assert_eq!(*latest.load(), 0);
+        let old = latest.load();
+        latest.store(Arc::new(1));
+        assert_eq!(*old, 0);
+        assert_eq!(*latest.load(), 1);
+    }
+
+    #[test]
+    fn store_releases_old() {
+        struct Value<'c>(&'c AtomicUsize);
+
+        impl Drop for Value<'_> {
+            fn drop(&mut self) {
+                let _ = self.0.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let dropped = AtomicUsize::new(0);
+        let first = Arc::new(Value(&dropped));
+        let latest = SharedLatest::new(first.clone());
+        assert_eq!(Arc::count(&first), 2);
+        latest.store(Arc::new(Value(&dropped)));
+        assert_eq!(Arc::count(&first), 1);
+        drop(first);
+        assert_eq!(dropped.load(Ordering::Relaxed), 1);
+        drop(latest);
+        assert_eq!(dropped.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn concurrent_monotonic() {
+        const STEPS: usize = 10_000;
+
+        let latest = SharedLatest::new(Arc::new(0));
+        scope(|s| {
+            let _ = s.spawn(|| {
+                for i in 1..=STEPS {
+                    latest.store(Arc::new(i));
+                }
+            });
+            for _ in 0..4 {
+                let _ = s.spawn(|| {
+                    // A single writer publishes increasing values.
+                    let mut prev = 0;
+                    for _ in 0..STEPS {
+                        let value = *latest.load();
+                        assert!(value >= prev);
+                        prev = value;
+                    }
+                });
+            }
+        });
+        assert_eq!(*latest.load(), STEPS);
+    }
+}
+
+mod correctness {
+    use cs431_homework::test::loom::sync::Arc as LoomArc;
+    use cs431_homework::test::loom::{model, thread};
+
+    use super::*;
+
+    #[test]
+    /// The readers get one of the published values, and the replaced values are released.
+    fn store_load_concurrent() {
+        model(|| {
+            let values = [0, 1, 2].map(Arc::new);
+            let latest = LoomArc::new(SharedLatest::new(values[0].clone()));
+            let writers = [1, 2].map(|i| {
+                let latest = latest.clone();
+                let value = values[i].clone();
+                thread::spawn(move || latest.store(value))
+            });
+            let reader = {
+                let latest = latest.clone();
+                thread::spawn(move || assert!(*latest.load() <= 2))
+            };
+            for writer in writers {
+                writer.join().unwrap();
+            }
+            reader.join().unwrap();
+
+            let last = latest.load();
+            assert_ne!(*last, 0);
+            drop(last);
+            // Only the latest value is still held by the holder.
+            for value in &values {
+                let held = Arc::ptr_eq(value, &latest.load());
+                assert_eq!(Arc::count(value), if held { 2 } else { 1 });
+            }
+        })
+    }
+}
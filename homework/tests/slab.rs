@@ -0,0 +1,74 @@
+#[cfg(not(feature = "check-loom"))]
+mod basic {
+    use std::thread::scope;
+
+    use cs431_homework::Slab;
+
+    #[test]
+    #[ignore = "the GrowableArray homework (`get` and `Drop`) is not done yet"]
+    fn smoke() {
+        let slab = Slab::new();
+        assert!(slab.get(0).is_none());
+        let a = slab.insert("a".to_string());
+        let b = slab.insert("b".to_string());
+        assert_ne!(a, b);
+        assert_eq!(slab.get(a).as_deref().map(String::as_str), Some("a"));
+
+        // A removed value stays alive while a clone of its `Arc` does.
+        let held = slab.get(b).unwrap();
+        assert_eq!(slab.remove(b).as_deref().map(String::as_str), Some("b"));
+        assert!(slab.get(b).is_none());
+        assert!(slab.remove(b).is_none());
+        assert_eq!(*held, "b");
+
+        // The indices are not reused.
+        let c = slab.insert("c".to_string());
+        assert!(c != a && c != b);
+        assert!(slab.remove(1 << 40).is_none());
+    }
+
+    #[test]
+    #[ignore = "the GrowableArray homework (`get` and `Drop`) is not done yet"]
+    fn stress_concurrent() {
+        const THREADS: usize = 16;
+        const STEPS: usize = 10_000;
+
+        let slab = Slab::new();
+        let indices = scope(|s| {
+            let handles = (0..THREADS)
+                .map(|t| {
+                    let slab = &slab;
+                    s.spawn(move || {
+                        let mut indices = Vec::with_capacity(STEPS);
+                        for i in 0..STEPS {
+                            let value = t * STEPS + i;
+                            let index = slab.insert(value);
+                            // Concurrent inserts don't overwrite this value.
+                            assert_eq!(slab.get(index).as_deref(), Some(&value));
+                            // Remove every other value, racing with the reads of the other threads.
+                            if i % 2 == 1 {
+                                assert_eq!(slab.remove(index).as_deref(), Some(&value));
+                            }
+                            indices.push((index, value));
+                        }
+                        indices
+                    })
+                })
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .flat_map(|h| h.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        let mut seen = indices.iter().map(|(index, _)| *index).collect::<Vec<_>>();
+        seen.sort_unstable();
+        seen.dedup();
+        assert_eq!(seen.len(), THREADS * STEPS);
+        for (index, value) in indices {
+            let removed = value % STEPS % 2 == 1;
+            let expected = (!removed).then_some(value);
+            assert_eq!(slab.get(index).as_deref().copied(), expected);
+        }
+    }
+}
@@ -1,5 +1,9 @@
 #![feature(cfg_sanitize)]
 
+use std::collections::HashSet;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::Relaxed;
+
 use crossbeam_epoch as epoch;
 use cs431_homework::test::adt::map;
 use cs431_homework::{ConcurrentMap, SplitOrderedList};
@@ -60,3 +64,120 @@ fn log_concurrent() {
     const STEPS: usize = 4096 * if cfg!(sanitize = "thread") { 16 } else { 64 };
     map::log_concurrent::<_, _, SplitOrderedList<usize>>(THREADS, STEPS);
 }
+
+#[test]
+fn count_less_than() {
+    const KEYS: [usize; 8] = [0, 1, 2, 3, 10, 100, 1000, (1 << 63) - 1];
+
+    let list = SplitOrderedList::new();
+    let guard = epoch::pin();
+    assert_eq!(list.count_less_than(&100), 0);
+    for key in KEYS {
+        assert_eq!(list.insert(key, key, &guard), Ok(()));
+    }
+    assert_eq!(list.delete(&3, &guard), Ok(&3));
+
+    for (threshold, count) in [
+        (0, 0),
+        (1, 1),
+        (3, 3),
+        (4, 3),
+        (100, 4),
+        (101, 5),
+        ((1 << 63) - 1, 6),
+        (usize::MAX, 7),
+    ] {
+        assert_eq!(list.count_less_than(&threshold), count, "{threshold}");
+    }
+}
+
+#[test]
+fn iter() {
+    let list = SplitOrderedList::new();
+    let guard = epoch::pin();
+    assert_eq!(list.iter(&guard).count(), 0);
+
+    // Enough keys to initialize many buckets, and so many sentinel nodes.
+    let keys = (0..1000).map(|i| i * 7).collect::<HashSet<_>>();
+    for &key in &keys {
+        assert_eq!(list.insert(key, key, &guard), Ok(()));
+    }
+    for key in (0..1000).step_by(3).map(|i| i * 7) {
+        assert_eq!(list.delete(&key, &guard), Ok(&key));
+    }
+
+    let mut expected = keys
+        .into_iter()
+        .filter(|key| key % 21 != 0)
+        .collect::<Vec<_>>();
+    expected.sort_unstable();
+    let mut values = list.iter(&guard).copied().collect::<Vec<_>>();
+    values.sort_unstable();
+    assert_eq!(values, expected);
+}
+
+#[test]
+fn parallel_scan() {
+    let list = SplitOrderedList::new();
+    let guard = epoch::pin();
+    let visits = (0..1000).map(|_| AtomicUsize::new(0)).collect::<Vec<_>>();
+    list.parallel_scan(4, |_| panic!("the list is empty"), &guard);
+
+    for key in 0..1000 {
+        assert_eq!(list.insert(key, key, &guard), Ok(()));
+    }
+    // A power of two, a non-power of two, and more threads than the buckets.
+    for threads in [4, 3, 1, 10_000] {
+        list.parallel_scan(
+            threads,
+            |&value| {
+                let _ = visits[value].fetch_add(1, Relaxed);
+            },
+            &guard,
+        );
+        for (key, visit) in visits.iter().enumerate() {
+            assert_eq!(visit.swap(0, Relaxed), 1, "{key} with {threads} threads");
+        }
+    }
+}
+
+#[test]
+fn iter_during_resize() {
+    const OLD: usize = 256;
+    const NEW: usize = 64 * OLD;
+    let list = SplitOrderedList::new();
+    let guard = epoch::pin();
+    for key in 0..OLD {
+        assert_eq!(list.insert(key, key.to_string(), &guard), Ok(()));
+    }
+
+    let mut iter = list.iter(&guard);
+    let mut old = iter.by_ref().take(OLD / 2).cloned().collect::<Vec<_>>();
+    // The new keys are inserted while the iterator is in the middle of the list, growing the table
+    // many times, and deleted again so that their nodes are freed.
+    std::thread::scope(|s| {
+        let _ = s.spawn(|| {
+            let guard = epoch::pin();
+            for key in OLD..OLD + NEW {
+                assert_eq!(list.insert(key, key.to_string(), &guard), Ok(()));
+            }
+            for key in OLD..OLD + NEW {
+                assert!(list.delete(&key, &guard).is_ok());
+            }
+        });
+        for value in iter {
+            let key = value.parse::<usize>().unwrap();
+            assert!(key < OLD + NEW, "{value}");
+            if key < OLD {
+                old.push(value.clone());
+            }
+        }
+    });
+
+    let mut old = old
+        .into_iter()
+        .map(|value| value.parse::<usize>().unwrap())
+        .collect::<Vec<_>>();
+    old.sort_unstable();
+    assert_eq!(old, (0..OLD).collect::<Vec<_>>());
+}